@@ -1,11 +1,19 @@
 use crate::action_paths::get_action_cache_dir;
+use crate::actions::core;
 use crate::node::path::Path;
-use crate::{dir_tree, node, nonce, warning, Error};
+use crate::nonce::Nonce;
+use crate::system::concurrency;
+use crate::{dir_tree, node, warning, Error};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 const WAIT_ATIME_UPDATED_MS: u64 = 5;
+/// How many timestamp updates are queued before they are applied when
+/// reverting access times
+const REVERT_BATCH_SIZE: usize = 64;
+const ATIMES_SUPPORTED_KEY: &str = "ACCESS_TIMES_SUPPORTED";
 
-fn default_access_time_offset() -> chrono::Duration {
+pub fn default_access_time_offset() -> chrono::Duration {
     // This is somewhat arbitrary - we could set all access timestamps back to the
     // epoch. The offset time is guaranteed to be valid and is far enough in the
     // past to cover even vFAT access time granularity (days).
@@ -14,6 +22,58 @@ fn default_access_time_offset() -> chrono::Duration {
 
 pub struct RevertAccessTime {
     duration: chrono::Duration,
+    follow_symlinks: bool,
+    pending: Vec<PendingRevert>,
+}
+
+struct PendingRevert {
+    path: Path,
+    m_time: DateTime<Utc>,
+    follow_symlink: bool,
+}
+
+impl RevertAccessTime {
+    fn new(duration: chrono::Duration, options: &dir_tree::WalkOptions) -> RevertAccessTime {
+        RevertAccessTime {
+            duration,
+            follow_symlinks: options.follow_symlinks,
+            pending: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, path: &Path, metadata: &node::fs::Metadata) {
+        // The walk falls back to the metadata of the link itself if its target
+        // is missing, and the timestamps must be set on whatever the metadata
+        // describes
+        let follow_symlink = self.follow_symlinks && !metadata.file_type().is_symlink();
+        self.pending.push(PendingRevert {
+            path: path.clone(),
+            m_time: metadata.modified(),
+            follow_symlink,
+        });
+    }
+
+    /// Applies the queued timestamp changes, up to the concurrency limit at
+    /// once
+    async fn flush(&mut self) -> Result<(), Error> {
+        use futures::{StreamExt as _, TryStreamExt as _};
+
+        let pending = std::mem::take(&mut self.pending);
+        let duration = self.duration;
+        let updates = pending.iter().map(|revert| async move {
+            let a_time = revert.m_time - duration;
+            if revert.follow_symlink {
+                node::fs::utimes(&revert.path, &a_time, &revert.m_time).await
+            } else {
+                node::fs::lutimes(&revert.path, &a_time, &revert.m_time).await
+            }
+        });
+        futures::stream::iter(updates)
+            .buffer_unordered(concurrency::limit())
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(())
+    }
 }
 
 #[async_trait(?Send)]
@@ -27,21 +87,40 @@ impl dir_tree::Visitor for RevertAccessTime {
     }
 
     async fn visit_entry(&mut self, path: &Path, is_file: bool) -> Result<(), Error> {
+        let metadata = if self.follow_symlinks {
+            node::fs::metadata(path).await?
+        } else {
+            node::fs::symlink_metadata(path).await?
+        };
+        self.visit_entry_with_metadata(path, is_file, &metadata).await
+    }
+
+    async fn visit_entry_with_metadata(
+        &mut self,
+        path: &Path,
+        is_file: bool,
+        metadata: &node::fs::Metadata,
+    ) -> Result<(), Error> {
         if is_file {
-            set_atime_behind_mtime(path, &self.duration).await?;
+            self.push(path, metadata);
         } else {
             panic!("Expected to descend into all directories");
         }
+        if self.pending.len() >= REVERT_BATCH_SIZE {
+            self.flush().await?;
+        }
         Ok(())
     }
 }
 
-pub async fn revert_folder(path: &Path) -> Result<(), Error> {
-    let mut visitor = RevertAccessTime {
-        duration: default_access_time_offset(),
-    };
+/// Sets the access time of every file in a folder to the specified duration
+/// before its modification time
+pub async fn revert_folder_with_offset(path: &Path, offset: chrono::Duration) -> Result<(), Error> {
+    let options = dir_tree::WalkOptions::default();
+    let mut visitor = RevertAccessTime::new(offset, &options);
     let ignores = dir_tree::Ignores::default();
-    dir_tree::apply_visitor(path, &ignores, &mut visitor).await?;
+    dir_tree::apply_visitor_with_options(path, &ignores, &options, &mut visitor).await?;
+    visitor.flush().await?;
     Ok(())
 }
 
@@ -59,12 +138,51 @@ async fn set_atime_behind_mtime(path: &Path, duration: &chrono::Duration) -> Res
     Ok(())
 }
 
+/// Somewhere to remember values between phases of the action
+trait StateStore {
+    fn get(&self, name: &str) -> Option<String>;
+    fn save(&self, name: &str, value: &str);
+}
+
+/// Stores values in the action's state
+struct ActionState;
+
+impl StateStore for ActionState {
+    fn get(&self, name: &str) -> Option<String> {
+        core::get_state(name)
+    }
+
+    fn save(&self, name: &str, value: &str) {
+        core::save_state(name, value);
+    }
+}
+
+/// Determines whether the filesystem updates access times. The probe is only
+/// run if no earlier phase of the action recorded its result.
 pub async fn supports_atime() -> Result<bool, Error> {
+    supports_atime_with_state(&ActionState, probe_atime_support).await
+}
+
+async fn supports_atime_with_state<S, F, R>(state: &S, probe: F) -> Result<bool, Error>
+where
+    S: StateStore,
+    F: FnOnce() -> R,
+    R: std::future::Future<Output = Result<bool, Error>>,
+{
+    if let Some(supported) = state.get(ATIMES_SUPPORTED_KEY) {
+        return Ok(serde_json::from_str(&supported)?);
+    }
+    let supported = probe().await?;
+    state.save(ATIMES_SUPPORTED_KEY, &serde_json::to_string(&supported)?);
+    Ok(supported)
+}
+
+async fn probe_atime_support() -> Result<bool, Error> {
     use crate::system::sleep;
 
     let atime_check_dir = get_atime_check_dir().await?;
     let file_path = {
-        let nonce = nonce::build(8);
+        let nonce = Nonce::generate();
         atime_check_dir.join(&nonce.to_string())
     };
     let data = [0u8; 1];
@@ -86,3 +204,114 @@ pub async fn supports_atime() -> Result<bool, Error> {
     // This needs to be >= and not > since times are discrete
     Ok(metadata.accessed() >= metadata.modified())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{revert_folder_with_offset, supports_atime_with_state, RevertAccessTime, StateStore};
+    use crate::actions::io;
+    use crate::{dir_tree, node};
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[derive(Default)]
+    struct MemoryState {
+        values: RefCell<HashMap<String, String>>,
+    }
+
+    impl StateStore for MemoryState {
+        fn get(&self, name: &str) -> Option<String> {
+            self.values.borrow().get(name).cloned()
+        }
+
+        fn save(&self, name: &str, value: &str) {
+            self.values.borrow_mut().insert(name.into(), value.into());
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn many_files_end_behind_mtime() -> Result<(), JsValue> {
+        let dir = node::os::temp_dir().join(&format!("ferrous-actions-atime-batch-test - {}", chrono::Local::now()));
+        let nested = dir.join("nested");
+        node::fs::create_dir_all(&nested).await?;
+        let mut files = Vec::new();
+        for i in 0..150 {
+            let parent = if i % 2 == 0 { &dir } else { &nested };
+            let file = parent.join(&format!("file-{}", i));
+            node::fs::write_file(&file, b"content").await?;
+            files.push(file);
+        }
+        revert_folder_with_offset(&dir, chrono::Duration::hours(1))
+            .await
+            .expect("Failed to revert access times");
+        for file in &files {
+            let metadata = node::fs::symlink_metadata(file).await?;
+            assert!(metadata.accessed() < metadata.modified());
+        }
+        io::rm_rf(&dir).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn atime_probe_runs_once() {
+        let state = MemoryState::default();
+        let probes = Cell::new(0);
+        for _ in 0..2 {
+            let supported = supports_atime_with_state(&state, || async {
+                probes.set(probes.get() + 1);
+                Ok(true)
+            })
+            .await
+            .expect("Failed to determine access time support");
+            assert!(supported);
+        }
+        assert_eq!(probes.get(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    async fn offset_is_applied() -> Result<(), JsValue> {
+        let dir = node::os::temp_dir().join(&format!("ferrous-actions-atime-test - {}", chrono::Local::now()));
+        let file = dir.join("file");
+        node::fs::create_dir_all(&dir).await?;
+        node::fs::write_file(&file, b"content").await?;
+        let offset = chrono::Duration::hours(5);
+        revert_folder_with_offset(&dir, offset)
+            .await
+            .expect("Failed to revert access times");
+        let metadata = node::fs::symlink_metadata(&file).await?;
+        let error = (metadata.modified() - metadata.accessed() - offset).abs();
+        // Timestamps are passed to node.js as floating point seconds
+        assert!(error < chrono::Duration::milliseconds(1));
+        io::rm_rf(&dir).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn followed_link_reverts_target() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-atime-link-test - {}", chrono::Local::now()));
+        let walked = root.join("walked");
+        let target = root.join("target");
+        node::fs::create_dir_all(&walked).await?;
+        node::fs::write_file(&target, b"content").await?;
+        node::fs::symlink(&target, &walked.join("link")).await?;
+
+        // The walk reads the metadata of the target, so that is what must be
+        // updated rather than the link
+        let options = dir_tree::WalkOptions {
+            max_depth: None,
+            follow_symlinks: true,
+        };
+        let offset = chrono::Duration::hours(5);
+        let mut visitor = RevertAccessTime::new(offset, &options);
+        dir_tree::apply_visitor_with_options(&walked, &dir_tree::Ignores::default(), &options, &mut visitor)
+            .await
+            .expect("Failed to walk tree");
+        visitor.flush().await.expect("Failed to revert access times");
+        let metadata = node::fs::metadata(&target).await?;
+        let error = (metadata.modified() - metadata.accessed() - offset).abs();
+        assert!(error < chrono::Duration::milliseconds(1));
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{node, Error};
+use crate::{debug, node, Error};
 use node::path::Path;
 use std::borrow::Cow;
 
@@ -6,15 +6,70 @@ pub fn get_action_name() -> Cow<'static, str> {
     "ferrous-actions".into()
 }
 
+/// Finds the home directory, falling back to the one recorded for the user
+/// when `HOME` (or its equivalent) is unset or points at the root directory
+fn find_home_dir() -> Path {
+    let home = node::os::homedir();
+    // Root directories and empty paths have no parent
+    if home.parent().is_none() {
+        match node::os::user_info() {
+            Ok(info) if info.homedir.parent().is_some() => return info.homedir,
+            Ok(_) => {}
+            Err(e) => debug!("Unable to retrieve user information: {:?}", e),
+        }
+    }
+    home
+}
+
+/// Reads an XDG base directory variable. The specification requires these to
+/// be absolute, so relative values are ignored.
+fn get_xdg_dir(name: &str) -> Option<Path> {
+    node::process::get_var(name)
+        .filter(|value| !value.is_empty())
+        .map(|value| Path::from(value.as_str()))
+        .filter(Path::is_absolute)
+}
+
 #[allow(clippy::unnecessary_wraps)]
 pub fn get_action_share_dir() -> Result<Path, Error> {
-    Ok(node::os::homedir()
-        .join(".local")
-        .join("share")
-        .join(get_action_name().as_ref()))
+    let data_dir = get_xdg_dir("XDG_DATA_HOME").unwrap_or_else(|| find_home_dir().join_all([".local", "share"]));
+    Ok(data_dir.join(get_action_name().as_ref()))
 }
 
 #[allow(clippy::unnecessary_wraps)]
 pub fn get_action_cache_dir() -> Result<Path, Error> {
-    Ok(node::os::homedir().join(".cache").join(get_action_name().as_ref()))
+    let cache_dir = get_xdg_dir("XDG_CACHE_HOME").unwrap_or_else(|| find_home_dir().join(".cache"));
+    Ok(cache_dir.join(get_action_name().as_ref()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_action_cache_dir, get_action_name};
+    use crate::node;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn cache_dir_respects_xdg() {
+        let name = "XDG_CACHE_HOME";
+        let previous = node::process::get_var(name);
+        let xdg_cache = node::os::temp_dir().join("ferrous-actions-xdg-test");
+        node::process::set_var(name, &xdg_cache.to_string());
+        let with_xdg = get_action_cache_dir();
+        node::process::set_var(name, "relative");
+        let with_relative = get_action_cache_dir();
+        node::process::remove_var(name);
+        let without_xdg = get_action_cache_dir();
+        if let Some(previous) = previous {
+            node::process::set_var(name, &previous);
+        }
+
+        let action_name = get_action_name();
+        let default = node::os::homedir().join_all([".cache", action_name.as_ref()]);
+        assert_eq!(
+            with_xdg.expect("Failed to get cache dir"),
+            xdg_cache.join(action_name.as_ref())
+        );
+        assert_eq!(with_relative.expect("Failed to get cache dir"), default);
+        assert_eq!(without_xdg.expect("Failed to get cache dir"), default);
+    }
 }
@@ -1,7 +1,9 @@
 use crate::node;
 use crate::node::path::Path;
+use async_recursion::async_recursion;
 use js_sys::JsString;
 use std::convert::Into;
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 
 const WORKSPACE_ENV_VAR: &str = "GITHUB_WORKSPACE";
@@ -49,6 +51,12 @@ const WORKSPACE_OVERRIDDEN_TAG: &str = "#WORKSPACE_OVERRIDEN";
 /// - supply consistent relative paths (patterns rather) to the actions API
 /// - avoid issues related to archive paths being encoded relative to
 ///   `$GITHUB_WORKSPACE`.
+///
+/// When run outside GitHub Actions (e.g. when testing locally),
+/// `$GITHUB_WORKSPACE` may be unset. The toolkit then treats the current
+/// working directory as the workspace, so the original workspace is
+/// effectively the original working directory. In that case the variable is
+/// removed again on drop rather than being left pointing at the scoped path.
 #[derive(Debug)]
 struct ScopedWorkspace {
     original_cwd: Path,
@@ -56,8 +64,8 @@ struct ScopedWorkspace {
 }
 
 impl ScopedWorkspace {
-    /// Constructs a new `Entry` with the specified name which can be used to
-    /// either save or restore files.
+    /// Changes the working directory and workspace to `new_cwd`, recording
+    /// the originals so they can be restored on drop
     pub fn new(new_cwd: &Path) -> Result<ScopedWorkspace, JsValue> {
         let original_cwd = node::process::cwd();
         let original_workspace = node::process::get_env().get(WORKSPACE_ENV_VAR).cloned();
@@ -82,13 +90,42 @@ impl Drop for ScopedWorkspace {
     }
 }
 
+/// The outcome of saving a cache entry
+#[derive(Clone, Copy, Debug)]
+pub struct SaveResult {
+    /// The numeric cache ID
+    pub id: i64,
+
+    /// The total size of the files that were cached, if it could be determined
+    pub size_bytes: Option<u64>,
+}
+
+#[async_recursion(?Send)]
+async fn path_size(path: &Path) -> Result<u64, JsValue> {
+    let metadata = node::fs::symlink_metadata(path).await?;
+    if metadata.is_directory() {
+        let mut result = 0;
+        for entry in node::fs::read_dir(path).await? {
+            result += path_size(&entry.path()).await?;
+        }
+        Ok(result)
+    } else {
+        Ok(metadata.len())
+    }
+}
+
 /// Saves and retrieves cache entries
 pub struct Entry {
     key: JsString,
     paths: Vec<Path>,
     restore_keys: Vec<JsString>,
+    preferred_keys: Vec<JsString>,
     cross_os_archive: bool,
     relative_to: Option<Path>,
+    upload_concurrency: Option<u32>,
+    upload_chunk_size: Option<u32>,
+    download_concurrency: Option<u32>,
+    segment_timeout: Option<Duration>,
 }
 
 impl Entry {
@@ -97,11 +134,34 @@ impl Entry {
             key: key.into(),
             paths: Vec::new(),
             restore_keys: Vec::new(),
+            preferred_keys: Vec::new(),
             cross_os_archive: false,
             relative_to: None,
+            upload_concurrency: None,
+            upload_chunk_size: None,
+            download_concurrency: None,
+            segment_timeout: None,
         }
     }
 
+    /// Returns the key of the cache entry
+    pub fn get_key(&self) -> String {
+        self.key.clone().into()
+    }
+
+    /// Returns the key a restore is expected to match: the first restore key
+    /// if any were specified, and otherwise the entry key. Unlike the entry
+    /// key, this does not change between runs when the entry key carries
+    /// per-save details such as a timestamp.
+    pub fn get_primary_key(&self) -> String {
+        self.restore_keys.first().unwrap_or(&self.key).clone().into()
+    }
+
+    /// Returns the paths that will be cached or restored
+    pub fn get_paths(&self) -> &[Path] {
+        &self.paths
+    }
+
     /// Add the specified paths (not glob patterns) to be cached or restored
     pub fn paths<I: IntoIterator<Item = P>, P: Into<Path>>(&mut self, paths: I) -> &mut Entry {
         self.paths.extend(paths.into_iter().map(Into::into));
@@ -124,6 +184,34 @@ impl Entry {
         self
     }
 
+    /// Sets the number of parallel uploads used when saving. This is clamped to
+    /// between 1 and 32.
+    pub fn upload_concurrency(&mut self, concurrency: u32) -> &mut Entry {
+        self.upload_concurrency = Some(concurrency.clamp(1, MAX_TRANSFER_CONCURRENCY));
+        self
+    }
+
+    /// Sets the size in bytes of each chunk uploaded when saving. This is
+    /// clamped to between 1 MiB and 128 MiB.
+    pub fn upload_chunk_size(&mut self, bytes: u32) -> &mut Entry {
+        self.upload_chunk_size = Some(bytes.clamp(MIN_UPLOAD_CHUNK_SIZE, MAX_UPLOAD_CHUNK_SIZE));
+        self
+    }
+
+    /// Sets the number of parallel downloads used when restoring. This is
+    /// clamped to between 1 and 32.
+    pub fn download_concurrency(&mut self, concurrency: u32) -> &mut Entry {
+        self.download_concurrency = Some(concurrency.clamp(1, MAX_TRANSFER_CONCURRENCY));
+        self
+    }
+
+    /// Sets how long the download of a single segment may take before a
+    /// restore is aborted
+    pub fn segment_timeout(&mut self, timeout: Duration) -> &mut Entry {
+        self.segment_timeout = Some(timeout);
+        self
+    }
+
     /// Enables interaction between cache entries produced on Windows and other
     /// operating systems
     pub fn permit_sharing_with_windows(&mut self, allow: bool) -> &mut Entry {
@@ -149,12 +237,36 @@ impl Entry {
         self.restore_keys(std::iter::once(restore_key.into()))
     }
 
+    /// Specifies keys which are tried in order when restoring, before the entry
+    /// name and restore keys. Each must match a cache entry key exactly. This
+    /// makes it possible to prefer, for example, an entry created on the
+    /// current branch over the newest entry matching a restore key.
+    ///
+    /// This function is a Ferrous actions extension and not part of the GitHub
+    /// Actions Toolkit API.
+    pub fn prefer_keys<I, K>(&mut self, keys: I) -> &mut Entry
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<JsString>,
+    {
+        self.preferred_keys.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Saves the cache entry and returns the numeric cache ID together with the
+    /// total size of the cached files.
+    pub async fn save(&self) -> Result<SaveResult, JsValue> {
+        let size_bytes = self.total_size().await.ok();
+        let id = self.save_id().await?;
+        Ok(SaveResult { id, size_bytes })
+    }
+
     /// Saves the cache entry and returns a numeric cache ID.
-    pub async fn save(&self) -> Result<i64, JsValue> {
-        let patterns = self.build_patterns();
+    pub async fn save_id(&self) -> Result<i64, JsValue> {
+        let patterns = self.build_patterns()?;
         let result = {
             let _caching_scope = self.build_action_scope()?;
-            ffi::save_cache(patterns, &self.key, None, self.cross_os_archive).await?
+            ffi::save_cache(patterns, &self.key, self.build_upload_options(), self.cross_os_archive).await?
         };
         let result = result
             .dyn_ref::<js_sys::Number>()
@@ -168,15 +280,18 @@ impl Entry {
     }
 
     /// Saves the cache entry if either:
-    /// - The name and restore keys do not match anything currently in the cache
-    /// - A restore based on the restore keys match `old_restore_key`
+    /// - The name and first restore key do not match anything currently in the
+    ///   cache
+    /// - A restore based on the first restore key matches `old_restore_key`
     ///
     /// In other words, the cache entry is only saved if it is either completely
-    /// new, or an update to the cache entry that was previously restored.
+    /// new, or an update to the cache entry that was previously restored. Any
+    /// further restore keys are treated as fallbacks which identify different
+    /// entries, so are not considered.
     ///
     /// This functionality is a Ferrous Actions extension and not part of the
     /// GitHub Actions Toolkit API.
-    pub async fn save_if_update(&self, old_restore_key: Option<&str>) -> Result<Option<i64>, JsValue> {
+    pub async fn save_if_update(&self, old_restore_key: Option<&str>) -> Result<Option<SaveResult>, JsValue> {
         let new_restore_key = self.peek_restore().await?;
         if new_restore_key.is_none() || new_restore_key.as_deref() == old_restore_key {
             self.save().await.map(Some)
@@ -185,14 +300,61 @@ impl Entry {
         }
     }
 
-    fn build_patterns(&self) -> Vec<JsString> {
+    fn build_upload_options(&self) -> Option<js_sys::Object> {
+        if self.upload_concurrency.is_none() && self.upload_chunk_size.is_none() {
+            return None;
+        }
+        let options = js_sys::Map::new();
+        if let Some(concurrency) = self.upload_concurrency {
+            options.set(&"uploadConcurrency".into(), &concurrency.into());
+        }
+        if let Some(chunk_size) = self.upload_chunk_size {
+            options.set(&"uploadChunkSize".into(), &chunk_size.into());
+        }
+        Some(js_sys::Object::from_entries(&options).expect("Failed to convert options map to object"))
+    }
+
+    fn build_download_options(&self, peek: bool) -> js_sys::Object {
+        let options = js_sys::Map::new();
+        options.set(&"lookupOnly".into(), &peek.into());
+        if let Some(concurrency) = self.download_concurrency {
+            options.set(&"downloadConcurrency".into(), &concurrency.into());
+        }
+        if let Some(timeout) = self.segment_timeout {
+            #[allow(clippy::cast_precision_loss)]
+            let millis = timeout.as_millis() as f64;
+            options.set(&"segmentTimeoutInMs".into(), &millis.into());
+        }
+        js_sys::Object::from_entries(&options).expect("Failed to convert options map to object")
+    }
+
+    /// Sums the sizes of all files under the paths of this entry. Paths that do
+    /// not exist are skipped.
+    async fn total_size(&self) -> Result<u64, JsValue> {
+        let cwd = node::process::cwd();
+        let mut result = 0;
+        for path in &self.paths {
+            let path = cwd.join(path);
+            if path.exists().await {
+                result += path_size(&path).await?;
+            }
+        }
+        Ok(result)
+    }
+
+    fn build_patterns(&self) -> Result<Vec<JsString>, JsValue> {
         let cwd = node::process::cwd();
         let mut result = Vec::with_capacity(self.paths.len());
         for path in &self.paths {
-            // Rewrite path to be relative if we have a root
+            // Rewrite path to be relative if we have a root. Patterns cannot
+            // contain `..` so paths must not escape the root.
             let path = if let Some(relative_to) = &self.relative_to {
                 let absolute = cwd.join(path);
-                absolute.relative_to(relative_to)
+                let Some(relative) = absolute.strip_prefix(&cwd.join(relative_to)) else {
+                    let error = format!("Cache path {} is not located under root {}", absolute, relative_to);
+                    return Err(JsError::new(&error).into());
+                };
+                relative
             } else {
                 path.clone()
             };
@@ -205,25 +367,33 @@ impl Entry {
             // incorporated into the path hash.
             result.push(WORKSPACE_OVERRIDDEN_TAG.into());
         }
-        result
+        Ok(result)
     }
 
     fn path_to_glob(path: &Path) -> String {
         let path = path.to_string();
         // This should be valid even for absolute paths on Windows
         let path = path.replace(node::path::separator().as_ref(), "/");
+        let is_windows = node::os::current_platform() == node::os::Platform::Windows;
+        Self::escape_glob(&path, is_windows)
+    }
+
+    fn escape_glob(path: &str, is_windows: bool) -> String {
         // We do not escape ']' as it would close the character set
         let mut result = String::with_capacity(path.len());
-        let is_windows = node::os::platform() == "windows";
         for (idx, c) in path.chars().enumerate() {
             match c {
                 '*' | '?' | '#' | '~' | '[' => result.extend(['[', c, ']']),
                 '!' if idx == 0 => {
                     // A leading ! inverts a pattern, but it cannot be escaped
                     // with a character class because it means a compliment there.
-                    // We could backslash escape, but only on non-Windows platforms.
-                    // For now we assume that files starting with ! are rare.
-                    result.push('?');
+                    // Backslash escaping is only possible on non-Windows platforms
+                    // so on Windows we fall back to matching any character.
+                    if is_windows {
+                        result.push('?');
+                    } else {
+                        result.extend(['\\', c]);
+                    }
                 }
                 '\\' if !is_windows => {
                     // The glob syntax is platform specific, because of course it is. Backslash is
@@ -248,33 +418,159 @@ impl Entry {
     }
 
     async fn peek_restore(&self) -> Result<Option<String>, JsValue> {
-        self.peek_or_restore(true).await
+        let restore_keys = self.restore_keys.iter().take(1).cloned().collect();
+        self.lookup(true, restore_keys).await
     }
 
     pub async fn peek_or_restore(&self, peek: bool) -> Result<Option<String>, JsValue> {
-        use js_sys::Object;
+        let preferred = find_preferred(&self.preferred_keys, |key| async move {
+            self.lookup_key(true, key, Vec::new()).await
+        })
+        .await?;
+        if let Some(key) = preferred {
+            if peek {
+                return Ok(Some(key));
+            }
+            let key: JsString = key.into();
+            return self.lookup_key(false, &key, Vec::new()).await;
+        }
+        self.lookup(peek, self.restore_keys.clone()).await
+    }
 
-        let patterns = self.build_patterns();
-        let options = {
-            let options = js_sys::Map::new();
-            options.set(&"lookupOnly".into(), &peek.into());
-            Object::from_entries(&options).expect("Failed to convert options map to object")
-        };
+    async fn lookup(&self, peek: bool, restore_keys: Vec<JsString>) -> Result<Option<String>, JsValue> {
+        self.lookup_key(peek, &self.key, restore_keys).await
+    }
+
+    async fn lookup_key(
+        &self,
+        peek: bool,
+        key: &JsString,
+        restore_keys: Vec<JsString>,
+    ) -> Result<Option<String>, JsValue> {
+        let patterns = self.build_patterns()?;
+        let options = self.build_download_options(peek);
         let result = {
             let _caching_scope = self.build_action_scope()?;
-            ffi::restore_cache(
-                patterns,
-                &self.key,
-                self.restore_keys.clone(),
-                Some(options),
-                self.cross_os_archive,
-            )
-            .await?
+            ffi::restore_cache(patterns, key, restore_keys, Some(options), self.cross_os_archive).await?
         };
         Ok(result.dyn_ref::<JsString>().map(Into::into))
     }
 }
 
+/// Returns the result of the first lookup of the supplied keys which finds a
+/// cache entry, trying the keys in order
+async fn find_preferred<'a, F, R>(keys: &'a [JsString], mut lookup: F) -> Result<Option<String>, JsValue>
+where
+    F: FnMut(&'a JsString) -> R,
+    R: std::future::Future<Output = Result<Option<String>, JsValue>>,
+{
+    for key in keys {
+        if let Some(found) = lookup(key).await? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+const MAX_TRANSFER_CONCURRENCY: u32 = 32;
+const MIN_UPLOAD_CHUNK_SIZE: u32 = 1024 * 1024;
+const MAX_UPLOAD_CHUNK_SIZE: u32 = 128 * 1024 * 1024;
+
+const CACHES_PAGE_SIZE: usize = 100;
+const USER_AGENT: &str = "ferrous-actions";
+
+/// A cache entry stored for the current repository, as reported by the GitHub
+/// REST API
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct RemoteEntry {
+    pub id: i64,
+    pub key: String,
+    pub size_in_bytes: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RemoteEntryList {
+    actions_caches: Vec<RemoteEntry>,
+}
+
+fn caches_url() -> Result<String, JsValue> {
+    let env = node::process::get_env();
+    let api_url = env
+        .get("GITHUB_API_URL")
+        .map_or("https://api.github.com", String::as_str);
+    let repository = env
+        .get("GITHUB_REPOSITORY")
+        .ok_or_else(|| JsError::new("GITHUB_REPOSITORY is not set"))?;
+    Ok(format!("{}/repos/{}/actions/caches", api_url, repository))
+}
+
+fn build_headers(token: &str) -> js_sys::Object {
+    let headers = js_sys::Map::new();
+    headers.set(&"Authorization".into(), &format!("Bearer {}", token).into());
+    headers.set(&"Accept".into(), &"application/vnd.github+json".into());
+    js_sys::Object::from_entries(&headers).expect("Failed to convert headers map to object")
+}
+
+fn filter_by_key_prefix(entries: Vec<RemoteEntry>, key_prefix: &str) -> Vec<RemoteEntry> {
+    entries
+        .into_iter()
+        .filter(|entry| entry.key.starts_with(key_prefix))
+        .collect()
+}
+
+/// Lists the cache entries of the current repository whose keys start with the
+/// specified prefix. The token must be permitted to read Actions data.
+///
+/// This function is a Ferrous actions extension and not part of the GitHub
+/// Actions Toolkit API.
+pub async fn list_remote(token: &str, key_prefix: &str) -> Result<Vec<RemoteEntry>, JsValue> {
+    let client = ffi::HttpClient::new(&USER_AGENT.into());
+    let headers = build_headers(token);
+    let base_url = caches_url()?;
+    let mut result = Vec::new();
+    for page in 1.. {
+        let url = format!(
+            "{}?key={}&per_page={}&page={}",
+            base_url,
+            js_sys::encode_uri_component(key_prefix),
+            CACHES_PAGE_SIZE,
+            page
+        );
+        let response = client.get_json(&url.into(), &headers).await?;
+        let body = js_sys::Reflect::get(&response, &"result".into())?;
+        let body: String = js_sys::JSON::stringify(&body)?.into();
+        let list: RemoteEntryList = serde_json::from_str(&body).map_err(|e| JsError::new(&e.to_string()))?;
+        let num_entries = list.actions_caches.len();
+        result.extend(list.actions_caches);
+        if num_entries < CACHES_PAGE_SIZE {
+            break;
+        }
+    }
+    // Filter locally as well in case the API matching is looser than a prefix
+    Ok(filter_by_key_prefix(result, key_prefix))
+}
+
+/// Deletes the cache entry with the specified ID from the current repository.
+/// The token must be permitted to write Actions data.
+///
+/// This function is a Ferrous actions extension and not part of the GitHub
+/// Actions Toolkit API.
+pub async fn delete_remote(token: &str, id: i64) -> Result<(), JsValue> {
+    let client = ffi::HttpClient::new(&USER_AGENT.into());
+    let headers = build_headers(token);
+    let url = format!("{}/{}", caches_url()?, id);
+    let response = client.del(&url.into(), &headers).await?;
+    let status = js_sys::Reflect::get(&response, &"message".into())
+        .and_then(|message| js_sys::Reflect::get(&message, &"statusCode".into()))?
+        .as_f64()
+        .ok_or_else(|| JsError::new("Delete response lacked a status code"))?;
+    if (200.0..300.0).contains(&status) {
+        Ok(())
+    } else {
+        Err(JsError::new(&format!("Deleting cache entry {} failed with status {}", id, status)).into())
+    }
+}
+
 /// Low-level bindings to the GitHub Actions Tookit "cache" API
 pub mod ffi {
     use js_sys::{JsString, Object};
@@ -299,4 +595,183 @@ pub mod ffi {
             cross_os_archive: bool,
         ) -> Result<JsValue, JsValue>;
     }
+
+    #[wasm_bindgen(module = "@actions/http-client")]
+    extern "C" {
+        #[derive(Debug)]
+        pub type HttpClient;
+
+        #[wasm_bindgen(constructor)]
+        pub fn new(user_agent: &JsString) -> HttpClient;
+
+        #[wasm_bindgen(method, js_name = "getJson", catch)]
+        pub async fn get_json(this: &HttpClient, url: &JsString, headers: &Object) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(method, catch)]
+        pub async fn del(this: &HttpClient, url: &JsString, headers: &Object) -> Result<JsValue, JsValue>;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{filter_by_key_prefix, find_preferred, Entry, RemoteEntry, ScopedWorkspace, WORKSPACE_ENV_VAR};
+    use crate::actions::io;
+    use crate::node;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn scoped_workspace_without_workspace_var() -> Result<(), JsValue> {
+        let saved_workspace = node::process::get_env().remove(WORKSPACE_ENV_VAR);
+        node::process::remove_var(WORKSPACE_ENV_VAR);
+        let original_cwd = node::process::cwd();
+        let new_cwd = node::os::temp_dir();
+        {
+            let _workspace = ScopedWorkspace::new(&new_cwd)?;
+            assert_eq!(node::process::cwd(), new_cwd);
+            assert_eq!(
+                node::process::get_env().get(WORKSPACE_ENV_VAR),
+                Some(&new_cwd.to_string())
+            );
+        }
+        let restored_workspace = node::process::get_env().remove(WORKSPACE_ENV_VAR);
+        if let Some(saved_workspace) = saved_workspace {
+            node::process::set_var(WORKSPACE_ENV_VAR, &saved_workspace);
+        }
+        assert_eq!(node::process::cwd(), original_cwd);
+        assert_eq!(restored_workspace, None);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn preferred_keys_tried_in_order() -> Result<(), JsValue> {
+        use js_sys::JsString;
+        use std::cell::RefCell;
+
+        let keys: Vec<JsString> = ["feature-branch", "main", "release"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let existing = ["release", "main"];
+        let attempts = RefCell::new(Vec::new());
+        let found = find_preferred(&keys, |key| {
+            let key = String::from(key);
+            attempts.borrow_mut().push(key.clone());
+            async move { Ok(existing.contains(&key.as_str()).then_some(key)) }
+        })
+        .await?;
+        assert_eq!(found.as_deref(), Some("main"));
+        assert_eq!(attempts.into_inner(), ["feature-branch", "main"]);
+
+        let found = find_preferred(&keys[..1], |_| async { Ok(None) }).await?;
+        assert_eq!(found, None);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn total_size_sums_files() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-cache-size-test - {}", chrono::Local::now()));
+        let nested = root.join("nested");
+        node::fs::create_dir_all(&nested).await?;
+        node::fs::write_file(&root.join("first"), &[0u8; 100]).await?;
+        node::fs::write_file(&nested.join("second"), &[0u8; 23]).await?;
+        node::fs::write_file(&root.join("not-cached"), &[0u8; 1000]).await?;
+
+        let mut entry = Entry::new("size-test");
+        entry.path(root.join("first")).path(&nested).path(root.join("missing"));
+        let size = entry.total_size().await;
+        io::rm_rf(&root).await?;
+        assert_eq!(size?, 123);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn upload_options() {
+        let get = |options: &js_sys::Object, name: &str| {
+            js_sys::Reflect::get(options, &name.into())
+                .expect("Failed to read option")
+                .as_f64()
+        };
+
+        let mut entry = Entry::new("upload-options-test");
+        assert!(entry.build_upload_options().is_none());
+        entry.upload_concurrency(8).upload_chunk_size(64 * 1024 * 1024);
+        let options = entry.build_upload_options().expect("Missing upload options");
+        assert_eq!(get(&options, "uploadConcurrency"), Some(8.0));
+        assert_eq!(get(&options, "uploadChunkSize"), Some(f64::from(64 * 1024 * 1024)));
+
+        entry.upload_concurrency(0).upload_chunk_size(1);
+        let options = entry.build_upload_options().expect("Missing upload options");
+        assert_eq!(get(&options, "uploadConcurrency"), Some(1.0));
+        assert_eq!(get(&options, "uploadChunkSize"), Some(f64::from(1024 * 1024)));
+    }
+
+    #[wasm_bindgen_test]
+    fn download_options() {
+        use std::time::Duration;
+
+        let get = |options: &js_sys::Object, name: &str| {
+            js_sys::Reflect::get(options, &name.into()).expect("Failed to read option")
+        };
+
+        let mut entry = Entry::new("download-options-test");
+        let options = entry.build_download_options(true);
+        assert_eq!(get(&options, "lookupOnly").as_bool(), Some(true));
+        assert!(get(&options, "downloadConcurrency").is_undefined());
+        assert!(get(&options, "segmentTimeoutInMs").is_undefined());
+
+        entry.download_concurrency(4).segment_timeout(Duration::from_secs(90));
+        let options = entry.build_download_options(false);
+        assert_eq!(get(&options, "lookupOnly").as_bool(), Some(false));
+        assert_eq!(get(&options, "downloadConcurrency").as_f64(), Some(4.0));
+        assert_eq!(get(&options, "segmentTimeoutInMs").as_f64(), Some(90_000.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn leading_exclamation_escaped() {
+        assert_eq!(Entry::escape_glob("!important/file", false), "\\!important/file");
+        assert_eq!(Entry::escape_glob("!important/file", true), "?important/file");
+        assert_eq!(Entry::escape_glob("dir/!file", false), "dir/!file");
+        assert_eq!(Entry::escape_glob("dir/!file", true), "dir/!file");
+    }
+
+    #[wasm_bindgen_test]
+    fn paths_must_be_under_root() {
+        let root = node::os::temp_dir().join("root");
+        let mut entry = Entry::new("root-test");
+        entry.root(&root).path(root.join("inner").join("..").join("file"));
+        let patterns = entry.build_patterns().expect("Path under root was rejected");
+        assert_eq!(String::from(&patterns[0]), "file");
+
+        let mut entry = Entry::new("root-test");
+        entry.root(&root).path(root.join("..").join("outside"));
+        assert!(entry.build_patterns().is_err());
+
+        let mut entry = Entry::new("root-test");
+        entry.root(&root).path(root.join("..dotted"));
+        assert!(entry.build_patterns().is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    fn key_prefix_filtering() {
+        let entries: Vec<_> = [
+            "Cargo index (abc)",
+            "Cargo index (def)",
+            "Cargo crates (abc)",
+            "cargo index",
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, key)| RemoteEntry {
+            id: i64::try_from(id).expect("ID out of range"),
+            key: key.into(),
+            size_in_bytes: 0,
+        })
+        .collect();
+        let matched: Vec<_> = filter_by_key_prefix(entries, "Cargo index")
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(matched, [0, 1]);
+    }
 }
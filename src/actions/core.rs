@@ -1,5 +1,6 @@
 use crate::node::path::Path;
 use js_sys::{JsString, Number, Object};
+use std::sync::atomic::{AtomicBool, Ordering};
 use wasm_bindgen::JsValue;
 
 /// Formats and outputs a GitHub actions log line at debug level
@@ -134,7 +135,7 @@ impl Input {
 }
 
 /// Builder for outputting annotations
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Annotation {
     message: String,
     title: Option<String>,
@@ -161,7 +162,7 @@ impl<M: Into<String>> From<M> for Annotation {
 }
 
 /// Annotation levels
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum AnnotationLevel {
     /// Notice
     Notice,
@@ -288,6 +289,41 @@ pub fn save_state<N: Into<JsString>, V: Into<JsString>>(name: N, value: V) {
     ffi::save_state(&name, &value);
 }
 
+// Workflow commands are not echoed unless enabled
+static COMMAND_ECHO: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables the echoing of workflow commands to the log
+pub fn set_command_echo(enabled: bool) {
+    COMMAND_ECHO.store(enabled, Ordering::Relaxed);
+    ffi::set_command_echo(enabled);
+}
+
+/// Restores the previous command echo setting when dropped
+#[must_use = "Command echo is restored as soon as the guard is dropped"]
+pub struct CommandEchoGuard {
+    previous: bool,
+    set: fn(bool),
+}
+
+impl CommandEchoGuard {
+    fn new(enabled: bool, set: fn(bool)) -> CommandEchoGuard {
+        let previous = COMMAND_ECHO.load(Ordering::Relaxed);
+        set(enabled);
+        CommandEchoGuard { previous, set }
+    }
+}
+
+impl Drop for CommandEchoGuard {
+    fn drop(&mut self) {
+        (self.set)(self.previous);
+    }
+}
+
+/// Disables echoing of workflow commands until the returned guard is dropped
+pub fn suppress_command_echo() -> CommandEchoGuard {
+    CommandEchoGuard::new(false, set_command_echo)
+}
+
 /// Retrieves previously saved action state
 pub fn get_state<N: Into<JsString>>(name: N) -> Option<String> {
     let name = name.into();
@@ -310,6 +346,48 @@ pub fn end_group() {
     ffi::end_group();
 }
 
+/// Ends a foldable group when dropped
+#[must_use = "The group ends as soon as the guard is dropped"]
+pub struct GroupGuard {
+    end: fn(),
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        (self.end)();
+    }
+}
+
+/// Starts a foldable group which ends when the returned guard is dropped,
+/// including on early return
+pub fn group<N: Into<JsString>>(name: N) -> GroupGuard {
+    start_group(name);
+    GroupGuard { end: end_group }
+}
+
+/// Runs a future within a foldable group
+pub async fn group_scoped<N: Into<JsString>, F: std::future::Future>(name: N, future: F) -> F::Output {
+    let _group = group(name);
+    future.await
+}
+
+/// Requests an OIDC ID token for the workflow run, optionally for a specific
+/// audience. The workflow must be granted the `id-token: write` permission,
+/// otherwise this returns an error.
+pub async fn get_id_token(audience: Option<&str>) -> Result<String, JsValue> {
+    let audience = audience.map(JsString::from);
+    let token = ffi::get_id_token(audience).await?;
+    token
+        .as_string()
+        .ok_or_else(|| wasm_bindgen::JsError::new("ID token was not a string").into())
+}
+
+/// Appends Markdown to the summary of the current job
+pub async fn append_summary<S: Into<JsString>>(markdown: S) -> Result<(), JsValue> {
+    ffi::SUMMARY.add_raw(&markdown.into(), Some(true)).write().await?;
+    Ok(())
+}
+
 /// Low-level bindings to the GitHub Actions Toolkit "core" API
 #[allow(clippy::drop_non_drop)]
 pub mod ffi {
@@ -371,10 +449,83 @@ pub mod ffi {
         #[wasm_bindgen(js_name = "getState")]
         pub fn get_state(name: &JsString) -> JsString;
 
+        #[wasm_bindgen(js_name = "setCommandEcho")]
+        pub fn set_command_echo(enabled: bool);
+
         #[wasm_bindgen(js_name = "startGroup")]
         pub fn start_group(name: &JsString);
 
         #[wasm_bindgen(js_name = "endGroup")]
         pub fn end_group();
+
+        #[wasm_bindgen(js_name = "getIDToken", catch)]
+        pub async fn get_id_token(audience: Option<JsString>) -> Result<JsValue, JsValue>;
+
+        /// Buffers Markdown for writing to the job summary
+        #[wasm_bindgen(js_name = "Summary")]
+        pub type Summary;
+
+        #[wasm_bindgen(js_name = "summary")]
+        pub static SUMMARY: Summary;
+
+        #[wasm_bindgen(method, js_name = "addRaw")]
+        pub fn add_raw(this: &Summary, text: &JsString, add_eol: Option<bool>) -> Summary;
+
+        #[wasm_bindgen(method, catch)]
+        pub async fn write(this: &Summary) -> Result<JsValue, JsValue>;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_id_token, CommandEchoGuard, GroupGuard, COMMAND_ECHO};
+    use crate::node;
+    use parking_lot::Mutex;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    static ENDED: Mutex<Vec<&'static str>> = parking_lot::const_mutex(Vec::new());
+
+    #[wasm_bindgen_test]
+    fn groups_end_in_reverse_order() {
+        fn end_outer() {
+            ENDED.lock().push("outer");
+        }
+
+        fn end_inner() {
+            ENDED.lock().push("inner");
+        }
+
+        {
+            let _outer = GroupGuard { end: end_outer };
+            let _inner = GroupGuard { end: end_inner };
+            assert!(ENDED.lock().is_empty());
+        }
+        assert_eq!(*ENDED.lock(), ["inner", "outer"]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn id_token_unavailable_without_permission() {
+        // Only set by the runner when the workflow has the id-token permission
+        if node::process::get_env().contains_key("ACTIONS_ID_TOKEN_REQUEST_URL") {
+            return;
+        }
+        assert!(get_id_token(Some("sts.amazonaws.com")).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn command_echo_restored() {
+        static CALLS: Mutex<Vec<bool>> = parking_lot::const_mutex(Vec::new());
+
+        fn set(enabled: bool) {
+            CALLS.lock().push(enabled);
+        }
+
+        COMMAND_ECHO.store(true, std::sync::atomic::Ordering::Relaxed);
+        {
+            let _echo = CommandEchoGuard::new(false, set);
+            assert_eq!(*CALLS.lock(), [false]);
+        }
+        assert_eq!(*CALLS.lock(), [false, true]);
+        COMMAND_ECHO.store(false, std::sync::atomic::Ordering::Relaxed);
     }
 }
@@ -2,12 +2,58 @@ use super::noop_stream;
 use super::push_line_splitter::PushLineSplitter;
 use crate::node;
 use crate::node::path::Path;
+use crate::system::rng;
 use js_sys::{JsString, Object};
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsValue;
 
+/// Exit code used by the timeout wrapper when it kills a command, matching the
+/// `timeout` utility
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Name of the JavaScript error returned when a command times out
+const TIMEOUT_ERROR_NAME: &str = "TimeoutError";
+
+/// Script run by node.js to enforce a timeout. It is written to a file rather
+/// than passed with `-e` so that the command line echoed for the command stays
+/// short and needs no quoting. The arguments are the timeout in
+/// milliseconds, the command, then the command's arguments. On timeout the
+/// entire process tree is killed, since descendants such as `rustc` would
+/// otherwise outlive the command and keep its output streams open. Outside
+/// Windows this is done by placing the command in its own process group.
+const TIMEOUT_WRAPPER: &str = r"
+const [timeout, command, ...args] = process.argv.slice(2);
+const { spawn, spawnSync } = require('child_process');
+const windows = process.platform === 'win32';
+const child = spawn(command, args, { stdio: 'inherit', detached: !windows });
+const killTree = (signal) => {
+  if (windows) {
+    spawnSync('taskkill', ['/pid', String(child.pid), '/T', '/F'], { stdio: 'ignore' });
+  } else {
+    try { process.kill(-child.pid, signal); } catch (error) {}
+  }
+};
+let timedOut = false;
+const timer = setTimeout(() => { timedOut = true; killTree('SIGKILL'); }, Number(timeout));
+for (const signal of ['SIGINT', 'SIGTERM']) {
+  process.on(signal, () => killTree(signal));
+}
+child.on('error', (error) => { clearTimeout(timer); console.error(error.message); process.exit(127); });
+child.on('exit', (code) => { clearTimeout(timer); process.exit(timedOut ? 124 : (code ?? 1)); });
+";
+
+/// Returns true if `error` was returned because a command exceeded its timeout
+pub fn is_timeout(error: &JsValue) -> bool {
+    use wasm_bindgen::JsCast as _;
+    error
+        .dyn_ref::<js_sys::Error>()
+        .is_some_and(|e| e.name() == TIMEOUT_ERROR_NAME)
+}
+
 #[derive(Debug, Clone, Copy)]
 enum StdioEnum {
     Inherit,
@@ -96,6 +142,10 @@ pub struct Command {
     stdout: Stdio,
     stderr: Stdio,
     cwd: Path,
+    env: HashMap<String, String>,
+    silent: bool,
+    input: Vec<u8>,
+    timeout: Option<Duration>,
 }
 
 impl Command {
@@ -115,13 +165,123 @@ impl Command {
         self
     }
 
+    /// Sets an environment variable for the command. All other environment
+    /// variables are inherited from the parent process.
+    pub fn env<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut Command {
+        self.envs(std::iter::once((key, value)))
+    }
+
+    /// Sets multiple environment variables for the command
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Command
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env
+            .extend(vars.into_iter().map(|(key, value)| (key.into(), value.into())));
+        self
+    }
+
+    /// Suppresses the echoing of the command line and the forwarding of its
+    /// output to the log. Output callbacks are still invoked.
+    pub fn silent(&mut self, silent: bool) -> &mut Command {
+        self.silent = silent;
+        self
+    }
+
+    /// Supplies data to be written to the standard input of the command.
+    /// Supplying empty input is equivalent to supplying none.
+    pub fn input(&mut self, data: Vec<u8>) -> &mut Command {
+        self.input = data;
+        self
+    }
+
+    /// Kills the command if it is still running after the specified duration,
+    /// in which case `exec` returns an error for which `is_timeout` is true. A
+    /// zero duration means no timeout.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Command {
+        self.timeout = (!timeout.is_zero()).then_some(timeout);
+        self
+    }
+
+    fn build_options(&self) -> js_sys::Map {
+        let options = js_sys::Map::new();
+        options.set(&"cwd".into(), &self.cwd.to_js_string());
+        options.set(&"silent".into(), &self.silent.into());
+        if self.timeout.is_some() {
+            // Exit codes are checked by us so that a timeout can be identified
+            options.set(&"ignoreReturnCode".into(), &true.into());
+        }
+        if !self.input.is_empty() {
+            let input = js_sys::Uint8Array::from(self.input.as_slice());
+            options.set(&"input".into(), &input);
+        }
+        if !self.env.is_empty() {
+            // If an environment is supplied, it entirely replaces that of the parent so we
+            // need to merge our variables into the existing ones
+            let env = js_sys::Map::new();
+            for (key, value) in node::process::get_env().iter().chain(self.env.iter()) {
+                env.set(&JsValue::from_str(key), &JsValue::from_str(value));
+            }
+            let env = Object::from_entries(&env).expect("Failed to convert environment map to object");
+            options.set(&"env".into(), &env);
+        }
+        options
+    }
+
+    fn command_line(&self, timeout_wrapper: Option<&Path>) -> (Path, Vec<JsString>) {
+        let args = self.args.iter().map(JsString::to_string);
+        match (self.timeout, timeout_wrapper) {
+            (Some(timeout), Some(wrapper)) => {
+                let prefix = [
+                    wrapper.to_js_string(),
+                    timeout.as_millis().to_string().into(),
+                    self.command.to_js_string(),
+                ];
+                (node::process::exec_path(), prefix.into_iter().chain(args).collect())
+            }
+            _ => (self.command.clone(), args.collect()),
+        }
+    }
+
+    /// Writes the timeout wrapper to a uniquely named file, so that concurrent
+    /// commands never see a partially written script
+    async fn write_timeout_wrapper() -> Result<Path, JsValue> {
+        let mut bytes = [0u8; 8];
+        rng::fill_bytes(&mut bytes);
+        let name = format!("ferrous-actions-timeout-{:016x}.js", u64::from_le_bytes(bytes));
+        let path = node::os::temp_dir().join(&name);
+        node::fs::write_file(&path, TIMEOUT_WRAPPER.as_bytes()).await?;
+        Ok(path)
+    }
+
+    fn check_timeout(&self, code: i32, elapsed: Duration) -> Result<i32, JsValue> {
+        let Some(timeout) = self.timeout else {
+            return Ok(code);
+        };
+        if code == TIMEOUT_EXIT_CODE && elapsed >= timeout {
+            let error = js_sys::Error::new(&format!("The process '{}' timed out after {:?}", self.command, timeout));
+            error.set_name(TIMEOUT_ERROR_NAME);
+            Err(error.into())
+        } else if code != 0 {
+            let error = format!("The process '{}' failed with exit code {}", self.command, code);
+            Err(wasm_bindgen::JsError::new(&error).into())
+        } else {
+            Ok(code)
+        }
+    }
+
     /// Executes the command and returns the status code
     pub async fn exec(&mut self) -> Result<i32, JsValue> {
-        let command = self.command.to_string();
-        let command = Self::escape_command(command.as_str());
+        let timeout_wrapper = match self.timeout {
+            Some(_) => Some(Self::write_timeout_wrapper().await?),
+            None => None,
+        };
+        let (command, args) = self.command_line(timeout_wrapper.as_ref());
+        let command = Self::escape_command(&command.to_string());
         let command: JsString = command.into();
-        let args: Vec<JsString> = self.args.iter().map(JsString::to_string).collect();
-        let options = js_sys::Map::new();
+        let options = self.build_options();
         let listeners = js_sys::Map::new();
 
         let outline_adapter = self.outline.clone().map(StreamToLines::new);
@@ -133,7 +293,6 @@ impl Command {
             listeners.set(&"stderr".into(), callback.as_ref());
         }
 
-        options.set(&"cwd".into(), &self.cwd.to_js_string());
         let sink = noop_stream::Sink::default();
         if let StdioEnum::Null = self.stdout.inner {
             options.set(&"outStream".into(), sink.as_ref());
@@ -145,6 +304,7 @@ impl Command {
         let listeners = Object::from_entries(&listeners).expect("Failed to convert listeners map to object");
         options.set(&"listeners".into(), &listeners);
         let options = Object::from_entries(&options).expect("Failed to convert options map to object");
+        let started = node::process::hrtime();
         let result = ffi::exec(&command, Some(args), &options).await.map(|r| {
             #[allow(clippy::cast_possible_truncation)]
             let code = r.as_f64().expect("exec didn't return a number") as i32;
@@ -154,7 +314,12 @@ impl Command {
         // Be explicit about line-buffer flushing
         drop(outline_adapter);
         drop(errline_adapter);
-        result
+        let elapsed = node::process::hrtime().saturating_sub(started);
+        if let Some(wrapper) = &timeout_wrapper {
+            // Failing to tidy up is harmless
+            drop(node::fs::remove_file(wrapper).await);
+        }
+        self.check_timeout(result?, elapsed)
     }
 
     /// Sets a callback to be called each time a new line is written to standard
@@ -169,9 +334,16 @@ impl Command {
     /// Sets a callback to be called each time a new line is written to standard
     /// error. Note that line splitting is done by an internal re-implementation
     /// of line splitting and not the GitHub Actions Toolkit one due to
-    /// issues with the latter.
+    /// issues with the latter. If a callback was already set, both are called,
+    /// in the order they were set.
     pub fn errline<F: Fn(&str) + 'static + Sync + Send>(&mut self, callback: F) -> &mut Command {
-        self.errline = Some(Arc::new(Box::new(callback)));
+        self.errline = Some(match self.errline.take() {
+            Some(existing) => Arc::new(Box::new(move |line: &str| {
+                existing(line);
+                callback(line);
+            })),
+            None => Arc::new(Box::new(callback)),
+        });
         self
     }
 
@@ -229,6 +401,10 @@ impl<'a> From<&'a Path> for Command {
             stdout: Stdio::inherit(),
             stderr: Stdio::inherit(),
             cwd: node::process::cwd(),
+            env: HashMap::new(),
+            silent: false,
+            input: Vec::new(),
+            timeout: None,
         }
     }
 }
@@ -248,3 +424,111 @@ pub mod ffi {
         ) -> Result<JsValue, JsValue>;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{is_timeout, Command};
+    use crate::actions::io;
+    use crate::node::path::Path;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn silent_sets_option() {
+        let path = Path::from("command");
+        let mut command = Command::from(&path);
+        assert_eq!(command.build_options().get(&"silent".into()).as_bool(), Some(false));
+        command.silent(true);
+        assert_eq!(command.build_options().get(&"silent".into()).as_bool(), Some(true));
+    }
+
+    #[wasm_bindgen_test]
+    async fn child_sees_env_var() -> Result<(), JsValue> {
+        let node = io::which("node", true).await?;
+        let output: Arc<Mutex<String>> = Arc::default();
+        let output_captured = output.clone();
+        Command::from(&node)
+            .args(["-e", "process.stdout.write(process.env.FERROUS_ACTIONS_TEST)"])
+            .env("FERROUS_ACTIONS_TEST", "injected")
+            .outline(move |line| *output_captured.lock() += line)
+            .exec()
+            .await?;
+        assert_eq!(output.lock().as_str(), "injected");
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn child_reads_input() -> Result<(), JsValue> {
+        let node = io::which("node", true).await?;
+        let lines: Arc<Mutex<Vec<String>>> = Arc::default();
+        let lines_captured = lines.clone();
+        Command::from(&node)
+            .args(["-e", "process.stdin.pipe(process.stdout)"])
+            .input(b"first\nsecond\n".to_vec())
+            .outline(move |line| lines_captured.lock().push(line.to_string()))
+            .exec()
+            .await?;
+        assert_eq!(&lines.lock()[..2], ["first", "second"]);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn long_running_child_times_out() -> Result<(), JsValue> {
+        let node = io::which("node", true).await?;
+        let error = Command::from(&node)
+            .args(["-e", "setTimeout(() => {}, 60000)"])
+            .timeout(Duration::from_millis(500))
+            .exec()
+            .await
+            .expect_err("Command should have timed out");
+        assert!(is_timeout(&error));
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn timeout_kills_descendants() -> Result<(), JsValue> {
+        use crate::node;
+
+        // The grandchild inherits the output streams, so the command would not
+        // complete until they are closed if the grandchild survived
+        let node = io::which("node", true).await?;
+        let script = "require('child_process').spawn(process.execPath, ['-e', 'setTimeout(() => {}, 60000)'], \
+                      { stdio: 'inherit' }); setTimeout(() => {}, 60000)";
+        let started = node::process::hrtime();
+        let error = Command::from(&node)
+            .args(["-e", script])
+            .timeout(Duration::from_millis(500))
+            .outline(|_| {})
+            .exec()
+            .await
+            .expect_err("Command should have timed out");
+        assert!(is_timeout(&error));
+        assert!(node::process::hrtime().saturating_sub(started) < Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn child_within_timeout() -> Result<(), JsValue> {
+        let node = io::which("node", true).await?;
+        let output: Arc<Mutex<String>> = Arc::default();
+        let output_captured = output.clone();
+        Command::from(&node)
+            .args(["-e", "process.stdout.write(process.argv[1])", "argument with spaces"])
+            .timeout(Duration::from_secs(30))
+            .outline(move |line| *output_captured.lock() += line)
+            .exec()
+            .await?;
+        assert_eq!(output.lock().as_str(), "argument with spaces");
+        let error = Command::from(&node)
+            .args(["-e", "process.exit(3)"])
+            .timeout(Duration::from_secs(30))
+            .exec()
+            .await
+            .expect_err("Command should have failed");
+        assert!(!is_timeout(&error));
+        Ok(())
+    }
+}
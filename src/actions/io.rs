@@ -1,5 +1,5 @@
 use crate::node::path::Path;
-use js_sys::JsString;
+use js_sys::{JsString, Object};
 use wasm_bindgen::JsValue;
 
 /// Locates the absolute path of the command with the specified name. If `check`
@@ -10,6 +10,21 @@ pub async fn which<T: Into<JsString>>(tool: T, check: bool) -> Result<Path, JsVa
     Ok(Path::from(path))
 }
 
+/// Locates all paths of commands with the specified name that are present on
+/// `$PATH`, in the order they would be searched.
+pub async fn which_all<T: Into<JsString>>(tool: T) -> Result<Vec<Path>, JsValue> {
+    use wasm_bindgen::JsCast as _;
+
+    let paths = ffi::find_in_path(&tool.into()).await?;
+    let paths = paths
+        .dyn_into::<js_sys::Array>()
+        .map_err(|_| wasm_bindgen::JsError::new("findInPath didn't return an array"))?
+        .iter()
+        .map(|path| Path::from(JsString::from(path)))
+        .collect();
+    Ok(paths)
+}
+
 /// Recursively removes all files at the specified path
 pub async fn rm_rf<P: Into<JsString>>(path: P) -> Result<(), JsValue> {
     let path = path.into();
@@ -17,9 +32,43 @@ pub async fn rm_rf<P: Into<JsString>>(path: P) -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Creates a folder and any required parent folders at the specified path
+pub async fn mkdir_p<P: Into<JsString>>(path: P) -> Result<(), JsValue> {
+    let path = path.into();
+    ffi::mkdir_p(&path).await?;
+    Ok(())
+}
+
+/// Copies a file or folder. If `recursive` is not set, copying a folder will
+/// fail. If `force` is set, existing files at the destination will be
+/// overwritten.
+pub async fn cp<S: Into<JsString>, D: Into<JsString>>(
+    source: S,
+    dest: D,
+    recursive: bool,
+    force: bool,
+) -> Result<(), JsValue> {
+    let options = js_sys::Map::new();
+    options.set(&"recursive".into(), &recursive.into());
+    options.set(&"force".into(), &force.into());
+    let options = Object::from_entries(&options).expect("Failed to convert options map to object");
+    ffi::cp(&source.into(), &dest.into(), Some(options)).await?;
+    Ok(())
+}
+
+/// Moves a file or folder. If `force` is set, an existing file at the
+/// destination will be overwritten.
+pub async fn mv<S: Into<JsString>, D: Into<JsString>>(source: S, dest: D, force: bool) -> Result<(), JsValue> {
+    let options = js_sys::Map::new();
+    options.set(&"force".into(), &force.into());
+    let options = Object::from_entries(&options).expect("Failed to convert options map to object");
+    ffi::mv(&source.into(), &dest.into(), Some(options)).await?;
+    Ok(())
+}
+
 /// Low-level bindings to the GitHub Actions Toolkit "io" API
 pub mod ffi {
-    use js_sys::JsString;
+    use js_sys::{JsString, Object};
     use wasm_bindgen::prelude::*;
 
     #[wasm_bindgen(module = "@actions/io")]
@@ -27,7 +76,84 @@ pub mod ffi {
         #[wasm_bindgen(js_name = "which", catch)]
         pub async fn which(tool: &JsString, check: Option<bool>) -> Result<JsValue, JsValue>;
 
+        #[wasm_bindgen(js_name = "findInPath", catch)]
+        pub async fn find_in_path(tool: &JsString) -> Result<JsValue, JsValue>;
+
         #[wasm_bindgen(js_name = "rmRF", catch)]
         pub async fn rm_rf(path: &JsString) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(js_name = "mkdirP", catch)]
+        pub async fn mkdir_p(path: &JsString) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(catch)]
+        pub async fn cp(source: &JsString, dest: &JsString, options: Option<Object>) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(catch)]
+        pub async fn mv(source: &JsString, dest: &JsString, options: Option<Object>) -> Result<JsValue, JsValue>;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::node;
+    use crate::node::path::Path;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn temp_path(name: &str) -> Path {
+        let file_name = format!("ferrous-actions-io-test-{} - {}", name, chrono::Local::now());
+        node::os::temp_dir().join(&file_name)
+    }
+
+    #[wasm_bindgen_test]
+    async fn which_all_finds_node() -> Result<(), JsValue> {
+        let paths = super::which_all("node").await?;
+        assert!(!paths.is_empty());
+        assert_eq!(paths[0], super::which("node", true).await?);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn mkdir_p_rm_rf() -> Result<(), JsValue> {
+        let first = temp_path("mkdir");
+        let second = first.join("a").join("b");
+        super::mkdir_p(&second).await?;
+        assert!(second.exists().await);
+        // Creating an existing folder is not an error
+        super::mkdir_p(&second).await?;
+        super::rm_rf(&first).await?;
+        assert!(!first.exists().await);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn cp_recursive() -> Result<(), JsValue> {
+        let from = temp_path("cp-from");
+        let to = temp_path("cp-to");
+        let data = format!("{}", chrono::Local::now()).into_bytes();
+        super::mkdir_p(from.join("nested")).await?;
+        node::fs::write_file(&from.join("nested").join("file"), &data).await?;
+        assert!(super::cp(&from, &to, false, false).await.is_err());
+        super::cp(&from, &to, true, false).await?;
+        assert!(from.exists().await);
+        assert_eq!(node::fs::read_file(&to.join("nested").join("file")).await?, data);
+        super::rm_rf(&from).await?;
+        super::rm_rf(&to).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn mv_force() -> Result<(), JsValue> {
+        let from = temp_path("mv-from");
+        let to = temp_path("mv-to");
+        node::fs::write_file(&from, b"from").await?;
+        node::fs::write_file(&to, b"to").await?;
+        assert!(super::mv(&from, &to, false).await.is_err());
+        assert!(from.exists().await);
+        super::mv(&from, &to, true).await?;
+        assert!(!from.exists().await);
+        assert_eq!(node::fs::read_file(&to).await?, b"from");
+        super::rm_rf(&to).await?;
+        Ok(())
     }
 }
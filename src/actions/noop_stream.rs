@@ -3,6 +3,7 @@ use js_sys::{Function, Object};
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsValue;
 
+/// A writable stream which discards everything written to it and never errors
 pub struct Sink {
     _write: Closure<dyn FnMut(JsValue, JsValue, JsValue)>,
     writable: stream::ffi::Writable,
@@ -27,8 +28,33 @@ impl Default for Sink {
     }
 }
 
+impl Sink {
+    /// Ends the stream, waiting until it has finished
+    #[allow(dead_code)]
+    pub async fn end(&self) -> Result<(), JsValue> {
+        stream::end(&self.writable).await
+    }
+}
+
 impl AsRef<JsValue> for Sink {
     fn as_ref(&self) -> &JsValue {
         self.writable.as_ref()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Sink;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    async fn discards_writes_and_ends() -> Result<(), JsValue> {
+        let sink = Sink::default();
+        for _ in 0..16 {
+            let chunk = js_sys::Uint8Array::from(&[0u8; 1024][..]);
+            sink.writable.write(&chunk);
+        }
+        sink.end().await
+    }
+}
@@ -1,16 +1,35 @@
 use crate::node::path::Path;
-use crate::node::process;
+use crate::node::{self, process};
+use crate::system::{rng, sleep};
+use crate::{info, warning};
+use derivative::Derivative;
+use futures::future::Either;
 use js_sys::JsString;
-use std::borrow::Cow;
 use std::convert::Into;
+use std::future::Future;
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const PROGRESS_LOG_STEPS: u64 = 10;
+const PROGRESS_LOG_UNKNOWN_SIZE_STEP: u64 = 10 * 1024 * 1024;
+
+type ProgressCallback = Box<dyn FnMut(u64, Option<u64>)>;
+
 /// Builder for a tool downloader
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct DownloadTool {
     url: JsString,
     dest: Option<Path>,
     auth: Option<JsString>,
+    headers: js_sys::Map,
+    retries: u32,
+    retry_delay: Duration,
+    expected_size: Option<u64>,
+    #[derivative(Debug = "ignore")]
+    on_progress: Option<ProgressCallback>,
 }
 
 impl<U: Into<JsString>> From<U> for DownloadTool {
@@ -20,6 +39,11 @@ impl<U: Into<JsString>> From<U> for DownloadTool {
             url: url.into(),
             dest: None,
             auth: None,
+            headers: js_sys::Map::new(),
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            expected_size: None,
+            on_progress: None,
         }
     }
 }
@@ -37,17 +61,195 @@ impl DownloadTool {
         self
     }
 
+    /// Specify an additional HTTP header
+    pub fn header<K: Into<JsString>, V: Into<JsString>>(&mut self, key: K, value: V) -> &mut Self {
+        self.headers(std::iter::once((key, value)))
+    }
+
+    /// Specify multiple additional HTTP headers
+    pub fn headers<I, K, V>(&mut self, headers: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<JsString>,
+        V: Into<JsString>,
+    {
+        for (key, value) in headers {
+            self.headers.set(&key.into(), &value.into());
+        }
+        self
+    }
+
+    /// Set the number of times a failed download should be retried. Failures
+    /// due to client errors such as a 404 are never retried.
+    pub fn retries(&mut self, retries: u32) -> &mut Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set the delay before the first retry. The delay doubles after each
+    /// subsequent failure.
+    pub fn retry_delay(&mut self, delay: Duration) -> &mut Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Set the expected size of the download in bytes. This is only used as
+    /// the total supplied to the progress callback.
+    pub fn expected_size(&mut self, size: u64) -> &mut Self {
+        self.expected_size = Some(size);
+        self
+    }
+
+    /// Set a callback which is periodically passed the number of bytes
+    /// downloaded so far and the expected total, if known. Progress is
+    /// determined by polling the size of the partially downloaded file.
+    pub fn on_progress<F: FnMut(u64, Option<u64>) + 'static>(&mut self, callback: F) -> &mut Self {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Log progress at `info` level each time another tenth of the download
+    /// completes. When the expected size is unknown, progress is logged every
+    /// 10 MiB instead.
+    pub fn log_progress<N: Into<String>>(&mut self, name: N) -> &mut Self {
+        let name = name.into();
+        let mut last_step = 0;
+        self.on_progress(move |downloaded, total| {
+            let step = progress_step(downloaded, total);
+            if step > last_step {
+                last_step = step;
+                match total {
+                    Some(total) if total > 0 => info!(
+                        "Downloaded {}% of {} ({} of {} bytes)",
+                        step * 100 / PROGRESS_LOG_STEPS,
+                        name,
+                        downloaded,
+                        total
+                    ),
+                    _ => info!("Downloaded {} bytes of {}", downloaded, name),
+                }
+            }
+        })
+    }
+
     /// Perform the download and return the path the file was downloaded to
     pub async fn download(&mut self) -> Result<Path, JsValue> {
-        let dest = self.dest.as_ref().map(|dest| {
-            let mut resolved = process::cwd();
-            resolved.push(dest.clone());
-            JsString::from(&resolved)
+        let dest = match (&self.dest, &self.on_progress) {
+            (Some(dest), _) => {
+                let mut resolved = process::cwd();
+                resolved.push(dest.clone());
+                Some(resolved)
+            }
+            // We need to know where the file is being written to in order to poll its size
+            (None, Some(_)) => Some(default_download_dir().join(&random_file_name())),
+            (None, None) => None,
+        };
+        let dest_js = dest.as_ref().map(JsString::from);
+        let headers = js_sys::Object::from_entries(&self.headers).expect("Failed to convert headers map to object");
+        let download = retry_with_backoff(self.retries, self.retry_delay, || {
+            ffi::download_tool(&self.url, dest_js.as_ref(), self.auth.as_ref(), Some(&headers))
         });
-        ffi::download_tool(&self.url, dest.as_ref(), self.auth.as_ref(), None)
-            .await
-            .map(Into::<JsString>::into)
-            .map(Into::<Path>::into)
+        let result = match (&dest, self.on_progress.as_mut()) {
+            (Some(dest), Some(callback)) => watch_progress(dest, self.expected_size, download, callback.as_mut()).await,
+            _ => download.await,
+        };
+        result.map(Into::<JsString>::into).map(Into::<Path>::into)
+    }
+}
+
+fn progress_step(downloaded: u64, total: Option<u64>) -> u64 {
+    match total {
+        Some(total) if total > 0 => downloaded.min(total).saturating_mul(PROGRESS_LOG_STEPS) / total,
+        _ => downloaded / PROGRESS_LOG_UNKNOWN_SIZE_STEP,
+    }
+}
+
+fn default_download_dir() -> Path {
+    // Matches the location the toolkit uses when no destination is specified
+    process::get_env()
+        .get("RUNNER_TEMP")
+        .map_or_else(node::os::temp_dir, |dir| Path::from(dir.as_str()))
+}
+
+fn random_file_name() -> String {
+    use std::fmt::Write as _;
+
+    let mut bytes = [0u8; 16];
+    rng::fill_bytes(&mut bytes);
+    bytes.iter().fold(String::new(), |mut name, byte| {
+        write!(name, "{:02x}", byte).expect("Unable to write to string");
+        name
+    })
+}
+
+async fn watch_progress<F, T>(
+    path: &Path,
+    total: Option<u64>,
+    operation: F,
+    callback: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<T, JsValue>
+where
+    F: Future<Output = Result<T, JsValue>>,
+{
+    let mut operation = std::pin::pin!(operation);
+    let mut last_reported = None;
+    loop {
+        let tick = std::pin::pin!(sleep::sleep(&PROGRESS_POLL_INTERVAL));
+        match futures::future::select(operation.as_mut(), tick).await {
+            Either::Left((result, _)) => {
+                if result.is_ok() {
+                    if let Ok(metadata) = node::fs::metadata(path).await {
+                        callback(metadata.len(), total);
+                    }
+                }
+                return result;
+            }
+            Either::Right(((), _)) => {
+                // The file will not exist until the response headers have been received
+                if let Ok(metadata) = node::fs::metadata(path).await {
+                    let downloaded = metadata.len();
+                    if last_reported != Some(downloaded) {
+                        last_reported = Some(downloaded);
+                        callback(downloaded, total);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_retryable(error: &JsValue) -> bool {
+    // Unexpected HTTP responses are reported using an `HTTPError` which carries
+    // the status code. Client errors will not be fixed by retrying, except for
+    // timeouts and rate limiting.
+    #[allow(clippy::cast_possible_truncation)]
+    let status = js_sys::Reflect::get(error, &"httpStatusCode".into())
+        .ok()
+        .and_then(|status| status.as_f64())
+        .map(|status| status as i64);
+    match status {
+        Some(status) => !(400..500).contains(&status) || status == 408 || status == 429,
+        None => true,
+    }
+}
+
+async fn retry_with_backoff<F, R, T>(retries: u32, delay: Duration, mut operation: F) -> Result<T, JsValue>
+where
+    F: FnMut() -> R,
+    R: Future<Output = Result<T, JsValue>>,
+{
+    let mut delay = delay;
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Err(e) if attempt < retries && is_retryable(&e) => {
+                attempt += 1;
+                warning!("Download attempt {} failed, retrying in {:?}: {:?}", attempt, delay, e);
+                sleep::sleep(&delay).await;
+                delay = delay.saturating_mul(2);
+            }
+            result => return result,
+        }
     }
 }
 
@@ -57,7 +259,7 @@ pub async fn download_tool<O: Into<DownloadTool>>(options: O) -> Result<Path, Js
 }
 
 /// Different types of compression that may be applied to a tar file
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum StreamCompression {
     /// None
     None,
@@ -70,34 +272,180 @@ pub enum StreamCompression {
 
     /// LZMA
     Xz,
+
+    /// Zstandard
+    Zstd,
 }
 
 impl StreamCompression {
-    fn tar_flag(&self) -> Cow<str> {
+    /// The single-letter option for this compression, if it has one
+    fn tar_flag(self) -> Option<char> {
         match self {
-            StreamCompression::None => "",
-            StreamCompression::Gzip => "z",
-            StreamCompression::Bzip2 => "j",
-            StreamCompression::Xz => "J",
+            StreamCompression::None | StreamCompression::Zstd => None,
+            StreamCompression::Gzip => Some('z'),
+            StreamCompression::Bzip2 => Some('j'),
+            StreamCompression::Xz => Some('J'),
+        }
+    }
+
+    /// The arguments passed to `tar` to extract an archive with this
+    /// compression. Zstandard has no single-letter option shared by GNU and BSD
+    /// tar, so it is passed as a separate long option.
+    fn tar_args(self) -> Vec<String> {
+        let mut bundled = String::from("x");
+        bundled.extend(self.tar_flag());
+        let mut args = vec![bundled];
+        if self == StreamCompression::Zstd {
+            args.push("--zstd".into());
         }
-        .into()
+        args
+    }
+}
+
+/// Builder for extracting a tar file
+#[derive(Debug)]
+pub struct ExtractTar {
+    path: Path,
+    compression: StreamCompression,
+    dest: Option<Path>,
+    strip_components: u32,
+}
+
+impl ExtractTar {
+    /// Constructs an `ExtractTar` that will extract the specified file
+    pub fn new<P: Into<Path>>(path: P, compression: StreamCompression) -> ExtractTar {
+        ExtractTar {
+            path: path.into(),
+            compression,
+            dest: None,
+            strip_components: 0,
+        }
+    }
+
+    /// Set the directory to extract into
+    pub fn dest<D: Into<Path>>(&mut self, dest: D) -> &mut Self {
+        self.dest = Some(dest.into());
+        self
+    }
+
+    /// Set the number of leading path components removed from each extracted
+    /// entry. This can be used to flatten a wrapper directory such as
+    /// `tool-1.2.3/` so that its contents are placed directly in the
+    /// destination.
+    pub fn strip_components(&mut self, count: u32) -> &mut Self {
+        self.strip_components = count;
+        self
+    }
+
+    fn tar_args(&self) -> Vec<String> {
+        let mut args = self.compression.tar_args();
+        if self.strip_components != 0 {
+            args.push(format!("--strip-components={}", self.strip_components));
+        }
+        args
+    }
+
+    /// Perform the extraction and return the directory extracted into
+    pub async fn extract(&self) -> Result<Path, JsValue> {
+        let tar_args = self.tar_args().into_iter().map(JsString::from).collect();
+        let path: JsString = (&self.path).into();
+        let dest = self.dest.as_ref().map(Into::<JsString>::into);
+        let dest = ffi::extract_tar(&path, dest.as_ref(), Some(tar_args)).await?;
+        let dest: JsString = dest.into();
+        Ok(dest.into())
     }
 }
 
 /// Extracts a tar file with the specified compression. An output directory can
 /// be optionally specified.
 pub async fn extract_tar(path: &Path, compression: StreamCompression, dest: Option<&Path>) -> Result<Path, JsValue> {
-    let mut tar_option = String::from("x");
-    tar_option += &compression.tar_flag();
-    let tar_option = vec![JsString::from(tar_option)];
+    let mut extract = ExtractTar::new(path, compression);
+    if let Some(dest) = dest {
+        extract.dest(dest);
+    }
+    extract.extract().await
+}
 
+/// Extracts a zip file. An output directory can be optionally specified.
+pub async fn extract_zip(path: &Path, dest: Option<&Path>) -> Result<Path, JsValue> {
     let path: JsString = path.into();
     let dest = dest.map(Into::<JsString>::into);
-    let dest = ffi::extract_tar(&path, dest.as_ref(), Some(tar_option)).await?;
+    let dest = ffi::extract_zip(&path, dest.as_ref()).await?;
     let dest: JsString = dest.into();
     Ok(dest.into())
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ArchiveFormat {
+    Tar(StreamCompression),
+    Zip,
+}
+
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8] = b"ustar";
+
+impl ArchiveFormat {
+    /// The number of bytes from the start of a file needed to detect its format
+    const HEADER_LEN: usize = TAR_MAGIC_OFFSET + TAR_MAGIC.len();
+
+    /// Reads no more than the leading bytes needed by `detect`
+    async fn read_header(path: &Path) -> Result<Vec<u8>, JsValue> {
+        let mut file = crate::node::fs::File::open(path).await?;
+        let mut header = vec![0u8; Self::HEADER_LEN];
+        let mut filled = 0;
+        let result = loop {
+            match file.read(&mut header[filled..]).await {
+                Ok(0) => break Ok(()),
+                Ok(bytes_read) => {
+                    filled += bytes_read;
+                    if filled == header.len() {
+                        break Ok(());
+                    }
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        file.close().await?;
+        result?;
+        header.truncate(filled);
+        Ok(header)
+    }
+
+    fn detect(header: &[u8]) -> Option<ArchiveFormat> {
+        let format = if header.starts_with(&[0x1f, 0x8b]) {
+            ArchiveFormat::Tar(StreamCompression::Gzip)
+        } else if header.starts_with(b"BZh") {
+            ArchiveFormat::Tar(StreamCompression::Bzip2)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            ArchiveFormat::Tar(StreamCompression::Xz)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            ArchiveFormat::Tar(StreamCompression::Zstd)
+        } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            ArchiveFormat::Zip
+        } else if header
+            .get(TAR_MAGIC_OFFSET..)
+            .is_some_and(|magic| magic.starts_with(TAR_MAGIC))
+        {
+            ArchiveFormat::Tar(StreamCompression::None)
+        } else {
+            return None;
+        };
+        Some(format)
+    }
+}
+
+/// Extracts an archive, determining its format from its content. Zip files
+/// and tar files compressed with any of the supported `StreamCompression`
+/// methods are recognised. An output directory can be optionally specified.
+pub async fn extract(path: &Path, dest: Option<&Path>) -> Result<Path, JsValue> {
+    let header = ArchiveFormat::read_header(path).await?;
+    match ArchiveFormat::detect(&header) {
+        Some(ArchiveFormat::Tar(compression)) => extract_tar(path, compression, dest).await,
+        Some(ArchiveFormat::Zip) => extract_zip(path, dest).await,
+        None => Err(JsError::new(&format!("Unable to determine archive format of {}", path)).into()),
+    }
+}
+
 /// Saves a path into a local cache
 pub async fn cache_dir(tool: &str, version: &str, path: &Path, arch: Option<&str>) -> Result<Path, JsValue> {
     let path: JsString = path.into();
@@ -109,9 +457,38 @@ pub async fn cache_dir(tool: &str, version: &str, path: &Path, arch: Option<&str
     Ok(dest.into())
 }
 
+/// Saves a single file into a local cache under the specified name
+pub async fn cache_file(
+    source_file: &Path,
+    target_name: &str,
+    tool: &str,
+    version: &str,
+    arch: Option<&str>,
+) -> Result<Path, JsValue> {
+    let source_file: JsString = source_file.into();
+    let target_name: JsString = target_name.into();
+    let tool: JsString = tool.into();
+    let version: JsString = version.into();
+    let arch: Option<JsString> = arch.map(Into::into);
+    let dest = ffi::cache_file(&source_file, &target_name, &tool, &version, arch.as_ref()).await?;
+    let dest: JsString = dest.into();
+    Ok(dest.into())
+}
+
+/// Locates the folder of a tool in the local cache. The version may be a
+/// semantic version range, in which case the latest matching version is
+/// returned.
+pub fn find(tool: &str, version: &str, arch: Option<&str>) -> Result<Option<Path>, JsValue> {
+    let tool: JsString = tool.into();
+    let version: JsString = version.into();
+    let arch: Option<JsString> = arch.map(Into::into);
+    let path = ffi::find(&tool, &version, arch.as_ref())?;
+    Ok(if path.length() == 0 { None } else { Some(path.into()) })
+}
+
 /// Low level bindings for the GitHub Actions Toolkit "tool cache" API
 pub mod ffi {
-    use js_sys::{JsString, Map};
+    use js_sys::{JsString, Object};
     use wasm_bindgen::prelude::*;
 
     #[wasm_bindgen(module = "@actions/tool-cache")]
@@ -121,7 +498,7 @@ pub mod ffi {
             url: &JsString,
             dest: Option<&JsString>,
             auth: Option<&JsString>,
-            headers: Option<&Map>,
+            headers: Option<&Object>,
         ) -> Result<JsValue, JsValue>;
 
         #[wasm_bindgen(js_name = "cacheDir", catch)]
@@ -132,11 +509,256 @@ pub mod ffi {
             arch: Option<&JsString>,
         ) -> Result<JsValue, JsValue>;
 
+        #[wasm_bindgen(js_name = "cacheFile", catch)]
+        pub async fn cache_file(
+            source_file: &JsString,
+            target_file: &JsString,
+            tool: &JsString,
+            version: &JsString,
+            arch: Option<&JsString>,
+        ) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(catch)]
+        pub fn find(tool: &JsString, version_spec: &JsString, arch: Option<&JsString>) -> Result<JsString, JsValue>;
+
         #[wasm_bindgen(js_name = "extractTar", catch)]
         pub async fn extract_tar(
             file: &JsString,
             dest: Option<&JsString>,
             flags: Option<Vec<JsString>>,
         ) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(js_name = "extractZip", catch)]
+        pub async fn extract_zip(file: &JsString, dest: Option<&JsString>) -> Result<JsValue, JsValue>;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{progress_step, ArchiveFormat, StreamCompression};
+    use crate::node;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::Duration;
+    use wasm_bindgen::{JsError, JsValue};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn http_error(status: u16) -> JsValue {
+        let error: JsValue = JsError::new("Unexpected HTTP response").into();
+        js_sys::Reflect::set(&error, &"httpStatusCode".into(), &status.into()).expect("Unable to set status code");
+        error
+    }
+
+    async fn count_attempts(retries: u32, failures: u32, error: fn() -> JsValue) -> (u32, bool) {
+        let attempts = Cell::new(0);
+        let result = super::retry_with_backoff(retries, Duration::from_millis(1), || {
+            attempts.set(attempts.get() + 1);
+            let attempt = attempts.get();
+            async move {
+                if attempt > failures {
+                    Ok(())
+                } else {
+                    Err(error())
+                }
+            }
+        })
+        .await;
+        (attempts.get(), result.is_ok())
+    }
+
+    #[wasm_bindgen_test]
+    fn download_tool_headers() {
+        let mut download = super::DownloadTool::from("https://example.com/tool.tar.gz");
+        download
+            .auth("token secret")
+            .header("User-Agent", "ferrous-actions")
+            .headers([("X-First", "1"), ("X-Second", "2")])
+            .header("X-First", "3");
+        let headers = &download.headers;
+        assert_eq!(headers.size(), 3);
+        for (key, value) in [("User-Agent", "ferrous-actions"), ("X-First", "3"), ("X-Second", "2")] {
+            assert_eq!(headers.get(&key.into()).as_string().as_deref(), Some(value));
+        }
+        assert_eq!(download.auth.map(String::from).as_deref(), Some("token secret"));
+    }
+
+    #[wasm_bindgen_test]
+    async fn retry_until_success() {
+        assert_eq!(count_attempts(3, 2, || http_error(503)).await, (3, true));
+        assert_eq!(
+            count_attempts(3, 2, || JsError::new("ECONNRESET").into()).await,
+            (3, true)
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn retry_until_exhausted() {
+        assert_eq!(count_attempts(2, 5, || http_error(503)).await, (3, false));
+        assert_eq!(count_attempts(0, 5, || http_error(503)).await, (1, false));
+    }
+
+    #[wasm_bindgen_test]
+    async fn client_errors_fail_fast() {
+        assert_eq!(count_attempts(3, 2, || http_error(404)).await, (1, false));
+        assert_eq!(count_attempts(3, 2, || http_error(429)).await, (3, true));
+    }
+
+    #[wasm_bindgen_test]
+    fn detect_archive_formats() {
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        let cases: [(&[u8], Option<ArchiveFormat>); 8] = [
+            (
+                &[0x1f, 0x8b, 0x08, 0x00],
+                Some(ArchiveFormat::Tar(StreamCompression::Gzip)),
+            ),
+            (b"BZh91AY", Some(ArchiveFormat::Tar(StreamCompression::Bzip2))),
+            (
+                &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00],
+                Some(ArchiveFormat::Tar(StreamCompression::Xz)),
+            ),
+            (
+                &[0x28, 0xb5, 0x2f, 0xfd],
+                Some(ArchiveFormat::Tar(StreamCompression::Zstd)),
+            ),
+            (b"PK\x03\x04\x14\x00", Some(ArchiveFormat::Zip)),
+            (&tar, Some(ArchiveFormat::Tar(StreamCompression::None))),
+            (b"#!/bin/sh", None),
+            (&[], None),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(ArchiveFormat::detect(header), expected);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn header_read_is_bounded() -> Result<(), JsValue> {
+        let mut tar = vec![0u8; 4096];
+        tar[257..262].copy_from_slice(b"ustar");
+        let path = node::os::temp_dir().join(&format!("ferrous-actions-header-test - {}", chrono::Local::now()));
+        node::fs::write_file(&path, &tar).await?;
+        let header = ArchiveFormat::read_header(&path).await;
+        node::fs::write_file(&path, b"PK\x03\x04").await?;
+        let short_header = ArchiveFormat::read_header(&path).await;
+        node::fs::remove_file(&path).await?;
+        let header = header?;
+        assert_eq!(header.len(), ArchiveFormat::HEADER_LEN);
+        assert_eq!(
+            ArchiveFormat::detect(&header),
+            Some(ArchiveFormat::Tar(StreamCompression::None))
+        );
+        assert_eq!(short_header?, b"PK\x03\x04");
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn tar_args_for_compression() {
+        for (compression, expected) in [
+            (StreamCompression::None, vec!["x"]),
+            (StreamCompression::Gzip, vec!["xz"]),
+            (StreamCompression::Bzip2, vec!["xj"]),
+            (StreamCompression::Xz, vec!["xJ"]),
+            (StreamCompression::Zstd, vec!["x", "--zstd"]),
+        ] {
+            assert_eq!(compression.tar_args(), expected);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn strip_components_arg() {
+        let mut extract = super::ExtractTar::new("tool.tar.gz", StreamCompression::Gzip);
+        assert_eq!(extract.tar_args(), ["xz"]);
+        extract.strip_components(1);
+        assert_eq!(extract.tar_args(), ["xz", "--strip-components=1"]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn extract_strips_wrapper_dir() -> Result<(), JsValue> {
+        use crate::actions::exec::Command;
+
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-strip-test - {}", chrono::Local::now()));
+        let wrapper = root.join_all(["source", "tool-1.2.3"]);
+        node::fs::create_dir_all(&wrapper).await?;
+        node::fs::write_file(&wrapper.join("tool"), b"Nothing to see here\n").await?;
+        let archive = root.join("tool.tar");
+        let tar = crate::actions::io::which("tar", true).await?;
+        Command::from(&tar)
+            .arg("-cf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(root.join("source"))
+            .arg("tool-1.2.3")
+            .exec()
+            .await?;
+
+        let dest = root.join("extracted");
+        let extracted = super::ExtractTar::new(&archive, StreamCompression::None)
+            .dest(&dest)
+            .strip_components(1)
+            .extract()
+            .await?;
+        assert_eq!(extracted, dest);
+        assert!(dest.join("tool").exists().await);
+        assert!(!dest.join("tool-1.2.3").exists().await);
+
+        crate::actions::io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn progress_reports_full_size() -> Result<(), JsValue> {
+        let path = node::os::temp_dir().join(&format!("ferrous-actions-progress-test - {}", chrono::Local::now()));
+        let data = vec![0x5au8; 100_000];
+        let observed = Rc::new(RefCell::new(Vec::new()));
+        let captured = observed.clone();
+        let mut callback = move |downloaded, total| captured.borrow_mut().push((downloaded, total));
+        let written = super::watch_progress(
+            &path,
+            Some(100_000),
+            async {
+                node::fs::write_file(&path, &data).await?;
+                Ok(path.clone())
+            },
+            &mut callback,
+        )
+        .await?;
+        assert_eq!(observed.borrow().last(), Some(&(100_000, Some(100_000))));
+        node::fs::remove_file(&written).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn cache_file_and_find() -> Result<(), JsValue> {
+        let unique = format!("ferrous-actions-tool-cache-test - {}", chrono::Local::now());
+        let root = node::os::temp_dir().join(&unique);
+        let cache_root = root.join("cache");
+        node::fs::create_dir_all(&cache_root).await?;
+        node::process::set_var("RUNNER_TOOL_CACHE", &cache_root.to_string());
+
+        let tool = "ferrous-actions-test-tool";
+        let file_name = "tool-binary";
+        let source = root.join(file_name);
+        node::fs::write_file(&source, b"Nothing to see here\n").await?;
+        assert!(super::find(tool, "1.2.3", None)?.is_none());
+        super::cache_file(&source, file_name, tool, "1.2.3", None).await?;
+        let found = super::find(tool, "1.x", None)?.expect("Cached tool was not found");
+        assert!(found.join(file_name).exists().await);
+        assert!(super::find(tool, "2.x", None)?.is_none());
+
+        node::process::remove_var("RUNNER_TOOL_CACHE");
+        crate::actions::io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn progress_steps() {
+        assert_eq!(progress_step(0, Some(1000)), 0);
+        assert_eq!(progress_step(99, Some(1000)), 0);
+        assert_eq!(progress_step(100, Some(1000)), 1);
+        assert_eq!(progress_step(1000, Some(1000)), 10);
+        // Overshooting the expected size never reports beyond 100%
+        assert_eq!(progress_step(5000, Some(1000)), 10);
+        assert_eq!(progress_step(1000, Some(0)), 0);
+        assert_eq!(progress_step(25 * 1024 * 1024, None), 2);
     }
 }
@@ -1,7 +1,10 @@
-use crate::node;
+use crate::node::os::Platform;
 use crate::node::path::Path;
 use serde::{Deserialize, Serialize};
 
+/// A relative or absolute path stored as a list of components so that it can
+/// be rendered for any platform. The canonical string form uses `/` as a
+/// separator.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct AgnosticPath {
     components: Vec<String>,
@@ -18,29 +21,59 @@ impl AgnosticPath {
             .chain(trailing)
             .join(separator)
     }
-}
 
-impl From<&Path> for AgnosticPath {
-    fn from(os_path: &Path) -> AgnosticPath {
-        let os_path = os_path.to_string();
-        let separator = node::path::separator();
-        let (os_path, trailing_separator) = if let Some(stripped) = os_path.strip_suffix(separator.as_ref()) {
-            (stripped, true)
-        } else {
-            (os_path.as_str(), false)
+    fn separator_for(platform: &Platform) -> &'static str {
+        match platform {
+            Platform::Windows => "\\",
+            _ => "/",
+        }
+    }
+
+    /// Parses a path in the form used by the specified platform. Windows
+    /// accepts both `\` and `/` as separators.
+    pub fn from_os_str(os_path: &str, platform: &Platform) -> AgnosticPath {
+        let is_separator = |c: char| match platform {
+            Platform::Windows => c == '\\' || c == '/',
+            _ => c == '/',
+        };
+        let (os_path, trailing_separator) = match os_path.strip_suffix(is_separator) {
+            Some(stripped) => (stripped, true),
+            None => (os_path, false),
         };
-        let components = os_path.split(separator.as_ref()).map(str::to_string).collect();
+        let components = os_path.split(is_separator).map(str::to_string).collect();
         AgnosticPath {
             components,
             trailing_separator,
         }
     }
+
+    /// Renders the path in the form used by the specified platform
+    pub fn to_os_string(&self, platform: &Platform) -> String {
+        self.to_string_with_separator(Self::separator_for(platform))
+    }
+
+    /// Converts a path for the current platform
+    pub fn from_os(os_path: &Path) -> AgnosticPath {
+        Self::from_os_str(&os_path.to_string(), &crate::node::os::current_platform())
+    }
+
+    /// Converts to a path for the specified platform. Note that the resulting
+    /// path is still normalized according to the rules of the current
+    /// platform.
+    pub fn to_os(&self, platform: &Platform) -> Path {
+        Path::from(&self.to_os_string(platform))
+    }
+}
+
+impl From<&Path> for AgnosticPath {
+    fn from(os_path: &Path) -> AgnosticPath {
+        AgnosticPath::from_os(os_path)
+    }
 }
 
 impl From<&AgnosticPath> for Path {
     fn from(path: &AgnosticPath) -> Path {
-        let os_path = path.to_string_with_separator(&node::path::separator());
-        Path::from(&os_path)
+        path.to_os(&crate::node::os::current_platform())
     }
 }
 
@@ -50,3 +83,39 @@ impl std::fmt::Display for AgnosticPath {
         string.fmt(formatter)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::AgnosticPath;
+    use crate::node::os::Platform;
+    use crate::node::path::Path;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn round_trip_between_platforms() {
+        let unix = Platform::Linux;
+        let windows = Platform::Windows;
+        let path = AgnosticPath::from_os_str("registry/index/github.com-1ecc6299db9ec823/", &unix);
+        assert_eq!(path.to_string(), "registry/index/github.com-1ecc6299db9ec823/");
+        let windows_form = path.to_os_string(&windows);
+        assert_eq!(windows_form, "registry\\index\\github.com-1ecc6299db9ec823\\");
+        let from_windows = AgnosticPath::from_os_str(&windows_form, &windows);
+        assert_eq!(from_windows, path);
+        assert_eq!(
+            from_windows.to_os_string(&unix),
+            "registry/index/github.com-1ecc6299db9ec823/"
+        );
+
+        // Windows also accepts forward slashes, but backslashes are not separators elsewhere
+        assert_eq!(AgnosticPath::from_os_str("git/db\\serde", &windows).components.len(), 3);
+        assert_eq!(AgnosticPath::from_os_str("git/db\\serde", &unix).components.len(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn round_trip_current_platform() {
+        let path = Path::from("git").join("checkouts").join("serde-0123456789abcdef");
+        let agnostic = AgnosticPath::from_os(&path);
+        assert_eq!(agnostic.to_string(), "git/checkouts/serde-0123456789abcdef");
+        assert_eq!(agnostic.to_os(&crate::node::os::current_platform()), path);
+    }
+}
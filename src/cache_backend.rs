@@ -0,0 +1,282 @@
+use crate::actions::cache::Entry as CacheEntry;
+use crate::actions::core;
+use crate::input_manager::{self, Input};
+use crate::{info, Error};
+use async_trait::async_trait;
+use itertools::Itertools as _;
+use std::cell::RefCell;
+
+/// The outcome of an attempt to save a cache entry
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SaveOutcome {
+    Saved,
+    AlreadyExists,
+    Skipped,
+}
+
+/// Performs the transfers of cache entries to and from the remote cache
+#[async_trait(?Send)]
+pub trait Backend {
+    async fn restore(&self, entry: &CacheEntry) -> Result<Option<String>, Error>;
+
+    async fn save(&self, entry: &CacheEntry) -> Result<SaveOutcome, Error>;
+
+    async fn save_if_update(&self, entry: &CacheEntry, old_restore_key: Option<&str>) -> Result<SaveOutcome, Error>;
+
+    /// Whether this backend only logs what would happen, in which case
+    /// callers should also leave local files untouched
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+}
+
+/// Transfers entries using the GitHub Actions cache
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Remote;
+
+#[async_trait(?Send)]
+impl Backend for Remote {
+    async fn restore(&self, entry: &CacheEntry) -> Result<Option<String>, Error> {
+        Ok(entry.restore().await?)
+    }
+
+    async fn save(&self, entry: &CacheEntry) -> Result<SaveOutcome, Error> {
+        entry.save().await?;
+        Ok(SaveOutcome::Saved)
+    }
+
+    async fn save_if_update(&self, entry: &CacheEntry, old_restore_key: Option<&str>) -> Result<SaveOutcome, Error> {
+        let result = entry.save_if_update(old_restore_key).await?;
+        Ok(if result.is_some() {
+            SaveOutcome::Saved
+        } else {
+            SaveOutcome::AlreadyExists
+        })
+    }
+}
+
+/// Logs the entries that would be transferred without touching the cache
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DryRun;
+
+impl DryRun {
+    fn describe(entry: &CacheEntry) -> String {
+        format!("{} (paths: {})", entry.get_key(), entry.get_paths().iter().join(", "))
+    }
+}
+
+#[async_trait(?Send)]
+impl Backend for DryRun {
+    async fn restore(&self, entry: &CacheEntry) -> Result<Option<String>, Error> {
+        info!("Dry run: skipping restore of {}", Self::describe(entry));
+        Ok(None)
+    }
+
+    async fn save(&self, entry: &CacheEntry) -> Result<SaveOutcome, Error> {
+        info!("Dry run: skipping save of {}", Self::describe(entry));
+        Ok(SaveOutcome::Skipped)
+    }
+
+    async fn save_if_update(&self, entry: &CacheEntry, _old_restore_key: Option<&str>) -> Result<SaveOutcome, Error> {
+        self.save(entry).await
+    }
+
+    fn is_dry_run(&self) -> bool {
+        true
+    }
+}
+
+/// Passes transfers through to another backend, recording the key requested
+/// and the key matched (if any) for every restore
+pub struct Recording {
+    inner: Box<dyn Backend>,
+    restores: RefCell<Vec<(String, Option<String>)>>,
+}
+
+impl Recording {
+    pub fn new(inner: Box<dyn Backend>) -> Recording {
+        Recording {
+            inner,
+            restores: RefCell::default(),
+        }
+    }
+
+    /// Publishes the `cache-hit`, `cache-primary-key` and `cache-restored-key`
+    /// step outputs describing the restores performed so far
+    pub fn set_outputs(&self) {
+        for (name, value) in restore_outputs(&self.restores.borrow()) {
+            core::set_output(name, value);
+        }
+    }
+}
+
+/// Computes outputs mirroring those of `actions/cache`. Since several entries
+/// may be restored, the keys are newline separated and a hit requires every
+/// entry to have been restored from its primary key. Saved keys extend the
+/// primary key with attributes such as the save time, so a restored key
+/// matches if the primary key is a prefix of it.
+fn restore_outputs(restores: &[(String, Option<String>)]) -> [(&'static str, String); 3] {
+    let hit = !restores.is_empty()
+        && restores.iter().all(|(primary, restored)| {
+            restored
+                .as_deref()
+                .is_some_and(|restored| restored.starts_with(primary.as_str()))
+        });
+    let primary_keys = restores.iter().map(|(primary, _)| primary).join("\n");
+    let restored_keys = restores.iter().filter_map(|(_, restored)| restored.as_ref()).join("\n");
+    [
+        ("cache-hit", hit.to_string()),
+        ("cache-primary-key", primary_keys),
+        ("cache-restored-key", restored_keys),
+    ]
+}
+
+#[async_trait(?Send)]
+impl Backend for Recording {
+    async fn restore(&self, entry: &CacheEntry) -> Result<Option<String>, Error> {
+        let restored = self.inner.restore(entry).await?;
+        self.restores
+            .borrow_mut()
+            .push((entry.get_primary_key(), restored.clone()));
+        Ok(restored)
+    }
+
+    async fn save(&self, entry: &CacheEntry) -> Result<SaveOutcome, Error> {
+        self.inner.save(entry).await
+    }
+
+    async fn save_if_update(&self, entry: &CacheEntry, old_restore_key: Option<&str>) -> Result<SaveOutcome, Error> {
+        self.inner.save_if_update(entry, old_restore_key).await
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.inner.is_dry_run()
+    }
+}
+
+/// Selects the backend according to the `dry-run` input
+pub fn from_input(input_manager: &input_manager::Manager) -> Result<Box<dyn Backend>, Error> {
+    let dry_run = input_manager.get_bool(Input::DryRun)?.unwrap_or(false);
+    Ok(if dry_run { Box::new(DryRun) } else { Box::new(Remote) })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{restore_outputs, Backend, DryRun, Recording, Remote, SaveOutcome};
+    use crate::actions::cache::Entry as CacheEntry;
+    use crate::node;
+    use crate::Error;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// Restores entries from a fixed mapping of primary keys to matched keys
+    struct Fixed {
+        matches: HashMap<String, String>,
+    }
+
+    #[async_trait(?Send)]
+    impl Backend for Fixed {
+        async fn restore(&self, entry: &CacheEntry) -> Result<Option<String>, Error> {
+            Ok(self.matches.get(&entry.get_primary_key()).cloned())
+        }
+
+        async fn save(&self, _entry: &CacheEntry) -> Result<SaveOutcome, Error> {
+            Ok(SaveOutcome::Skipped)
+        }
+
+        async fn save_if_update(
+            &self,
+            _entry: &CacheEntry,
+            _old_restore_key: Option<&str>,
+        ) -> Result<SaveOutcome, Error> {
+            Ok(SaveOutcome::Skipped)
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn restores_are_recorded() {
+        let matches = [("exact", "exact"), ("fallback", "older")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let backend = Recording::new(Box::new(Fixed { matches }));
+        for key in ["exact", "fallback", "missing"] {
+            backend.restore(&CacheEntry::new(key)).await.expect("Restore failed");
+        }
+        let outputs = restore_outputs(&backend.restores.borrow());
+        assert_eq!(
+            outputs,
+            [
+                ("cache-hit", "false".to_string()),
+                ("cache-primary-key", "exact\nfallback\nmissing".to_string()),
+                ("cache-restored-key", "exact\nolder".to_string()),
+            ]
+        );
+        let exact = [("exact".to_string(), Some("exact".to_string()))];
+        assert_eq!(restore_outputs(&exact)[0], ("cache-hit", "true".to_string()));
+        assert_eq!(restore_outputs(&[])[0], ("cache-hit", "false".to_string()));
+    }
+
+    fn built_entry(lock_file: &str) -> CacheEntry {
+        use crate::cache_key_builder::CacheKeyBuilder;
+
+        let mut builder = CacheKeyBuilder::new("restore outputs test");
+        builder.add_key_data("toolchain");
+        builder.add_fallback();
+        builder.add_key_data(lock_file);
+        builder.into_entry()
+    }
+
+    #[wasm_bindgen_test]
+    async fn hit_detected_for_built_keys() {
+        // Stands in for an entry saved by an earlier run, whose key differs in
+        // its timestamp and nonce
+        let saved = built_entry("lock").get_key();
+        let current = built_entry("lock");
+        assert_ne!(current.get_key(), saved);
+        assert_eq!(current.get_primary_key(), built_entry("lock").get_primary_key());
+
+        let changed = built_entry("changed lock");
+        let matches = [current.get_primary_key(), changed.get_primary_key()]
+            .into_iter()
+            .map(|primary| (primary, saved.clone()))
+            .collect();
+        let backend = Recording::new(Box::new(Fixed { matches }));
+        backend.restore(&current).await.expect("Restore failed");
+        assert_eq!(
+            restore_outputs(&backend.restores.borrow())[0],
+            ("cache-hit", "true".to_string())
+        );
+        // Restoring via the fallback key is not a hit
+        backend.restore(&changed).await.expect("Restore failed");
+        assert_eq!(
+            restore_outputs(&backend.restores.borrow())[0],
+            ("cache-hit", "false".to_string())
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn recording_forwards_dry_run() {
+        assert!(Recording::new(Box::new(DryRun)).is_dry_run());
+        assert!(!Recording::new(Box::new(Remote)).is_dry_run());
+    }
+
+    // The test environment has no cache service, so any attempt to reach the
+    // cache would fail
+    #[wasm_bindgen_test]
+    async fn dry_run_does_not_transfer() {
+        let mut entry = CacheEntry::new("ferrous-actions-dry-run-test");
+        entry.path(node::os::temp_dir().join("ferrous-actions-dry-run-test"));
+        let backend = DryRun;
+        assert_eq!(backend.restore(&entry).await.expect("Restore failed"), None);
+        assert_eq!(backend.save(&entry).await.expect("Save failed"), SaveOutcome::Skipped);
+        assert_eq!(
+            backend
+                .save_if_update(&entry, Some("old-key"))
+                .await
+                .expect("Save failed"),
+            SaveOutcome::Skipped
+        );
+    }
+}
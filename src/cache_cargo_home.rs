@@ -2,27 +2,28 @@ use crate::action_paths::get_action_cache_dir;
 use crate::actions::cache::Entry as CacheEntry;
 use crate::actions::core;
 use crate::agnostic_path::AgnosticPath;
+use crate::cache_backend::{self, Backend as CacheBackend, SaveOutcome};
 use crate::delta::{render_list as render_delta_list, Action as DeltaAction};
 use crate::dir_tree::match_relative_paths;
+use crate::error::Context as _;
 use crate::fingerprinting::{fingerprint_path_with_ignores, Fingerprint, Ignores};
 use crate::hasher::Blake3 as Blake3Hasher;
 use crate::input_manager::{self, Input};
 use crate::job::Job;
 use crate::node::os::homedir;
 use crate::node::path::Path;
-use crate::{actions, error, info, node, notice, safe_encoding, warning, Error};
+use crate::{actions, cache_target_dir, debug, error, info, node, notice, safe_encoding, warning, Error};
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use rustup_toolchain_manifest::HashValue;
 use serde::{Deserialize, Serialize};
 use simple_path_match::{PathMatch, PathMatchBuilder};
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::hash::Hash as _;
 use std::str::FromStr;
 use strum::{Display, EnumIter, EnumString, IntoEnumIterator, IntoStaticStr};
 
-const ATIMES_SUPPORTED_KEY: &str = "ACCESS_TIMES_SUPPORTED";
 const DEFAULT_CROSS_OS_SHARING: CrossPlatformSharing = CrossPlatformSharing::All;
 const SCOPE_HASH_KEY: &str = "SCOPE_HASH";
 
@@ -68,6 +69,9 @@ impl CrossPlatformSharing {
 struct Group {
     restore_key: Option<String>,
     entries: BTreeMap<AgnosticPath, Fingerprint>,
+    /// Names of the checkouts made from this group. Only tracked for Git
+    /// repositories.
+    revisions: BTreeSet<String>,
 }
 
 impl Group {
@@ -78,6 +82,60 @@ impl Group {
     pub fn last_modified(&self) -> Option<DateTime<Utc>> {
         self.entries.values().filter_map(Fingerprint::modified).max()
     }
+
+    fn recache_decision(
+        &self,
+        cache_type: CacheType,
+        old: &Group,
+        now: DateTime<Utc>,
+        min_recache_interval: &chrono::Duration,
+    ) -> RecacheDecision {
+        if Cache::compare_groups(&old.entries, &self.entries).is_empty() {
+            return RecacheDecision::Unchanged;
+        }
+        // A fetch will modify a Git repository even if nothing new was checked out, so
+        // only a previously unseen revision is treated as a meaningful change
+        if cache_type.tracks_revisions() && self.revisions.is_subset(&old.revisions) {
+            return RecacheDecision::NoNewRevision;
+        }
+        // The modification time is dubious because we cannot track when file deletions
+        // occur and modifications times could be preserved from some sort of archive.
+        // It should work fine for changes to Git repos however, which are our main
+        // concern.
+        let old_modification = old.last_modified().unwrap_or_default();
+        // Be robust against our delta being negative.
+        let modification_delta = std::cmp::max(chrono::Duration::zero(), now - old_modification);
+        if modification_delta > *min_recache_interval {
+            RecacheDecision::Changed
+        } else {
+            RecacheDecision::TooRecent(modification_delta)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RecacheDecision {
+    /// The group did not previously exist in the cache
+    New,
+
+    /// The group's content is unchanged
+    Unchanged,
+
+    /// The group's content changed, but no new revision was checked out from it
+    NoNewRevision,
+
+    /// The group's content changed, but its previous content is newer than the
+    /// minimum recache interval
+    TooRecent(chrono::Duration),
+
+    /// The group's content changed and should be saved
+    Changed,
+}
+
+impl RecacheDecision {
+    fn should_save(self) -> bool {
+        matches!(self, RecacheDecision::New | RecacheDecision::Changed)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -94,15 +152,24 @@ struct GroupIdentifier {
     entries_hash: HashValue,
 }
 
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+struct DependencyList {
+    groups: Vec<GroupIdentifier>,
+    revisions: BTreeMap<AgnosticPath, BTreeSet<String>>,
+}
+
 impl Cache {
     pub async fn new(cache_type: CacheType) -> Result<Cache, Error> {
         let sources = HashMap::new();
-        Self::new_with_sources(cache_type, sources).await
+        // Checkouts are deleted when the cache is built, so must be found first
+        let revisions = find_checkout_revisions(cache_type).await?;
+        Self::new_with_sources(cache_type, sources, revisions).await
     }
 
     async fn new_with_sources(
         cache_type: CacheType,
         mut sources: HashMap<AgnosticPath, String>,
+        mut revisions: BTreeMap<AgnosticPath, BTreeSet<String>>,
     ) -> Result<Cache, Error> {
         // Delete derived content at any paths we want to build the cache at
         for delete_path in find_additional_delete_paths(cache_type).await? {
@@ -119,22 +186,33 @@ impl Cache {
         );
         let top_depth_glob = depth_to_match(grouping_depth)?;
         let folder_path = find_path(cache_type);
-        let top_depth_paths = match_relative_paths(&folder_path, &top_depth_glob, true).await?;
+        let top_depth_paths = match_relative_paths(&folder_path, &top_depth_glob, None, true).await?;
         let entry_depth_relative = entry_depth - grouping_depth;
         let mut map = BTreeMap::new();
         for group in top_depth_paths {
             let group_path = folder_path.join(&group);
+            let group = AgnosticPath::from(&group);
             map.insert(
-                AgnosticPath::from(&group),
+                group.clone(),
                 Group {
-                    restore_key: sources.remove(&AgnosticPath::from(&group)),
+                    restore_key: sources.remove(&group),
                     entries: Self::build_group(cache_type, &group_path, entry_depth_relative).await?,
+                    revisions: revisions.remove(&group).unwrap_or_default(),
                 },
             );
         }
         if !sources.is_empty() {
             error!("One or more restored cache keys did not map to a path: {:#?}", sources);
         }
+        let memory = node::process::memory_usage();
+        debug!(
+            "Memory usage after walking {} cache: RSS {} bytes, heap {}/{} bytes, external {} bytes",
+            cache_type.friendly_name(),
+            memory.rss,
+            memory.heap_used,
+            memory.heap_total,
+            memory.external
+        );
         Ok(Cache {
             cache_type,
             root: map,
@@ -158,11 +236,13 @@ impl Cache {
     }
 
     pub async fn restore_from_env(
+        backend: &dyn CacheBackend,
         cache_type: CacheType,
         scope: &HashValue,
         cross_platform_sharing: CrossPlatformSharing,
+        atime_offset: chrono::Duration,
     ) -> Result<Cache, Error> {
-        use crate::access_times::revert_folder;
+        use crate::access_times::revert_folder_with_offset;
         use itertools::Itertools as _;
 
         let job = Job::from_env()?;
@@ -181,8 +261,9 @@ impl Cache {
         }
 
         let entry = build_cache_entry_dependencies(cache_type, scope, &job)?;
-        let restore_key = entry.restore().await.map_err(Error::Js)?;
+        let restore_key = backend.restore(&entry).await?;
         let mut restore_keys = HashMap::new();
+        let mut revisions = BTreeMap::new();
         if let Some(restore_key) = restore_key {
             info!(
                 "Located dependencies list for {} in cache using key {}.",
@@ -190,19 +271,19 @@ impl Cache {
                 restore_key
             );
             let dep_file_path = dependency_file_path(cache_type, scope, &job)?;
-            let groups: Vec<GroupIdentifier> = {
+            let dependencies: DependencyList = {
                 let file_contents = node::fs::read_file(&dep_file_path).await?;
                 postcard::from_bytes(&file_contents)?
             };
-            let group_list_string = groups.iter().map(|g| &g.path).join(", ");
+            let group_list_string = dependencies.groups.iter().map(|g| &g.path).join(", ");
             info!(
                 "The following groups will be restored for cache type {}: {}",
                 cache_type.friendly_name(),
                 group_list_string
             );
-            for group in &groups {
+            for group in &dependencies.groups {
                 let entry = Self::group_identifier_to_cache_entry(cache_type, group, cross_platform_sharing);
-                if let Some(name) = entry.restore().await? {
+                if let Some(name) = backend.restore(&entry).await? {
                     info!("Restored cache key: {}", name);
                     restore_keys.insert(group.path.clone(), name);
                 } else {
@@ -213,89 +294,73 @@ impl Cache {
                     );
                 }
             }
+            revisions = dependencies.revisions;
         } else {
             info!("No existing dependency list for {} found.", cache_type.friendly_name());
         }
         // Ensure we at least have an empty folder
         node::fs::create_dir_all(&folder_path).await?;
         // Revert access times
-        revert_folder(&folder_path).await?;
-        Self::new_with_sources(cache_type, restore_keys).await
+        revert_folder_with_offset(&folder_path, atime_offset).await?;
+        Self::new_with_sources(cache_type, restore_keys, revisions).await
     }
 
     pub async fn save_changes(
         &self,
+        backend: &dyn CacheBackend,
         old: &Cache,
         scope_hash: &HashValue,
         min_recache_interval: &chrono::Duration,
         cross_platform_sharing: CrossPlatformSharing,
     ) -> Result<(), Error> {
         let job = Job::from_env()?;
+        let now = chrono::Utc::now();
+        let decisions: BTreeMap<&AgnosticPath, RecacheDecision> = self
+            .root
+            .iter()
+            .map(|(path, group)| {
+                let decision = old.root.get(path).map_or(RecacheDecision::New, |old_group| {
+                    group.recache_decision(self.cache_type, old_group, now, min_recache_interval)
+                });
+                (path, decision)
+            })
+            .collect();
+
         let dep_file_path = dependency_file_path(self.cache_type, scope_hash, &job)?;
-        let old_groups = if dep_file_path.exists().await {
+        let old_dependencies: DependencyList = if dep_file_path.exists().await {
             let file_contents = node::fs::read_file(&dep_file_path).await?;
             postcard::from_bytes(&file_contents)?
         } else {
-            Vec::new()
+            DependencyList::default()
         };
-        let new_groups = self.group_identifiers();
-        let group_list_delta = Self::compare_group_lists(&old_groups, &new_groups);
-        if group_list_delta.is_empty() {
+        let new_dependencies = DependencyList {
+            groups: self.group_identifiers(),
+            revisions: self.recorded_revisions(old, &decisions),
+        };
+        let group_list_delta = Self::compare_group_lists(&old_dependencies.groups, &new_dependencies.groups);
+        if group_list_delta.is_empty() && old_dependencies.revisions == new_dependencies.revisions {
             info!("{} dependency list is unchanged.", self.cache_type.friendly_name());
         } else {
             info!("{} dependency list changed:", self.cache_type.friendly_name());
-            info!("{}", render_delta_list(&group_list_delta));
-            let serialized_groups = postcard::to_stdvec(&new_groups)?;
-            {
-                let parent = dep_file_path.parent();
+            if !group_list_delta.is_empty() {
+                info!("{}", render_delta_list(&group_list_delta));
+            }
+            let serialized_dependencies = postcard::to_stdvec(&new_dependencies)?;
+            if let Some(parent) = dep_file_path.parent() {
                 node::fs::create_dir_all(&parent).await?;
             }
-            node::fs::write_file(&dep_file_path, &serialized_groups).await?;
+            node::fs::write_file(&dep_file_path, &serialized_dependencies).await?;
             let dependencies_entry = build_cache_entry_dependencies(self.cache_type, scope_hash, &job)?;
-            dependencies_entry.save().await?;
-            info!("{} dependency list was successfully saved.", self.cache_type);
+            if backend.save(&dependencies_entry).await? == SaveOutcome::Saved {
+                info!("{} dependency list was successfully saved.", self.cache_type);
+            }
         }
 
-        for (path, group) in &self.root {
-            let (attempt_save, old_restore_key) = if let Some(old_group) = old.root.get(path) {
-                let group_delta = Self::compare_groups(&old_group.entries, &group.entries);
-                let attempt_save = if group_delta.is_empty() {
-                    // The group's content is unchanged
-                    false
-                } else {
-                    // The modification time is dubious because we cannot track when file deletions
-                    // occur and modifications times could be preserved from some sort of archive.
-                    // It should work fine for changes to Git repos however, which are our main
-                    // concern.
-                    let old_modification = old_group.last_modified().unwrap_or_default();
-                    // Be robust against our delta being negative.
-                    let modification_delta = chrono::Utc::now() - old_modification;
-                    let modification_delta = std::cmp::max(chrono::Duration::zero(), modification_delta);
-
-                    let interval_is_sufficient = modification_delta > *min_recache_interval;
-                    if interval_is_sufficient {
-                        info!("Cached {} group {} has changed:", self.cache_type.friendly_name(), path);
-                        info!("{}", render_delta_list(&group_delta));
-                        true
-                    } else {
-                        use humantime::format_duration;
-                        info!(
-                            "Cached {} group {} outdated by {}, but not updating cache since minimum recache interval is {}.",
-                            self.cache_type,
-                            path,
-                            format_duration(modification_delta.to_std()?),
-                            format_duration(min_recache_interval.to_std()?),
-                        );
-                        false
-                    }
-                };
-                (attempt_save, old_group.restore_key.as_deref())
-            } else {
-                // The group did not previously exist in the cache
-                (true, None)
-            };
-
-            if attempt_save {
+        for (path, decision) in decisions {
+            let old_group = old.root.get(path);
+            self.log_recache_decision(path, old_group, decision, min_recache_interval)?;
+            if decision.should_save() {
+                let old_restore_key = old_group.and_then(|g| g.restore_key.as_deref());
                 let identifier = self.build_group_identifier(path);
                 let entry = Self::group_identifier_to_cache_entry(self.cache_type, &identifier, cross_platform_sharing);
                 info!(
@@ -303,27 +368,86 @@ impl Cache {
                     self.cache_type.friendly_name(),
                     path
                 );
-                if entry.save_if_update(old_restore_key).await?.is_some() {
-                    info!(
-                        "{} cache group {} saved successfully.",
-                        self.cache_type.friendly_name(),
-                        path
-                    );
-                } else {
-                    info!(
-                        concat!(
-                            "It looks like the changed {} cache group {} already exists. ",
-                            "Not saving our version this time around because we can't be certain it's a useful update. "
-                        ),
-                        self.cache_type.friendly_name(),
-                        path
-                    );
+                match backend.save_if_update(&entry, old_restore_key).await? {
+                    SaveOutcome::Saved => {
+                        info!(
+                            "{} cache group {} saved successfully.",
+                            self.cache_type.friendly_name(),
+                            path
+                        );
+                    }
+                    SaveOutcome::AlreadyExists => {
+                        info!(
+                            concat!(
+                                "It looks like the changed {} cache group {} already exists. ",
+                                "Not saving our version this time around because we can't be certain it's a useful update. "
+                            ),
+                            self.cache_type.friendly_name(),
+                            path
+                        );
+                    }
+                    SaveOutcome::Skipped => {}
                 }
             }
         }
         Ok(())
     }
 
+    fn log_recache_decision(
+        &self,
+        path: &AgnosticPath,
+        old_group: Option<&Group>,
+        decision: RecacheDecision,
+        min_recache_interval: &chrono::Duration,
+    ) -> Result<(), Error> {
+        match decision {
+            RecacheDecision::New | RecacheDecision::Unchanged => {}
+            RecacheDecision::Changed => {
+                let old_group = old_group.expect("Changed group should have previously existed");
+                let group_delta = Self::compare_groups(&old_group.entries, &self.root[path].entries);
+                info!("Cached {} group {} has changed:", self.cache_type.friendly_name(), path);
+                info!("{}", render_delta_list(&group_delta));
+            }
+            RecacheDecision::NoNewRevision => {
+                info!(
+                    "Cached {} group {} changed, but not updating cache since no new revisions were checked out.",
+                    self.cache_type, path,
+                );
+            }
+            RecacheDecision::TooRecent(modification_delta) => {
+                use humantime::format_duration;
+                info!(
+                    "Cached {} group {} outdated by {}, but not updating cache since minimum recache interval is {}.",
+                    self.cache_type,
+                    path,
+                    format_duration(modification_delta.to_std()?),
+                    format_duration(min_recache_interval.to_std()?),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Determines the revisions to record in the dependency list. Groups we
+    /// declined to save because they changed too recently keep their old
+    /// revisions so that new revisions are still detected on a later run.
+    fn recorded_revisions(
+        &self,
+        old: &Cache,
+        decisions: &BTreeMap<&AgnosticPath, RecacheDecision>,
+    ) -> BTreeMap<AgnosticPath, BTreeSet<String>> {
+        self.root
+            .iter()
+            .filter_map(|(path, group)| {
+                let revisions = match (decisions.get(path), old.root.get(path)) {
+                    (Some(RecacheDecision::TooRecent(_)), Some(old_group)) => &old_group.revisions,
+                    _ => &group.revisions,
+                };
+                (!revisions.is_empty()).then(|| (path.clone(), revisions.clone()))
+            })
+            .collect()
+    }
+
     async fn build_entry(cache_type: CacheType, entry_path: &Path) -> Result<Fingerprint, Error> {
         let ignores = cache_type.ignores();
         fingerprint_path_with_ignores(entry_path, &ignores).await
@@ -335,7 +459,7 @@ impl Cache {
         entry_level: usize,
     ) -> Result<BTreeMap<AgnosticPath, Fingerprint>, Error> {
         let entry_level_glob = depth_to_match(entry_level)?;
-        let entry_level_paths = match_relative_paths(group_path, &entry_level_glob, true).await?;
+        let entry_level_paths = match_relative_paths(group_path, &entry_level_glob, None, true).await?;
         let mut map = BTreeMap::new();
         for path in entry_level_paths {
             let entry_path = group_path.join(&path);
@@ -428,7 +552,10 @@ impl Cache {
         left: &BTreeMap<AgnosticPath, Fingerprint>,
         right: &mut BTreeMap<AgnosticPath, Fingerprint>,
         right_path: &Path,
+        entries_are_files: bool,
     ) -> Result<(), Error> {
+        use node::fs::RmOptions;
+
         use itertools::{EitherOrBoth, Itertools as _};
         let from_iter = left.iter();
         let to_iter = right.iter();
@@ -443,7 +570,12 @@ impl Cache {
         for element_path in to_prune {
             let path = right_path.join(element_path);
             info!("Pruning unused cache element at {}", path);
-            actions::io::rm_rf(&path).await?;
+            // Refuse to delete a folder where a file is expected
+            let options = RmOptions {
+                recursive: !entries_are_files,
+                force: true,
+            };
+            node::fs::rm(&path, options).await?;
             right.remove(element_path);
         }
         Ok(())
@@ -460,7 +592,13 @@ impl Cache {
                 EitherOrBoth::Left(_) | EitherOrBoth::Right(_) => {}
                 EitherOrBoth::Both(left, right) => {
                     let entry_path = root_path.join(right.0);
-                    Self::prune_unused_entries(&left.1.entries, &mut right.1.entries, &entry_path).await?;
+                    Self::prune_unused_entries(
+                        &left.1.entries,
+                        &mut right.1.entries,
+                        &entry_path,
+                        self.cache_type.entries_are_files(),
+                    )
+                    .await?;
                 }
             }
         }
@@ -479,7 +617,7 @@ impl Cache {
     }
 }
 
-fn find_cargo_home() -> Path {
+pub fn find_cargo_home() -> Path {
     Path::from(CARGO_HOME.as_str())
 }
 
@@ -504,13 +642,41 @@ async fn find_additional_delete_paths(cache_type: CacheType) -> Result<Vec<Path>
     let path_matcher = path_match_builder.build()?;
     let home_path = find_cargo_home();
     let result = if home_path.exists().await {
-        match_relative_paths(&home_path, &path_matcher, false).await?
+        match_relative_paths(&home_path, &path_matcher, None, false).await?
     } else {
         Vec::new()
     };
     Ok(result)
 }
 
+async fn find_checkout_revisions(cache_type: CacheType) -> Result<BTreeMap<AgnosticPath, BTreeSet<String>>, Error> {
+    if cache_type.tracks_revisions() {
+        let checkouts_path = find_cargo_home().join("git").join("checkouts");
+        list_checkout_revisions(&checkouts_path).await
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Maps each repository in a Cargo Git checkouts folder to the names of the
+/// revisions checked out from it
+async fn list_checkout_revisions(checkouts_path: &Path) -> Result<BTreeMap<AgnosticPath, BTreeSet<String>>, Error> {
+    let mut result = BTreeMap::new();
+    if !checkouts_path.exists().await {
+        return Ok(result);
+    }
+    let revision_glob = depth_to_match(2)?;
+    for revision_path in match_relative_paths(checkouts_path, &revision_glob, None, true).await? {
+        if let Some(repo_path) = revision_path.parent() {
+            result
+                .entry(AgnosticPath::from(&repo_path))
+                .or_insert_with(BTreeSet::new)
+                .insert(revision_path.file_name());
+        }
+    }
+    Ok(result)
+}
+
 fn cached_folder_info_path(cache_type: CacheType) -> Result<Path, Error> {
     let file_name = format!("{}.postcard", cache_type.short_name());
     Ok(get_action_cache_dir()?.join("cached-folder-info").join(&file_name))
@@ -568,6 +734,9 @@ impl CacheType {
                 match_builder.add_pattern("registry/index/*/.cache")?;
             }
             CacheType::Crates => {
+                // Unpacked sources are never archived, so there is no need to prune
+                // stale entries individually. Unused crate files are pruned using
+                // access times instead.
                 match_builder.add_pattern("registry/src")?;
             }
             CacheType::GitRepos => {
@@ -604,6 +773,17 @@ impl CacheType {
         }
     }
 
+    fn entries_are_files(self) -> bool {
+        // Individual crate files are cached rather than folders
+        matches!(self, CacheType::Crates)
+    }
+
+    fn tracks_revisions(self) -> bool {
+        // Checkout names are the only way to tell whether a fetch into a Git
+        // repository brought in anything we needed
+        matches!(self, CacheType::GitRepos)
+    }
+
     fn default_min_recache_interval(self) -> chrono::Duration {
         match self {
             CacheType::Indices => chrono::Duration::days(2),
@@ -628,6 +808,21 @@ fn get_cross_platform_sharing(input_manager: &input_manager::Manager) -> Result<
     })
 }
 
+fn get_atime_offset(input_manager: &input_manager::Manager) -> Result<chrono::Duration, Error> {
+    use crate::access_times::default_access_time_offset;
+
+    let Some(hours) = input_manager.get_u64(Input::AtimeOffsetHours)? else {
+        return Ok(default_access_time_offset());
+    };
+    match i64::try_from(hours) {
+        Ok(hours) if hours > 0 => Ok(chrono::Duration::hours(hours)),
+        _ => {
+            let input_name: &str = Input::AtimeOffsetHours.into();
+            Err(Error::OptionParseError(input_name.into(), hours.to_string()))
+        }
+    }
+}
+
 fn get_types_to_cache(input_manager: &input_manager::Manager) -> Result<Vec<CacheType>, Error> {
     let mut result = HashSet::new();
     if let Some(types) = input_manager.get(Input::CacheOnly) {
@@ -647,13 +842,8 @@ fn get_min_recache_interval(
     input_manager: &input_manager::Manager,
     cache_type: CacheType,
 ) -> Result<chrono::Duration, Error> {
-    let result = if let Some(duration) = input_manager.get(cache_type.min_recache_input()) {
-        let duration = humantime::parse_duration(duration)?;
-        chrono::Duration::from_std(duration)?
-    } else {
-        cache_type.default_min_recache_interval()
-    };
-    Ok(result)
+    let result = input_manager.get_duration(cache_type.min_recache_input())?;
+    Ok(result.unwrap_or_else(|| cache_type.default_min_recache_interval()))
 }
 
 fn dependency_file_path(cache_type: CacheType, scope: &HashValue, job: &Job) -> Result<Path, Error> {
@@ -683,9 +873,81 @@ fn build_cache_entry_dependencies(cache_type: CacheType, scope: &HashValue, job:
     Ok(cache_entry)
 }
 
+async fn compute_scope_hash(atimes_supported: bool) -> Result<HashValue, Error> {
+    use crate::cargo_lock_hashing::hash_cargo_lock_files;
+
+    Ok(if atimes_supported {
+        // We can't use the empty array because it will encode to an empty string, which
+        // doesn't play well with `save_state`.
+        HashValue::from_bytes(&[42u8])
+    } else {
+        let cwd = node::process::cwd();
+        let lock_hash = hash_cargo_lock_files(&cwd).await?;
+        HashValue::from_bytes(&lock_hash.bytes)
+    })
+}
+
+/// Cache entries whose keys cannot be computed by `compute_cache_keys`, and
+/// why
+const UNCOMPUTED_CACHE_KEYS: [(&str, &str); 2] = [
+    (
+        "toolchain packages",
+        "keyed on packages from the release manifest, which is only downloaded by install-toolchain",
+    ),
+    (
+        "cargo install build artifacts",
+        "keyed on the arguments passed to cargo install",
+    ),
+];
+
+/// Computes the primary keys of the entries looked up first by the restore
+/// step, without touching the cache or the cached folders. The keys of the
+/// Cargo home content entries depend on the dependency lists found in the
+/// cache, so are not included. Other entries whose keys cannot be computed
+/// here map to `None`, so that they are still listed.
+async fn compute_cache_keys(input_manager: &input_manager::Manager) -> Result<BTreeMap<String, Option<String>>, Error> {
+    let atimes_supported = crate::access_times::supports_atime().await?;
+    let scope_hash = compute_scope_hash(atimes_supported).await?;
+    let job = Job::from_env()?;
+    let mut keys = BTreeMap::new();
+    for cache_type in get_types_to_cache(input_manager)? {
+        let entry = build_cache_entry_dependencies(cache_type, &scope_hash, &job)?;
+        keys.insert(
+            format!("{} (dependency list)", cache_type.friendly_name()),
+            Some(entry.get_primary_key()),
+        );
+    }
+    if let Some(target_dir) = cache_target_dir::get_target_dir(input_manager) {
+        let entry = cache_target_dir::build_cache_entry(&target_dir).await?;
+        keys.insert("target directory".into(), Some(entry.get_primary_key()));
+    }
+    for (name, _) in UNCOMPUTED_CACHE_KEYS {
+        keys.insert(name.into(), None);
+    }
+    Ok(keys)
+}
+
+/// Logs the keys computed by `compute_cache_keys` and publishes them as a JSON
+/// object in the `cache-keys` step output
+pub async fn report_cache_keys(input_manager: &input_manager::Manager) -> Result<(), Error> {
+    let keys = compute_cache_keys(input_manager).await?;
+    for (name, key) in &keys {
+        if let Some(key) = key {
+            info!("{}: {}", name, key);
+        } else {
+            let reason = UNCOMPUTED_CACHE_KEYS
+                .iter()
+                .find_map(|(uncomputed, reason)| (uncomputed == name).then_some(*reason))
+                .unwrap_or("unknown");
+            info!("{}: not computed ({})", name, reason);
+        }
+    }
+    core::set_output("cache-keys", serde_json::to_string(&keys)?);
+    Ok(())
+}
+
 pub async fn restore_cargo_cache(input_manager: &input_manager::Manager) -> Result<(), Error> {
     use crate::access_times::supports_atime;
-    use crate::cargo_lock_hashing::hash_cargo_lock_files;
 
     info!("Checking to see if filesystem supports access times...");
     let atimes_supported = supports_atime().await?;
@@ -703,38 +965,44 @@ pub async fn restore_cargo_cache(input_manager: &input_manager::Manager) -> Resu
             "Note that enabling file access times on Windows is generally a bad idea since Microsoft never implemented relatime semantics.")
         );
     }
-    core::save_state(ATIMES_SUPPORTED_KEY, serde_json::to_string(&atimes_supported)?);
 
-    let scope_hash = if atimes_supported {
-        // We can't use the empty array because it will encode to an empty string, which
-        // doesn't play well with `save_state`.
-        HashValue::from_bytes(&[42u8])
-    } else {
-        let cwd = node::process::cwd();
-        let lock_hash = hash_cargo_lock_files(&cwd).await?;
-        HashValue::from_bytes(&lock_hash.bytes)
-    };
+    let scope_hash = compute_scope_hash(atimes_supported).await?;
     core::save_state(SCOPE_HASH_KEY, safe_encoding::encode(&scope_hash));
 
+    let cargo_home = find_cargo_home();
+    if cargo_home.exists().await && !node::fs::access(&cargo_home, node::fs::AccessMode::WRITE).await? {
+        warning!(
+            "Cargo home {} is not writable so cached items are unlikely to be restored",
+            cargo_home
+        );
+    }
+
+    let backend = cache_backend::Recording::new(cache_backend::from_input(input_manager)?);
     let cross_platform_sharing = get_cross_platform_sharing(input_manager)?;
+    let atime_offset = get_atime_offset(input_manager)?;
     let cached_types = get_types_to_cache(input_manager)?;
     for cache_type in cached_types {
-        core::start_group(cache_type.friendly_name().to_string());
+        let _group = core::group(cache_type.friendly_name().to_string());
         // Mark as used to avoid spurious warnings (we only use this when we save the
         // entries)
         let _ = get_min_recache_interval(input_manager, cache_type)?;
 
         // Build the cache
-        let cache = Cache::restore_from_env(cache_type, &scope_hash, cross_platform_sharing).await?;
+        let cache = Cache::restore_from_env(&backend, cache_type, &scope_hash, cross_platform_sharing, atime_offset)
+            .await
+            .context(&format!("Failed to restore {} cache", cache_type.friendly_name()))?;
         let serialized_cache = postcard::to_stdvec(&cache)?;
         let cached_info_path = cached_folder_info_path(cache_type)?;
-        {
-            let parent = cached_info_path.parent();
+        if let Some(parent) = cached_info_path.parent() {
             node::fs::create_dir_all(&parent).await?;
         }
-        node::fs::write_file(&cached_info_path, &serialized_cache).await?;
-        core::end_group();
+        node::fs::write_file_if_changed(&cached_info_path, &serialized_cache).await?;
     }
+
+    if let Some(target_dir) = cache_target_dir::get_target_dir(input_manager) {
+        core::group_scoped("target directory", cache_target_dir::restore(&backend, &target_dir)).await?;
+    }
+    backend.set_outputs();
     Ok(())
 }
 
@@ -743,20 +1011,13 @@ pub async fn save_cargo_cache(input_manager: &input_manager::Manager) -> Result<
     let scope_hash = safe_encoding::decode(&scope_hash).expect("Failed to decode scope ID hash");
     let scope_hash = HashValue::from_bytes(&scope_hash);
 
-    let atimes_supported = core::get_state(ATIMES_SUPPORTED_KEY).expect("Failed to find access times support flag");
-    let atimes_supported: bool = serde_json::de::from_str(&atimes_supported)?;
+    let atimes_supported = crate::access_times::supports_atime().await?;
 
+    let backend = cache_backend::from_input(input_manager)?;
     let cross_platform_sharing = get_cross_platform_sharing(input_manager)?;
     let cached_types = get_types_to_cache(input_manager)?;
     for cache_type in cached_types {
-        core::start_group(cache_type.friendly_name().to_string());
-        // Delete items that should never make it into the cache
-        for delete_path in find_additional_delete_paths(cache_type).await? {
-            if delete_path.exists().await {
-                info!("Pruning redundant cache element: {}", delete_path);
-                actions::io::rm_rf(&delete_path).await?;
-            }
-        }
+        let _group = core::group(cache_type.friendly_name().to_string());
 
         // Restore the old cache
         let cache_old: Cache = {
@@ -765,7 +1026,8 @@ pub async fn save_cargo_cache(input_manager: &input_manager::Manager) -> Result<
             postcard::from_bytes(&cache_serialized)?
         };
 
-        // Construct the new cache
+        // Construct the new cache. This also deletes items that should never make it
+        // into the cache.
         let mut cache = Cache::new(cache_type).await?;
 
         // Check the path to the cached items hasn't changed
@@ -787,9 +1049,120 @@ pub async fn save_cargo_cache(input_manager: &input_manager::Manager) -> Result<
         // Save groups to cache if they have changed
         let min_recache_interval = get_min_recache_interval(input_manager, cache_type)?;
         cache
-            .save_changes(&cache_old, &scope_hash, &min_recache_interval, cross_platform_sharing)
-            .await?;
-        core::end_group();
+            .save_changes(
+                backend.as_ref(),
+                &cache_old,
+                &scope_hash,
+                &min_recache_interval,
+                cross_platform_sharing,
+            )
+            .await
+            .context(&format!("Failed to save {} cache", cache_type.friendly_name()))?;
+    }
+
+    if let Some(target_dir) = cache_target_dir::get_target_dir(input_manager) {
+        core::group_scoped(
+            "target directory",
+            cache_target_dir::save(backend.as_ref(), &target_dir),
+        )
+        .await?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        compute_cache_keys, list_checkout_revisions, Cache, CacheType, Group, RecacheDecision, UNCOMPUTED_CACHE_KEYS,
+    };
+    use crate::actions::io;
+    use crate::agnostic_path::AgnosticPath;
+    use crate::node;
+    use crate::node::path::Path;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    async fn write_file(path: &Path, content: &str) -> Result<(), JsValue> {
+        node::fs::create_dir_all(&path.parent().expect("Path should have a parent")).await?;
+        node::fs::write_file(path, content.as_bytes()).await
+    }
+
+    async fn build_git_group(git_dir: &Path, repo: &str) -> Group {
+        let entries = Cache::build_group(CacheType::GitRepos, &git_dir.join("db").join(repo), 1)
+            .await
+            .expect("Failed to build group");
+        let mut revisions = list_checkout_revisions(&git_dir.join("checkouts"))
+            .await
+            .expect("Failed to list checkouts");
+        Group {
+            restore_key: None,
+            entries,
+            revisions: revisions
+                .remove(&AgnosticPath::from(&Path::from(repo)))
+                .unwrap_or_default(),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn cache_keys_computed_for_each_type() {
+        use crate::input_manager::Manager as InputManager;
+
+        let vars = [
+            ("INPUT_CACHE-ONLY", "crates indices"),
+            ("INPUT_INTERNAL-USE-GITHUB-WORKFLOW", "\"ci\""),
+            ("INPUT_INTERNAL-USE-GITHUB-JOB", "\"test\""),
+            ("INPUT_INTERNAL-USE-MATRIX", "null"),
+        ];
+        for (name, value) in vars {
+            node::process::set_var(name, value);
+        }
+        let input_manager = InputManager::build().expect("Failed to build input manager");
+        let keys = compute_cache_keys(&input_manager).await;
+        let keys_again = compute_cache_keys(&input_manager).await;
+        for (name, _) in vars {
+            node::process::remove_var(name);
+        }
+        let keys = keys.expect("Failed to compute cache keys");
+        // The keys are those a lookup uses, so must not vary between runs
+        assert_eq!(keys_again.expect("Failed to compute cache keys"), keys);
+        let (computed, uncomputed): (Vec<_>, Vec<_>) = keys.iter().partition(|(_, key)| key.is_some());
+        assert_eq!(computed.len(), 2);
+        assert!(computed.iter().all(|(name, _)| name.ends_with("(dependency list)")));
+        assert_eq!(uncomputed.len(), UNCOMPUTED_CACHE_KEYS.len());
+    }
+
+    #[wasm_bindgen_test]
+    async fn git_repos_recached_for_new_revisions() -> Result<(), JsValue> {
+        let git_dir = node::os::temp_dir()
+            .join(&format!("ferrous-actions-recache-test - {}", chrono::Local::now()))
+            .join("git");
+        let db_dir = git_dir.join("db").join("serde-0123456789abcdef");
+        let checkouts_dir = git_dir.join("checkouts").join("serde-0123456789abcdef");
+        write_file(&db_dir.join("HEAD"), "ref: refs/heads/master").await?;
+        write_file(&db_dir.join("objects").join("pack-1"), "first").await?;
+        write_file(&checkouts_dir.join("1a2b3c4").join(".cargo-ok"), "").await?;
+        let old = build_git_group(&git_dir, "serde-0123456789abcdef").await;
+
+        // A fetch which did not result in a new checkout
+        write_file(&db_dir.join("FETCH_HEAD"), "1a2b3c4").await?;
+        let fetched = build_git_group(&git_dir, "serde-0123456789abcdef").await;
+
+        // A fetch followed by a checkout of a new revision
+        write_file(&db_dir.join("objects").join("pack-2"), "second").await?;
+        write_file(&checkouts_dir.join("5d6e7f8").join(".cargo-ok"), "").await?;
+        let updated = build_git_group(&git_dir, "serde-0123456789abcdef").await;
+        assert_eq!(updated.revisions.len(), 2);
+
+        let interval = chrono::Duration::days(1);
+        let fresh = chrono::Utc::now();
+        let stale = fresh + chrono::Duration::days(2);
+        let decide = |new: &Group, now| new.recache_decision(CacheType::GitRepos, &old, now, &interval);
+        assert_eq!(decide(&old, stale), RecacheDecision::Unchanged);
+        assert_eq!(decide(&fetched, stale), RecacheDecision::NoNewRevision);
+        assert!(matches!(decide(&updated, fresh), RecacheDecision::TooRecent(_)));
+        assert_eq!(decide(&updated, stale), RecacheDecision::Changed);
+
+        io::rm_rf(&git_dir.parent().expect("Path should have a parent")).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use crate::actions::cache;
+use crate::input_manager::{self, Input};
+use crate::{info, Error};
+
+/// Deletes all cache entries of the current repository whose keys start with
+/// the prefix supplied via the `clean-key-prefix` input
+pub async fn clean(input_manager: &input_manager::Manager) -> Result<(), Error> {
+    let key_prefix = input_manager.get_required(Input::CleanKeyPrefix)?;
+    let token = input_manager.get_required(Input::GithubToken)?;
+    let confirmed = input_manager.get_bool(Input::ConfirmClean)?.unwrap_or(false);
+    let entries = cache::list_remote(token, key_prefix).await?;
+    if !confirmed {
+        info!(
+            "Found {} cache entries with key prefix \"{}\", but not deleting them since {} is not set to true",
+            entries.len(),
+            key_prefix,
+            Input::ConfirmClean
+        );
+        return Ok(());
+    }
+    for entry in &entries {
+        info!("Deleting cache entry {} ({} bytes)", entry.key, entry.size_in_bytes);
+        cache::delete_remote(token, entry.id).await?;
+    }
+    info!("Deleted {} cache entries", entries.len());
+    Ok(())
+}
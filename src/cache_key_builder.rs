@@ -1,14 +1,29 @@
 use crate::actions::cache::Entry as CacheEntry;
-use crate::hasher::Blake3 as Blake3Hasher;
-use crate::{node, safe_encoding};
+use crate::hasher::{hash_file, Algorithm, Hasher};
+use crate::node::path::Path;
+use crate::nonce::Nonce;
+use crate::{node, safe_encoding, Error};
+use parking_lot::Mutex;
 use std::collections::BTreeMap;
 
-const CACHE_ENTRY_VERSION: &str = "18";
+const CACHE_ENTRY_VERSION: &str = "19";
+const FALLBACK_SEPARATOR: char = '/';
+
+static USER_VERSION: Mutex<Option<String>> = parking_lot::const_mutex(None);
+
+/// Sets a user-supplied version which is folded into all subsequently built
+/// cache keys, allowing all caches to be invalidated at once. Empty versions
+/// are ignored so that keys match those built without a version.
+pub fn set_user_version(version: Option<&str>) {
+    *USER_VERSION.lock() = version.filter(|v| !v.is_empty()).map(String::from);
+}
 
 pub struct CacheKeyBuilder {
     name: String,
-    hasher: Blake3Hasher,
+    hasher: Hasher,
+    fallbacks: Vec<Hasher>,
     attributes: BTreeMap<&'static str, (String, bool)>,
+    cross_os_archive: bool,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, strum::Display, strum::IntoStaticStr, Ord, PartialEq, PartialOrd)]
@@ -49,38 +64,91 @@ pub enum Attribute {
     #[strum(serialize = "toolchain_version")]
     ToolchainVersion,
 
+    #[strum(serialize = "rustup_version")]
+    RustupVersion,
+
     #[strum(serialize = "entries_hash")]
     EntriesHash,
+
+    #[strum(serialize = "cross_os_archive")]
+    CrossOsArchive,
 }
 
 impl CacheKeyBuilder {
-    fn empty(name: &str) -> CacheKeyBuilder {
+    fn empty(name: &str, algorithm: Algorithm, user_version: Option<&str>) -> CacheKeyBuilder {
         let mut result = CacheKeyBuilder {
             name: name.into(),
-            hasher: Blake3Hasher::default(),
+            hasher: Hasher::new(algorithm),
+            fallbacks: Vec::new(),
             attributes: BTreeMap::new(),
+            cross_os_archive: false,
         };
         result.add_key_data(CACHE_ENTRY_VERSION);
+        if let Some(user_version) = user_version {
+            result.add_key_data(user_version);
+        }
         result
     }
 
     pub fn new(name: &str) -> CacheKeyBuilder {
-        use crate::nonce;
+        Self::with_algorithm(name, Algorithm::default())
+    }
 
-        let mut result = Self::empty(name);
+    /// Constructs a builder which hashes key data with the specified algorithm
+    pub fn with_algorithm(name: &str, algorithm: Algorithm) -> CacheKeyBuilder {
+        let user_version = USER_VERSION.lock().clone();
+        let mut result = Self::empty(name, algorithm, user_version.as_deref());
         result.set_key_attribute(Attribute::Platform, node::os::platform());
         let date = chrono::Local::now();
         result.set_attribute(Attribute::Timestamp, date.to_string());
-        let nonce = nonce::build(8);
-        let nonce = safe_encoding::encode(nonce);
-        result.set_attribute(Attribute::Nonce, nonce);
+        result.set_attribute_nonce();
+        // Since we have the "platform" attribute, turning this on makes no difference
+        // unless the user overrides it
+        result.permit_sharing_with_windows(true);
         result
     }
 
+    /// Sets whether the entry may be restored on Windows when saved on another
+    /// platform, and vice versa. The two settings produce incompatible
+    /// archives, so the setting is part of the key.
+    pub fn permit_sharing_with_windows(&mut self, allow: bool) {
+        self.cross_os_archive = allow;
+        self.set_key_attribute(Attribute::CrossOsArchive, allow.to_string());
+    }
+
+    fn set_attribute_nonce(&mut self) {
+        let nonce = Nonce::generate();
+        self.set_attribute(Attribute::Nonce, safe_encoding::encode(nonce.as_bytes()));
+    }
+
     pub fn add_key_data<T: std::hash::Hash + ?Sized>(&mut self, data: &T) {
         data.hash(&mut self.hasher);
     }
 
+    /// Adds the content of the file at the specified path to the key. A
+    /// missing file is also recorded, so creating the file changes the key.
+    pub async fn add_path_contents(&mut self, path: &Path) -> Result<(), Error> {
+        let content_hash = match node::fs::metadata(path).await {
+            Ok(metadata) if !metadata.is_directory() => Some(hash_file(path).await?),
+            Ok(_) => {
+                return Err(Error::Js(
+                    wasm_bindgen::JsError::new(&format!("{} is a directory", path)).into(),
+                ))
+            }
+            Err(e) if node::fs::is_not_found(&e) => None,
+            Err(e) => return Err(e.into()),
+        };
+        self.add_key_data(&content_hash.as_ref().map(AsRef::<[u8]>::as_ref));
+        Ok(())
+    }
+
+    /// Adds a fallback restore key derived from the key data supplied so far.
+    /// Fallbacks are tried from the most to the least recently added once
+    /// the full key fails to match. Key attributes apply to every fallback.
+    pub fn add_fallback(&mut self) {
+        self.fallbacks.push(self.hasher.clone());
+    }
+
     pub fn set_key_attribute(&mut self, key: Attribute, value: String) {
         self.attributes.insert(key.into(), (value, true));
     }
@@ -107,31 +175,181 @@ impl CacheKeyBuilder {
         save_key.replace(',', ";")
     }
 
-    fn build_restore_key(name: &str, mut hasher: Blake3Hasher, attributes: &BTreeMap<&str, (String, bool)>) -> String {
+    fn build_id(mut hasher: Hasher, attributes: &BTreeMap<&str, (String, bool)>) -> String {
         use std::hash::Hash as _;
 
-        let id = {
-            attributes
-                .iter()
-                .filter_map(|(k, v)| v.1.then_some((k, &v.0)))
-                .for_each(|v| v.hash(&mut hasher));
-            let id: [u8; 32] = hasher.inner().finalize().into();
-            let id = &id[..8];
-            safe_encoding::encode(id)
-        };
+        attributes
+            .iter()
+            .filter_map(|(k, v)| v.1.then_some((k, &v.0)))
+            .for_each(|v| v.hash(&mut hasher));
+        let id = hasher.hash_value();
+        let id = &id.as_ref()[..8];
+        safe_encoding::encode(id)
+    }
 
-        let restore_key = format!("Ferrous Actions: {} - id={}", name, id);
-        restore_key.replace(',', ";")
+    /// Builds the restore keys, most specific first. The ID of each fallback is
+    /// embedded in the keys of all more specific ones so that each fallback
+    /// key is a prefix of the keys it should match.
+    fn build_restore_keys(
+        name: &str,
+        hasher: Hasher,
+        fallbacks: Vec<Hasher>,
+        attributes: &BTreeMap<&str, (String, bool)>,
+    ) -> Vec<String> {
+        let ids: Vec<String> = fallbacks
+            .into_iter()
+            .chain(std::iter::once(hasher))
+            .map(|hasher| Self::build_id(hasher, attributes))
+            .collect();
+        (1..=ids.len())
+            .rev()
+            .map(|num_ids| {
+                let mut restore_key = format!(
+                    "Ferrous Actions: {} - id={}",
+                    name,
+                    ids[..num_ids].join(&FALLBACK_SEPARATOR.to_string())
+                );
+                if num_ids < ids.len() {
+                    // Prevents a fallback ID matching a longer ID it happens to be a prefix of
+                    restore_key.push(FALLBACK_SEPARATOR);
+                }
+                restore_key.replace(',', ";")
+            })
+            .collect()
     }
 
     pub fn into_entry(self) -> CacheEntry {
-        let restore_key = Self::build_restore_key(&self.name, self.hasher, &self.attributes);
-        let save_key = Self::restore_key_to_save_key(&restore_key, &self.attributes);
+        let restore_keys = Self::build_restore_keys(&self.name, self.hasher, self.fallbacks, &self.attributes);
+        let save_key = Self::restore_key_to_save_key(&restore_keys[0], &self.attributes);
         let mut result = CacheEntry::new(save_key.as_str());
-        result.restore_key(restore_key);
-        // Since we have the "platform" attribute, turning this on makes no difference
-        // unless the user overrides it
-        result.permit_sharing_with_windows(true);
+        result.restore_keys(restore_keys);
+        result.permit_sharing_with_windows(self.cross_os_archive);
         result
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Algorithm, Attribute, CacheKeyBuilder, CACHE_ENTRY_VERSION};
+    use crate::hasher::Hasher;
+    use crate::node;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn restore_key(rustup_version: Option<&str>) -> String {
+        let mut builder = CacheKeyBuilder::new("rustc");
+        builder.add_key_data("package-id");
+        if let Some(version) = rustup_version {
+            builder.set_key_attribute(Attribute::RustupVersion, version.into());
+        }
+        CacheKeyBuilder::build_restore_keys(&builder.name, builder.hasher, builder.fallbacks, &builder.attributes)
+            .remove(0)
+    }
+
+    #[wasm_bindgen_test]
+    fn user_version_changes_key() {
+        use std::hash::Hash as _;
+
+        let hash = |user_version| {
+            CacheKeyBuilder::empty("crate files", Algorithm::default(), user_version)
+                .hasher
+                .hash_value()
+        };
+        let mut unversioned = Hasher::new(Algorithm::default());
+        CACHE_ENTRY_VERSION.hash(&mut unversioned);
+        assert_eq!(hash(None), unversioned.hash_value());
+        assert_eq!(hash(Some("1")), hash(Some("1")));
+        assert_ne!(hash(Some("1")), hash(Some("2")));
+        assert_ne!(hash(Some("1")), hash(None));
+    }
+
+    #[wasm_bindgen_test]
+    fn fallback_restore_key_order() {
+        let mut builder = CacheKeyBuilder::new("target directory");
+        builder.add_fallback();
+        builder.add_key_data("toolchain");
+        builder.add_fallback();
+        builder.add_key_data("lockfile");
+        builder.add_fallback();
+        builder.add_key_data("sources");
+        let keys =
+            CacheKeyBuilder::build_restore_keys(&builder.name, builder.hasher, builder.fallbacks, &builder.attributes);
+        assert_eq!(keys.len(), 4);
+        let ids: Vec<_> = keys[0]
+            .strip_prefix("Ferrous Actions: target directory - id=")
+            .expect("Unexpected restore key format")
+            .split('/')
+            .collect();
+        assert_eq!(ids.len(), 4);
+        for (num_ids, key) in (1..=4).rev().zip(keys.iter()) {
+            let mut expected = format!("Ferrous Actions: target directory - id={}", ids[..num_ids].join("/"));
+            if num_ids < 4 {
+                expected.push('/');
+            }
+            assert_eq!(key, &expected);
+        }
+        // Fallbacks are ordered from most to least specific
+        for pair in keys.windows(2) {
+            assert!(pair[0].starts_with(&pair[1]));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn no_fallbacks_single_restore_key() {
+        let mut builder = CacheKeyBuilder::new("crate files");
+        builder.add_key_data("group");
+        let keys =
+            CacheKeyBuilder::build_restore_keys(&builder.name, builder.hasher, builder.fallbacks, &builder.attributes);
+        assert_eq!(keys.len(), 1);
+        assert!(!keys[0].contains('/'));
+    }
+
+    #[wasm_bindgen_test]
+    fn rustup_version_changes_key() {
+        assert_eq!(restore_key(Some("1.26.0")), restore_key(Some("1.26.0")));
+        assert_ne!(restore_key(Some("1.26.0")), restore_key(Some("1.27.0")));
+        assert_ne!(restore_key(Some("1.26.0")), restore_key(None));
+    }
+
+    #[wasm_bindgen_test]
+    async fn path_contents_change_key() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-path-key-test - {}", chrono::Local::now()));
+        let config = root.join_all([".cargo", "config.toml"]);
+        node::fs::create_dir_all(&root.join(".cargo")).await?;
+        node::fs::write_file(&config, b"[build]\n").await?;
+
+        let key = || async {
+            let mut builder = CacheKeyBuilder::new("config");
+            builder.add_path_contents(&config).await.expect("Failed to hash config");
+            builder.hasher.hash_value()
+        };
+        let original = key().await;
+        assert_eq!(key().await, original);
+        node::fs::write_file(&config, b"[build]\nrustflags = []\n").await?;
+        let modified = key().await;
+        assert_ne!(modified, original);
+        node::fs::remove_file(&config).await?;
+        assert_ne!(key().await, modified);
+
+        let mut builder = CacheKeyBuilder::new("config");
+        assert!(builder.add_path_contents(&root).await.is_err());
+
+        crate::actions::io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn cross_os_archive_changes_key() {
+        let keys = |allow| {
+            let mut builder = CacheKeyBuilder::new("crate files");
+            builder.add_key_data("group");
+            builder.add_fallback();
+            builder.permit_sharing_with_windows(allow);
+            CacheKeyBuilder::build_restore_keys(&builder.name, builder.hasher, builder.fallbacks, &builder.attributes)
+        };
+        assert_eq!(keys(true), keys(true));
+        for (permitted, forbidden) in keys(true).iter().zip(keys(false).iter()) {
+            assert_ne!(permitted, forbidden);
+        }
+    }
+}
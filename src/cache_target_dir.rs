@@ -0,0 +1,148 @@
+use crate::actions::cache::Entry as CacheEntry;
+use crate::cache_backend::{Backend as CacheBackend, SaveOutcome};
+use crate::cargo_lock_hashing::hash_cargo_lock_files;
+use crate::dir_tree::match_relative_paths;
+use crate::input_manager::{self, Input};
+use crate::node::path::Path;
+use crate::{actions, info, node, rustup, Cargo, Error};
+use simple_path_match::{PathMatch, PathMatchBuilder};
+
+const DEFAULT_TARGET_DIR: &str = "target";
+const TARGET_DIR_ENV_VAR: &str = "CARGO_TARGET_DIR";
+
+/// Returns the target directory to cache, if target directory caching was
+/// requested
+pub fn get_target_dir(input_manager: &input_manager::Manager) -> Option<Path> {
+    let value = input_manager.get(Input::CacheTargetDir)?;
+    let relative = match value.trim() {
+        "false" | "" => return None,
+        "true" => node::process::get_env()
+            .remove(TARGET_DIR_ENV_VAR)
+            .unwrap_or_else(|| DEFAULT_TARGET_DIR.into()),
+        path => path.into(),
+    };
+    Some(node::process::cwd().join(&relative))
+}
+
+fn incremental_dirs_matcher() -> Result<PathMatch, Error> {
+    // Incremental compilation state lives at either <profile>/incremental or
+    // <triple>/<profile>/incremental
+    let mut builder = PathMatchBuilder::new(&node::path::separator());
+    builder.add_pattern("*/incremental")?;
+    builder.add_pattern("*/*/incremental")?;
+    Ok(builder.build()?)
+}
+
+async fn find_incremental_dirs(target_dir: &Path) -> Result<Vec<Path>, Error> {
+    let matcher = incremental_dirs_matcher()?;
+    match_relative_paths(target_dir, &matcher, None, false).await
+}
+
+/// Builds the cache entry for the target directory, keyed on the toolchain
+/// version and the `Cargo.lock` files in the current directory
+pub async fn build_cache_entry(target_dir: &Path) -> Result<CacheEntry, Error> {
+    use crate::cache_key_builder::{Attribute, CacheKeyBuilder};
+
+    let cwd = node::process::cwd();
+    let toolchain_version = Cargo::from_environment()
+        .await?
+        .get_toolchain_version(None, None)
+        .await?;
+    let lock_hash = hash_cargo_lock_files(&cwd).await?;
+    let mut builder = CacheKeyBuilder::new("target directory");
+    builder.add_key_data(&toolchain_version.long());
+    // Configuration such as rustflags changes what gets built
+    builder
+        .add_path_contents(&cwd.join_all([".cargo", "config.toml"]))
+        .await?;
+    // Components such as rust-src can change what gets built
+    let added_components = rustup::added_components();
+    if !added_components.is_empty() {
+        builder.add_key_data(&added_components);
+    }
+    // Reusing build artifacts from before a lockfile change is better than
+    // rebuilding everything
+    builder.add_fallback();
+    builder.add_key_data(&lock_hash.bytes);
+    builder.set_attribute(Attribute::ToolchainVersion, toolchain_version.short().to_string());
+    builder.set_attribute(Attribute::Path, target_dir.relative_to(&cwd).to_string());
+    let mut entry = builder.into_entry();
+    entry.root(cwd).path(target_dir);
+    Ok(entry)
+}
+
+pub async fn restore(backend: &dyn CacheBackend, target_dir: &Path) -> Result<(), Error> {
+    let entry = build_cache_entry(target_dir).await?;
+    if let Some(key) = backend.restore(&entry).await? {
+        info!("Restored target directory from cache with key {}", key);
+    } else {
+        info!("No cached target directory found");
+    }
+    Ok(())
+}
+
+pub async fn save(backend: &dyn CacheBackend, target_dir: &Path) -> Result<(), Error> {
+    if !target_dir.exists().await {
+        info!(
+            "Target directory {} does not exist, so it will not be cached",
+            target_dir
+        );
+        return Ok(());
+    }
+    for path in find_incremental_dirs(target_dir).await? {
+        if backend.is_dry_run() {
+            info!("Dry run: skipping removal of incremental compilation data at {}", path);
+        } else {
+            info!("Removing incremental compilation data at {}", path);
+            actions::io::rm_rf(&path).await?;
+        }
+    }
+    // The key already depends on the toolchain and lock files so there is no
+    // point saving if an entry for it exists
+    let entry = build_cache_entry(target_dir).await?;
+    match backend.save_if_update(&entry, None).await? {
+        SaveOutcome::Saved => info!("Saved target directory to cache"),
+        SaveOutcome::AlreadyExists => {
+            info!("Target directory already cached for this toolchain and set of lock files");
+        }
+        SaveOutcome::Skipped => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::find_incremental_dirs;
+    use crate::actions::io;
+    use crate::node;
+    use crate::node::path::Path;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    async fn incremental_dirs_are_found() -> Result<(), JsValue> {
+        let target_dir = node::os::temp_dir().join(&format!("ferrous-actions-target-test - {}", chrono::Local::now()));
+        let incremental = [
+            Path::from("debug").join("incremental"),
+            Path::from("x86_64-unknown-linux-gnu")
+                .join("release")
+                .join("incremental"),
+        ];
+        let retained = [Path::from("debug").join("deps"), Path::from("release").join("build")];
+        for path in incremental.iter().chain(retained.iter()) {
+            node::fs::create_dir_all(&target_dir.join(path)).await?;
+        }
+        let mut found: Vec<_> = find_incremental_dirs(&target_dir)
+            .await
+            .expect("Failed to match paths")
+            .iter()
+            .map(|p| p.relative_to(&target_dir).to_string())
+            .collect();
+        found.sort();
+        let mut expected: Vec<_> = incremental.iter().map(Path::to_string).collect();
+        expected.sort();
+        assert_eq!(found, expected);
+        io::rm_rf(&target_dir).await?;
+        Ok(())
+    }
+}
@@ -1,17 +1,43 @@
 use crate::action_paths::get_action_cache_dir;
-use crate::actions::exec::Command;
-use crate::actions::io;
+use crate::actions::exec::{self, Command};
+use crate::actions::{core, io};
 use crate::cargo_hooks::{
-    Annotation as AnnotationHook, Composite as CompositeHook, Hook as CargoHook, Install as CargoInstallHook,
+    parse_color_mode, Annotation as AnnotationHook, Color as ColorHook, Composite as CompositeHook, Hook as CargoHook,
+    Install as CargoInstallHook, JobReport as JobReportHook, LockFlags as LockFlagsHook, RustFlags as RustFlagsHook,
+    Timing as TimingHook,
 };
+use crate::cross::{Cross, Environment as CrossEnvironment};
 use crate::input_manager::{self, Input};
 use crate::node::path::Path;
 use crate::node::process;
-use crate::{node, nonce, Error};
+use crate::nonce::Nonce;
+use crate::proxy::Proxy;
+use crate::{debug, info, node, warning, Error};
 use std::borrow::Cow;
+use std::time::Duration;
 
-async fn create_empty_dir() -> Result<Path, Error> {
-    let nonce = nonce::build(8);
+/// Builds the `+<toolchain>` argument used to override the toolchain
+fn toolchain_arg(toolchain: &str) -> Result<String, Error> {
+    if toolchain.is_empty() || toolchain.starts_with('+') || toolchain.contains(char::is_whitespace) {
+        return Err(Error::InvalidToolchainName(toolchain.into()));
+    }
+    Ok(format!("+{}", toolchain))
+}
+
+/// Returns the path of the first cargo proxy found in the supplied
+/// directories
+async fn find_rustup_proxy(dirs: &[Path]) -> Option<Path> {
+    for dir in dirs {
+        let path = dir.join("cargo").with_exe_suffix();
+        if path.exists().await {
+            return Some(path);
+        }
+    }
+    None
+}
+
+pub async fn create_empty_dir() -> Result<Path, Error> {
+    let nonce = Nonce::generate();
     let path = get_action_cache_dir()?
         .join("empty-directories")
         .join(&nonce.to_string());
@@ -19,6 +45,38 @@ async fn create_empty_dir() -> Result<Path, Error> {
     Ok(path)
 }
 
+/// Subcommands known to accept cargo's common options such as `--locked` and
+/// `--color`. Others, like `cargo fmt`, may reject them.
+const COMMON_OPTION_SUBCOMMANDS: [&str; 19] = [
+    "bench",
+    "build",
+    "check",
+    "clean",
+    "clippy",
+    "doc",
+    "fetch",
+    "fix",
+    "generate-lockfile",
+    "install",
+    "metadata",
+    "package",
+    "publish",
+    "run",
+    "rustc",
+    "rustdoc",
+    "test",
+    "tree",
+    "update",
+];
+
+fn accepts_common_options(subcommand: &str) -> bool {
+    COMMON_OPTION_SUBCOMMANDS.contains(&subcommand)
+}
+
+fn get_flag_input(input_manager: &input_manager::Manager, input: Input) -> Result<bool, Error> {
+    Ok(input_manager.get_bool(input)?.unwrap_or(false))
+}
+
 struct ChangeCwdHook {
     new_cwd: String,
 }
@@ -30,9 +88,48 @@ impl CargoHook for ChangeCwdHook {
     }
 }
 
+const RETRY_INITIAL_DELAY: Duration = Duration::from_secs(5);
+
+/// Step outputs and the fields of `rustc -vV` output they are taken from
+const VERSION_OUTPUTS: [(&str, &str); 3] = [
+    ("rustc-version", "release"),
+    ("rustc-commit-hash", "commit-hash"),
+    ("rustc-host", "host"),
+];
+
+/// Fragments of cargo and curl error messages which indicate that a failure
+/// was caused by the network
+const NETWORK_ERROR_MARKERS: [&str; 9] = [
+    "spurious network error",
+    "failed to download",
+    "failed to fetch",
+    "failed to get successful HTTP response",
+    "Could not resolve host",
+    "Couldn't resolve host",
+    "Connection reset",
+    "SSL connect error",
+    "Timeout was reached",
+];
+
+/// Decides whether a failed cargo invocation is worth retrying given its
+/// standard error output
+fn is_retryable_failure(subcommand: &str, stderr: &str) -> bool {
+    if !NETWORK_ERROR_MARKERS.iter().any(|marker| stderr.contains(marker)) {
+        return false;
+    }
+    match subcommand {
+        "fetch" | "generate-lockfile" | "update" => true,
+        // Other subcommands may download dependencies before building, but a
+        // failure to compile is never retried
+        _ => !stderr.contains("could not compile") && !stderr.contains("error[E"),
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Cargo {
     path: Path,
+    cross: bool,
+    report_jobs: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -48,14 +145,101 @@ impl ToolchainVersion {
     pub fn long(&self) -> Cow<str> {
         self.long.as_str().into()
     }
+
+    /// The value of a `name: value` line of the verbose version output
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.long
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .map(str::trim)
+    }
+
+    /// Publishes the release, commit hash and host as step outputs
+    pub fn set_outputs(&self) {
+        for (output, field) in VERSION_OUTPUTS {
+            if let Some(value) = self.field(field) {
+                core::set_output(output, value);
+            }
+        }
+    }
+}
+
+/// Runs `rustc -Vv` for the specified toolchain, or the default one
+async fn get_toolchain_version(toolchain: Option<&str>, cwd: Option<&Path>) -> Result<ToolchainVersion, Error> {
+    use crate::actions::exec::Stdio;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    let rustc_path = io::which("rustc", true).await.map_err(Error::Js)?;
+    let mut command = Command::from(&rustc_path);
+    let output: Arc<Mutex<String>> = Arc::default();
+    let output_captured = output.clone();
+    if let Some(toolchain) = toolchain {
+        command.arg(toolchain_arg(toolchain)?.as_str());
+    }
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    command.arg("-Vv");
+    command
+        .outline(move |line| {
+            let mut out = output_captured.lock();
+            *out += line;
+            *out += "\n";
+        })
+        .stdout(Stdio::null());
+    command.exec().await?;
+    let long = output.lock().trim().to_string();
+    Ok(ToolchainVersion { long })
+}
+
+/// Publishes the version of the compiler for the specified toolchain, or the
+/// default one, as step outputs
+pub async fn set_version_outputs(toolchain: Option<&str>) -> Result<(), Error> {
+    // A toolchain file in the working directory could otherwise select a
+    // different toolchain to the one installed
+    let empty_dir = create_empty_dir().await?;
+    let version = get_toolchain_version(toolchain, Some(&empty_dir)).await?;
+    info!("Installed compiler is {}", version.short());
+    version.set_outputs();
+    Ok(())
 }
 
 impl Cargo {
     pub async fn from_environment() -> Result<Cargo, Error> {
-        io::which("cargo", true)
-            .await
-            .map(|path| Cargo { path })
-            .map_err(Error::Js)
+        let path = io::which("cargo", true).await.map_err(Error::Js)?;
+        match io::which_all("cargo").await {
+            Ok(candidates) if candidates.len() > 1 => {
+                debug!(
+                    "Multiple cargo binaries found on path, using {}: {:?}",
+                    path, candidates
+                );
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Unable to list cargo binaries on path: {:?}", e),
+        }
+        Ok(Cargo {
+            path,
+            cross: false,
+            report_jobs: true,
+        })
+    }
+
+    /// Constructs a `Cargo` which invokes the rustup proxy for cargo directly,
+    /// rather than whichever cargo is first on the path
+    pub async fn from_rustup_proxy() -> Result<Cargo, Error> {
+        use crate::cache_cargo_home::find_cargo_home;
+
+        let candidates: Vec<_> = crate::rustup::proxy_dir()
+            .into_iter()
+            .chain(std::iter::once(find_cargo_home().join("bin")))
+            .collect();
+        match find_rustup_proxy(&candidates).await {
+            Some(path) => Self::from_path(&path).await,
+            None => Err(Error::PathDoesNotExist(
+                candidates.last().expect("Candidate list is never empty").to_string(),
+            )),
+        }
     }
 
     pub async fn from_path(path: &Path) -> Result<Cargo, Error> {
@@ -63,10 +247,33 @@ impl Cargo {
         if !full_path.exists().await {
             return Err(Error::PathDoesNotExist(full_path.to_string()));
         }
-        let result = Cargo { path: full_path };
+        let result = Cargo {
+            path: full_path,
+            cross: false,
+            report_jobs: true,
+        };
         Ok(result)
     }
 
+    /// Sets whether the crates built by subsequent invocations are listed in
+    /// the job summary. This should be disabled for invocations the user did
+    /// not ask for.
+    pub fn report_jobs(&mut self, enabled: bool) -> &mut Cargo {
+        self.report_jobs = enabled;
+        self
+    }
+
+    /// Constructs a `Cargo` which invokes cargo via cross
+    pub async fn from_cross(cross: &Cross) -> Result<Cargo, Error> {
+        let mut result = Self::from_path(&cross.get_path()).await?;
+        result.cross = true;
+        Ok(result)
+    }
+
+    pub fn get_path(&self) -> Path {
+        self.path.clone()
+    }
+
     pub async fn get_installed(&self) -> Result<Vec<String>, Error> {
         use parking_lot::Mutex;
         use std::sync::Arc;
@@ -102,15 +309,35 @@ impl Cargo {
         input_manager: &input_manager::Manager,
     ) -> Result<CompositeHook, Error> {
         let mut hooks = CompositeHook::default();
+        let locked = get_flag_input(input_manager, Input::Locked)?;
+        let frozen = get_flag_input(input_manager, Input::Frozen)?;
+        let offline = get_flag_input(input_manager, Input::Offline)?;
+        if accepts_common_options(subcommand) {
+            hooks.push(LockFlagsHook::new(locked, frozen, offline, args));
+        } else if locked || frozen || offline {
+            debug!(
+                "Not passing lock file flags to cargo {} since it may not accept them",
+                subcommand
+            );
+        }
+        if let Some(color) = input_manager.get(Input::CargoColor) {
+            let mode = parse_color_mode(color)?;
+            if accepts_common_options(subcommand) {
+                hooks.push(ColorHook::new(mode, args));
+            } else {
+                debug!("Not passing --color to cargo {} since it may not accept it", subcommand);
+            }
+        }
+        if let Some(rust_flags) = input_manager.get(Input::RustFlags) {
+            hooks.push(RustFlagsHook::new(rust_flags));
+        }
+        hooks.push(Proxy::from_input(input_manager));
+        if self.cross {
+            hooks.push(CrossEnvironment::from_input(input_manager)?);
+        }
         match subcommand {
             "build" | "check" | "clippy" => {
-                let enabled = if let Some(enabled) = input_manager.get(Input::Annotations) {
-                    enabled
-                        .parse::<bool>()
-                        .map_err(|_| Error::OptionParseError("annotations".into(), enabled.to_string()))?
-                } else {
-                    true
-                };
+                let enabled = input_manager.get_bool(Input::Annotations)?.unwrap_or(true);
                 if enabled {
                     hooks.push(AnnotationHook::new(subcommand));
                 }
@@ -129,39 +356,20 @@ impl Cargo {
             }
             _ => {}
         }
+        if self.report_jobs {
+            hooks.push(JobReportHook::default());
+        }
+        // Pushed last so that timing excludes the work of other hooks
+        hooks.push(TimingHook::new(subcommand));
         Ok(hooks)
     }
 
-    async fn get_toolchain_version(
+    pub async fn get_toolchain_version(
         &self,
         toolchain: Option<&str>,
         cwd: Option<&Path>,
     ) -> Result<ToolchainVersion, Error> {
-        use crate::actions::exec::Stdio;
-        use parking_lot::Mutex;
-        use std::sync::Arc;
-
-        let rustc_path = io::which("rustc", true).await.map_err(Error::Js)?;
-        let mut command = Command::from(&rustc_path);
-        let output: Arc<Mutex<String>> = Arc::default();
-        let output_captured = output.clone();
-        if let Some(toolchain) = toolchain {
-            command.arg(format!("+{}", toolchain).as_str());
-        }
-        if let Some(cwd) = cwd {
-            command.current_dir(cwd);
-        }
-        command.arg("-Vv");
-        command
-            .outline(move |line| {
-                let mut out = output_captured.lock();
-                *out += line;
-                *out += "\n";
-            })
-            .stdout(Stdio::null());
-        command.exec().await?;
-        let long = output.lock().trim().to_string();
-        Ok(ToolchainVersion { long })
+        get_toolchain_version(toolchain, cwd).await
     }
 
     pub async fn run<'a, I>(
@@ -175,20 +383,21 @@ impl Cargo {
         I: IntoIterator<Item = &'a str>,
     {
         let args: Vec<String> = args.into_iter().map(Into::into).collect();
-        let mut final_args = Vec::with_capacity(args.len());
-        if let Some(toolchain) = toolchain {
-            final_args.push(format!("+{}", toolchain));
-        }
+        let toolchain_arg = toolchain.map(toolchain_arg).transpose()?;
         let mut hooks = self
             .get_hooks_for_subcommand(toolchain, subcommand, &args[..], input_manager)
             .await?;
-        final_args.push(subcommand.into());
-        final_args.extend(hooks.additional_cargo_options().into_iter().map(Cow::into_owned));
-        final_args.extend(args);
+        let options = hooks.additional_cargo_options().into_iter().map(Cow::into_owned);
+        let final_args = self.build_args(toolchain_arg, subcommand, options.chain(args));
         let mut command = Command::from(&self.path);
         command.args(final_args);
         hooks.modify_command(&mut command);
-        if let Err(e) = command.exec().await.map_err(Error::Js) {
+        if let Some(timeout) = input_manager.get_duration(Input::CargoTimeout)? {
+            // Negative durations are rejected when parsing
+            command.timeout(timeout.to_std().unwrap_or_default());
+        }
+        let retries = input_manager.get_u64(Input::CargoRetries)?.unwrap_or(0);
+        if let Err(e) = Self::exec_with_retries(&mut command, &hooks, subcommand, retries).await {
             hooks.failed().await;
             Err(e)
         } else {
@@ -196,4 +405,218 @@ impl Cargo {
             Ok(())
         }
     }
+
+    /// Arranges the arguments for an invocation. Cargo expects the toolchain
+    /// override before the subcommand, whereas cross requires it afterwards.
+    fn build_args<I>(&self, toolchain_arg: Option<String>, subcommand: &str, args: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut final_args = Vec::new();
+        if self.cross {
+            final_args.push(subcommand.into());
+            final_args.extend(toolchain_arg);
+        } else {
+            final_args.extend(toolchain_arg);
+            final_args.push(subcommand.into());
+        }
+        final_args.extend(args);
+        final_args
+    }
+
+    async fn exec_with_retries(
+        command: &mut Command,
+        hooks: &CompositeHook<'_>,
+        subcommand: &str,
+        retries: u64,
+    ) -> Result<(), Error> {
+        use crate::system::sleep;
+        use parking_lot::Mutex;
+        use std::sync::Arc;
+
+        let stderr: Arc<Mutex<String>> = Arc::default();
+        if retries > 0 {
+            let stderr = stderr.clone();
+            command.errline(move |line| {
+                let mut stderr = stderr.lock();
+                *stderr += line;
+                *stderr += "\n";
+            });
+        }
+        let mut delay = RETRY_INITIAL_DELAY;
+        let mut attempt = 0;
+        loop {
+            stderr.lock().clear();
+            hooks.attempt_started();
+            match command.exec().await {
+                Err(e)
+                    if attempt < retries
+                        && !exec::is_timeout(&e)
+                        && is_retryable_failure(subcommand, &stderr.lock()) =>
+                {
+                    attempt += 1;
+                    warning!(
+                        "cargo {} failed with what looks like a network error (attempt {} of {}), retrying in {:?}: {:?}",
+                        subcommand,
+                        attempt,
+                        retries + 1,
+                        delay,
+                        e
+                    );
+                    sleep::sleep(&delay).await;
+                    delay = delay.saturating_mul(2);
+                }
+                result => return result.map(|_| ()).map_err(Error::from),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        accepts_common_options, find_rustup_proxy, is_retryable_failure, toolchain_arg, Cargo, ToolchainVersion,
+    };
+    use crate::node;
+    use crate::node::path::Path;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    const RUSTC_VERSION: &str = concat!(
+        "rustc 1.70.0 (90c541806 2023-05-31)\n",
+        "binary: rustc\n",
+        "commit-hash: 90c541806f23a127002de5b4038be731ba1458ca\n",
+        "commit-date: 2023-05-31\n",
+        "host: x86_64-unknown-linux-gnu\n",
+        "release: 1.70.0\n",
+        "LLVM version: 16.0.2",
+    );
+
+    #[wasm_bindgen_test]
+    fn common_options_only_for_known_subcommands() {
+        for subcommand in ["build", "check", "clippy", "test", "install", "update"] {
+            assert!(accepts_common_options(subcommand));
+        }
+        for subcommand in ["fmt", "audit", "nextest"] {
+            assert!(!accepts_common_options(subcommand));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn toolchain_version_fields() {
+        let version = ToolchainVersion {
+            long: RUSTC_VERSION.into(),
+        };
+        assert_eq!(version.short(), "rustc 1.70.0 (90c541806 2023-05-31)");
+        assert_eq!(version.field("release"), Some("1.70.0"));
+        assert_eq!(
+            version.field("commit-hash"),
+            Some("90c541806f23a127002de5b4038be731ba1458ca")
+        );
+        assert_eq!(version.field("host"), Some("x86_64-unknown-linux-gnu"));
+        assert_eq!(version.field("LLVM version"), Some("16.0.2"));
+        assert_eq!(version.field("commit"), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn toolchain_version_outputs() -> Result<(), JsValue> {
+        let output_path = node::os::temp_dir().join(&format!(
+            "ferrous-actions-rustc-outputs-test - {}",
+            chrono::Local::now()
+        ));
+        node::fs::write_file(&output_path, b"").await?;
+        let original = node::process::get_var("GITHUB_OUTPUT");
+        node::process::set_var("GITHUB_OUTPUT", &output_path.to_string());
+        let version = ToolchainVersion {
+            long: RUSTC_VERSION.into(),
+        };
+        version.set_outputs();
+        match original {
+            Some(original) => node::process::set_var("GITHUB_OUTPUT", &original),
+            None => node::process::remove_var("GITHUB_OUTPUT"),
+        }
+        let written = node::fs::read_file(&output_path).await?;
+        let written = String::from_utf8_lossy(&written);
+        for (name, value) in [
+            ("rustc-version", "1.70.0"),
+            ("rustc-commit-hash", "90c541806f23a127002de5b4038be731ba1458ca"),
+            ("rustc-host", "x86_64-unknown-linux-gnu"),
+        ] {
+            assert!(written.contains(&format!("{}<<", name)));
+            assert!(written.contains(&format!("\n{}\n", value)));
+        }
+        node::fs::remove_file(&output_path).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn retry_decision() {
+        let network = concat!(
+            "    Updating crates.io index\n",
+            "warning: spurious network error (2 tries remaining): [6] Couldn't resolve host name\n",
+            "error: failed to download from `https://static.crates.io/crates/serde/1.0.193/download`\n",
+        );
+        let compile = concat!(
+            "   Compiling example v0.1.0 (/work/example)\n",
+            "error[E0425]: cannot find value `x` in this scope\n",
+            "error: could not compile `example` (lib) due to previous error\n",
+        );
+        assert!(is_retryable_failure("fetch", network));
+        assert!(is_retryable_failure("update", network));
+        assert!(is_retryable_failure("build", network));
+        assert!(!is_retryable_failure("fetch", compile));
+        assert!(!is_retryable_failure("build", compile));
+        // A network error reported by a build script does not make a compile failure retryable
+        let build_script = format!("{}{}", network, compile);
+        assert!(!is_retryable_failure("build", &build_script));
+        assert!(is_retryable_failure("update", &build_script));
+        assert!(!is_retryable_failure("fetch", ""));
+    }
+
+    #[wasm_bindgen_test]
+    async fn rustup_proxy_resolution() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-proxy-test - {}", chrono::Local::now()));
+        let missing = root.join("missing");
+        let empty = root.join("empty");
+        let bin = root.join("bin");
+        node::fs::create_dir_all(&empty).await?;
+        node::fs::create_dir_all(&bin).await?;
+        let proxy = bin.join("cargo").with_exe_suffix();
+        node::fs::write_file(&proxy, b"Nothing to see here\n").await?;
+
+        assert_eq!(find_rustup_proxy(&[missing.clone(), empty.clone()]).await, None);
+        assert_eq!(
+            find_rustup_proxy(&[missing, empty, bin.clone()]).await,
+            Some(proxy.clone())
+        );
+        assert_eq!(find_rustup_proxy(&[bin, root.clone()]).await, Some(proxy));
+
+        crate::actions::io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn toolchain_argument_position() {
+        let args = || ["--release".to_string()];
+        let mut cargo = Cargo {
+            path: Path::from("cargo"),
+            cross: false,
+            report_jobs: true,
+        };
+        let toolchain = toolchain_arg("stable").expect("Failed to build toolchain argument");
+        assert_eq!(
+            cargo.build_args(Some(toolchain.clone()), "build", args()),
+            ["+stable", "build", "--release"]
+        );
+        cargo.cross = true;
+        assert_eq!(
+            cargo.build_args(Some(toolchain), "build", args()),
+            ["build", "+stable", "--release"]
+        );
+        assert_eq!(cargo.build_args(None, "build", args()), ["build", "--release"]);
+
+        for invalid in ["", "nightly 2023-01-01", "+stable"] {
+            assert!(toolchain_arg(invalid).is_err());
+        }
+    }
 }
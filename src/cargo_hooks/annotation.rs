@@ -1,5 +1,5 @@
 use super::Hook;
-use crate::actions::core::AnnotationLevel;
+use crate::actions::core::{self, AnnotationLevel};
 use crate::actions::exec::Command;
 use crate::warning;
 use async_trait::async_trait;
@@ -19,6 +19,12 @@ impl Annotation {
     }
 
     fn process_json_record(cargo_subcommand: &str, line: &str) {
+        if let Some((annotation, level)) = Self::build_annotation(cargo_subcommand, line) {
+            annotation.output(level);
+        }
+    }
+
+    fn build_annotation(cargo_subcommand: &str, line: &str) -> Option<(core::Annotation, AnnotationLevel)> {
         use crate::actions::core::Annotation;
         use crate::node::path::Path;
         use cargo_metadata::Message;
@@ -26,14 +32,14 @@ impl Annotation {
         // Ignore blank lines
         let line = line.trim();
         if line.is_empty() {
-            return;
+            return None;
         }
 
         let metadata: Message = match serde_json::from_str(line) {
             Ok(metadata) => metadata,
             Err(e) => {
                 warning!("Unable to cargo output line as JSON metadata record: {}", e);
-                return;
+                return None;
             }
         };
         if let Message::CompilerMessage(compiler_message) = metadata {
@@ -57,7 +63,9 @@ impl Annotation {
                     .start_column(span.column_start)
                     .end_column(span.column_end);
             }
-            annotation.output(level);
+            Some((annotation, level))
+        } else {
+            None
         }
     }
 
@@ -91,3 +99,37 @@ impl Hook for Annotation {
             .stdout(Stdio::null());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Annotation;
+    use crate::actions::core::{self, AnnotationLevel};
+    use crate::node::path::Path;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    const UNUSED_VARIABLE: &str = r#"{"reason":"compiler-message","package_id":"example 0.1.0 (path+file:///home/runner/work/example)","manifest_path":"/home/runner/work/example/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"example","src_path":"/home/runner/work/example/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"message":{"rendered":"warning: unused variable: `x`\n --> src/lib.rs:2:9\n","children":[],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `x`","spans":[{"byte_end":26,"byte_start":25,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"highlight_end":10,"highlight_start":9,"text":"    let x = 1;"}]}]}}"#;
+
+    const BUILD_FINISHED: &str = r#"{"reason":"build-finished","success":true}"#;
+
+    #[wasm_bindgen_test]
+    fn annotation_from_diagnostic() {
+        let (annotation, level) =
+            Annotation::build_annotation("clippy", UNUSED_VARIABLE).expect("Diagnostic should produce an annotation");
+        let mut expected = core::Annotation::from("warning: unused variable: `x`\n --> src/lib.rs:2:9\n");
+        expected
+            .title("cargo-clippy: unused variable: `x`")
+            .file(&Path::from("src/lib.rs"))
+            .start_line(2)
+            .end_line(2)
+            .start_column(9)
+            .end_column(10);
+        assert_eq!(annotation, expected);
+        assert_eq!(level, AnnotationLevel::Warning);
+    }
+
+    #[wasm_bindgen_test]
+    fn non_diagnostics_ignored() {
+        assert!(Annotation::build_annotation("build", BUILD_FINISHED).is_none());
+        assert!(Annotation::build_annotation("build", "  ").is_none());
+    }
+}
@@ -0,0 +1,104 @@
+use super::Hook;
+use crate::actions::exec::Command;
+use crate::input_manager::Input;
+use crate::Error;
+use async_trait::async_trait;
+use std::borrow::Cow;
+use std::str::FromStr as _;
+use strum::{EnumString, IntoStaticStr};
+
+const TERM_COLOR_VAR: &str = "CARGO_TERM_COLOR";
+
+/// When cargo should use colored output
+#[derive(Clone, Copy, Debug, Eq, PartialEq, EnumString, IntoStaticStr)]
+pub enum ColorMode {
+    #[strum(serialize = "auto")]
+    Auto,
+
+    #[strum(serialize = "always")]
+    Always,
+
+    #[strum(serialize = "never")]
+    Never,
+}
+
+pub fn parse_color_mode(value: &str) -> Result<ColorMode, Error> {
+    ColorMode::from_str(value).map_err(|_| {
+        let input_name: &str = Input::CargoColor.into();
+        Error::OptionParseError(input_name.into(), value.into())
+    })
+}
+
+/// Passes `--color` to cargo. When color is forced on, `CARGO_TERM_COLOR` is
+/// also set so that tools run by cargo produce color too.
+#[derive(Clone, Debug)]
+pub struct Color {
+    mode: ColorMode,
+    pass_flag: bool,
+}
+
+impl Color {
+    pub fn new(mode: ColorMode, args: &[String]) -> Color {
+        let pass_flag = !args.iter().any(|a| a == "--color" || a.starts_with("--color="));
+        Color { mode, pass_flag }
+    }
+
+    fn vars(&self) -> Vec<(&'static str, &'static str)> {
+        match self.mode {
+            ColorMode::Always => vec![(TERM_COLOR_VAR, "always")],
+            ColorMode::Auto | ColorMode::Never => Vec::new(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Hook for Color {
+    fn additional_cargo_options(&self) -> Vec<Cow<str>> {
+        if self.pass_flag {
+            let mode: &'static str = self.mode.into();
+            vec!["--color".into(), mode.into()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn modify_command(&self, command: &mut Command) {
+        command.envs(self.vars());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_color_mode, Color, ColorMode};
+    use crate::cargo_hooks::Hook as _;
+    use crate::Error;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn flag_and_env_for_each_mode() {
+        for (value, mode, vars) in [
+            ("auto", ColorMode::Auto, vec![]),
+            ("always", ColorMode::Always, vec![("CARGO_TERM_COLOR", "always")]),
+            ("never", ColorMode::Never, vec![]),
+        ] {
+            assert_eq!(parse_color_mode(value).expect("Failed to parse color mode"), mode);
+            let hook = Color::new(mode, &[]);
+            assert_eq!(hook.additional_cargo_options(), ["--color", value]);
+            assert_eq!(hook.vars(), vars);
+        }
+        assert!(matches!(
+            parse_color_mode("sometimes"),
+            Err(Error::OptionParseError(name, value)) if name == "cargo-color" && value == "sometimes"
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    fn color_in_user_args_is_not_repeated() {
+        for args in [["--color", "never"].as_slice(), ["--color=never"].as_slice()] {
+            let args: Vec<_> = args.iter().copied().map(String::from).collect();
+            let hook = Color::new(ColorMode::Always, &args);
+            assert!(hook.additional_cargo_options().is_empty());
+            assert_eq!(hook.vars(), [("CARGO_TERM_COLOR", "always")]);
+        }
+    }
+}
@@ -12,6 +12,9 @@ pub trait Hook {
         let _ = command;
     }
 
+    /// Called before each attempt to run the command, including retries
+    fn attempt_started(&self) {}
+
     async fn succeeded(&mut self) {}
     async fn failed(&mut self) {}
 }
@@ -43,6 +46,12 @@ impl<'a> Hook for Composite<'a> {
         }
     }
 
+    fn attempt_started(&self) {
+        for hook in &self.hooks {
+            hook.attempt_started();
+        }
+    }
+
     async fn succeeded(&mut self) {
         for hook in self.hooks.iter_mut().rev() {
             hook.succeeded().await;
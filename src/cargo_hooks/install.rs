@@ -139,7 +139,7 @@ impl Hook for Install {
                             old_fingerprint.content_hash(),
                             new_fingerprint.content_hash()
                         );
-                        let delta = new_fingerprint.changes_from(old_fingerprint);
+                        let delta = old_fingerprint.diff(&new_fingerprint);
                         info!("{}", render_delta_list(&delta));
                     }
                     changed
@@ -0,0 +1,112 @@
+use super::Hook;
+use crate::actions::core;
+use crate::actions::exec::Command;
+use crate::job::{CrateStatus, JobReport as Report};
+use crate::warning;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Classifies cargo's progress output, which is written to standard error.
+/// `Fresh` lines are only emitted when cargo is run with `--verbose`.
+fn classify_line(line: &str) -> Option<(CrateStatus, String)> {
+    let mut tokens = line.split_whitespace();
+    let status = match tokens.next()? {
+        "Compiling" => CrateStatus::Built,
+        "Fresh" => CrateStatus::Restored,
+        _ => return None,
+    };
+    let name = tokens.next()?;
+    let result = match tokens.next() {
+        Some(version) if version.starts_with('v') => format!("{} {}", name, version),
+        _ => name.to_string(),
+    };
+    Some((status, result))
+}
+
+/// Adds a summary of which crates cargo built and which were already up to
+/// date to the job summary. Since cargo only reports up to date crates when
+/// run with `--verbose`, usually only built crates are listed.
+#[derive(Default)]
+pub struct JobReport {
+    report: Arc<Mutex<Report>>,
+}
+
+impl JobReport {
+    async fn write_summary(&self) {
+        let rendered = {
+            let report = self.report.lock();
+            if report.is_empty() {
+                return;
+            }
+            report.render()
+        };
+        let summary = format!("### Crates restored and built\n\n```\n{}```", rendered);
+        if let Err(e) = core::append_summary(summary).await {
+            warning!("Unable to write crate summary: {:?}", e);
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Hook for JobReport {
+    fn modify_command(&self, command: &mut Command) {
+        let report = self.report.clone();
+        command.errline(move |line| {
+            if let Some((status, name)) = classify_line(line) {
+                report.lock().record(status, &name);
+            }
+        });
+    }
+
+    fn attempt_started(&self) {
+        // Only the final attempt is reported
+        self.report.lock().clear();
+    }
+
+    async fn succeeded(&mut self) {
+        self.write_summary().await;
+    }
+
+    async fn failed(&mut self) {
+        self.write_summary().await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{classify_line, JobReport};
+    use crate::cargo_hooks::Hook as _;
+    use crate::job::CrateStatus;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn classify_cargo_output() {
+        assert_eq!(
+            classify_line("   Compiling serde v1.0.193"),
+            Some((CrateStatus::Built, "serde v1.0.193".into()))
+        );
+        assert_eq!(
+            classify_line("       Fresh itoa v1.0.9"),
+            Some((CrateStatus::Restored, "itoa v1.0.9".into()))
+        );
+        assert_eq!(
+            classify_line("   Compiling my-crate v0.1.0 (/home/runner/work/my-crate)"),
+            Some((CrateStatus::Built, "my-crate v0.1.0".into()))
+        );
+        assert_eq!(
+            classify_line("    Finished dev [unoptimized + debuginfo] target(s) in 2.50s"),
+            None
+        );
+        assert_eq!(classify_line("  Downloaded serde v1.0.193"), None);
+        assert_eq!(classify_line(""), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn new_attempt_discards_earlier_crates() {
+        let hook = JobReport::default();
+        hook.report.lock().record(CrateStatus::Built, "serde v1.0.193");
+        hook.attempt_started();
+        assert!(hook.report.lock().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+use super::Hook;
+use async_trait::async_trait;
+use std::borrow::Cow;
+
+/// Appends Cargo's `--locked`, `--frozen` and `--offline` flags when requested
+#[derive(Clone, Debug, Default)]
+pub struct LockFlags {
+    flags: Vec<&'static str>,
+}
+
+impl LockFlags {
+    pub fn new(locked: bool, frozen: bool, offline: bool, args: &[String]) -> LockFlags {
+        let flags = [(locked, "--locked"), (frozen, "--frozen"), (offline, "--offline")]
+            .into_iter()
+            .filter(|(requested, flag)| *requested && !args.iter().any(|a| a == flag))
+            .map(|(_, flag)| flag)
+            .collect();
+        LockFlags { flags }
+    }
+}
+
+#[async_trait(?Send)]
+impl Hook for LockFlags {
+    fn additional_cargo_options(&self) -> Vec<Cow<str>> {
+        self.flags.iter().map(|flag| Cow::Borrowed(*flag)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LockFlags;
+    use crate::cargo_hooks::Hook as _;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn no_flags_unless_requested() {
+        let hook = LockFlags::new(false, false, false, &[]);
+        assert!(hook.additional_cargo_options().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn requested_flags_appear_once() {
+        let hook = LockFlags::new(true, false, true, &[]);
+        assert_eq!(hook.additional_cargo_options(), ["--locked", "--offline"]);
+
+        let hook = LockFlags::new(true, true, true, &[]);
+        assert_eq!(hook.additional_cargo_options(), ["--locked", "--frozen", "--offline"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn flags_in_user_args_are_not_repeated() {
+        let args = vec!["--release".to_string(), "--locked".to_string()];
+        let hook = LockFlags::new(true, true, false, &args);
+        assert_eq!(hook.additional_cargo_options(), ["--frozen"]);
+    }
+}
@@ -1,7 +1,17 @@
 mod annotation;
+mod color;
 mod hook;
 mod install;
+mod job_report;
+mod lock_flags;
+mod rust_flags;
+mod timing;
 
 pub(crate) use annotation::*;
+pub(crate) use color::*;
 pub(crate) use hook::*;
 pub(crate) use install::*;
+pub(crate) use job_report::*;
+pub(crate) use lock_flags::*;
+pub(crate) use rust_flags::*;
+pub(crate) use timing::*;
@@ -0,0 +1,55 @@
+use super::Hook;
+use crate::actions::exec::Command;
+use crate::node;
+use async_trait::async_trait;
+
+const RUSTFLAGS_VAR: &str = "RUSTFLAGS";
+
+/// Sets `RUSTFLAGS` for the cargo process, preserving any inherited value
+#[derive(Clone, Debug)]
+pub struct RustFlags {
+    value: String,
+}
+
+impl RustFlags {
+    pub fn new(flags: &str) -> RustFlags {
+        let inherited = node::process::get_env().remove(RUSTFLAGS_VAR);
+        let value = Self::merge(inherited.as_deref(), flags);
+        RustFlags { value }
+    }
+
+    fn merge(inherited: Option<&str>, additional: &str) -> String {
+        let additional = additional.trim();
+        match inherited.map(str::trim) {
+            Some(inherited) if !inherited.is_empty() => format!("{} {}", inherited, additional),
+            _ => additional.to_string(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Hook for RustFlags {
+    fn modify_command(&self, command: &mut Command) {
+        command.env(RUSTFLAGS_VAR, self.value.as_str());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RustFlags;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn merge_with_inherited() {
+        assert_eq!(
+            RustFlags::merge(Some("-D warnings "), " -C target-cpu=generic"),
+            "-D warnings -C target-cpu=generic"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn merge_without_inherited() {
+        assert_eq!(RustFlags::merge(None, "-C opt-level=2"), "-C opt-level=2");
+        assert_eq!(RustFlags::merge(Some("  "), "-C opt-level=2"), "-C opt-level=2");
+    }
+}
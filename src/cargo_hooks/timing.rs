@@ -0,0 +1,85 @@
+use super::Hook;
+use crate::actions::core;
+use crate::actions::exec::Command;
+use crate::{info, node};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::time::Duration;
+
+const DURATION_OUTPUT_NAME: &str = "cargo-duration-ms";
+
+/// Records the wall-clock time taken by a cargo invocation
+pub struct Timing {
+    subcommand: String,
+    clock: Box<dyn Fn() -> Duration + Send + Sync>,
+    start: Mutex<Option<Duration>>,
+}
+
+impl Timing {
+    pub fn new(subcommand: &str) -> Timing {
+        Self::with_clock(subcommand, node::process::hrtime)
+    }
+
+    fn with_clock<C: Fn() -> Duration + Send + Sync + 'static>(subcommand: &str, clock: C) -> Timing {
+        Timing {
+            subcommand: subcommand.into(),
+            clock: Box::new(clock),
+            start: Mutex::default(),
+        }
+    }
+
+    fn elapsed_ms(&self) -> Option<u64> {
+        let start = (*self.start.lock())?;
+        let elapsed = (self.clock)().saturating_sub(start);
+        // Round to the nearest millisecond
+        let elapsed = (elapsed + Duration::from_micros(500)).as_millis();
+        Some(u64::try_from(elapsed).unwrap_or(u64::MAX))
+    }
+
+    fn report(&self, outcome: &str) {
+        if let Some(elapsed) = self.elapsed_ms() {
+            info!("cargo {} {} after {} ms", self.subcommand, outcome, elapsed);
+            core::set_output(DURATION_OUTPUT_NAME, elapsed.to_string());
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Hook for Timing {
+    fn modify_command(&self, _command: &mut Command) {
+        *self.start.lock() = Some((self.clock)());
+    }
+
+    async fn succeeded(&mut self) {
+        self.report("succeeded");
+    }
+
+    async fn failed(&mut self) {
+        self.report("failed");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Timing;
+    use crate::actions::exec::Command;
+    use crate::cargo_hooks::Hook as _;
+    use crate::node::path::Path;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn elapsed_uses_clock() {
+        let now = Arc::new(Mutex::new(Duration::from_secs(1)));
+        let hook = {
+            let now = now.clone();
+            Timing::with_clock("build", move || *now.lock())
+        };
+        assert_eq!(hook.elapsed_ms(), None);
+        hook.modify_command(&mut Command::from(&Path::from("cargo")));
+        *now.lock() = Duration::from_micros(3_500_400);
+        assert_eq!(hook.elapsed_ms(), Some(2500));
+    }
+}
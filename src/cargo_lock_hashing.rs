@@ -1,6 +1,6 @@
 use crate::dir_tree::{self, Ignores};
 use crate::node::path::Path;
-use crate::{node, Error};
+use crate::{node, warning, Error};
 use async_trait::async_trait;
 
 #[derive(Debug)]
@@ -38,19 +38,70 @@ pub async fn hash_cargo_lock_files(path: &Path) -> Result<HashInfo, Error> {
         name: "Cargo.lock".into(),
         paths: Vec::new(),
     };
-    let ignores = Ignores::default();
+    let mut ignores = Ignores::default();
+    // Build outputs and Git metadata can be large and never contain lock files we
+    // care about
+    ignores.add_pattern("**/target")?;
+    ignores.add_pattern("**/.git")?;
     dir_tree::apply_visitor(path, &ignores, &mut visitor).await?;
-    let mut paths: Vec<_> = visitor.paths.iter().map(Path::to_string).collect();
+    hash_lock_files(&visitor.paths).await
+}
+
+/// Hashes the contents of the supplied lock files. The result does not depend
+/// on the order in which the files are supplied. Files which do not exist are
+/// skipped.
+pub async fn hash_lock_files(paths: &[Path]) -> Result<HashInfo, Error> {
+    let mut paths: Vec<_> = paths.iter().map(Path::to_string).collect();
     // We want the paths in a deterministic order
     paths.sort();
+    paths.dedup();
     let mut hasher = blake3::Hasher::new();
+    let mut num_files = 0;
     for path in &paths {
-        let file_content = node::fs::read_file(path.as_str()).await?;
+        let path = Path::from(path);
+        if !path.exists().await {
+            warning!("Lock file {} does not exist and will not be hashed", path);
+            continue;
+        }
+        let file_content = node::fs::read_file(&path).await?;
         hasher.update(&file_content);
+        num_files += 1;
     }
     let result = HashInfo {
-        num_files: paths.len(),
+        num_files,
         bytes: hasher.finalize().into(),
     };
     Ok(result)
 }
+
+#[cfg(test)]
+mod test {
+    use super::hash_lock_files;
+    use crate::actions::io;
+    use crate::node;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    async fn hash_is_order_independent() -> Result<(), JsValue> {
+        let dir = node::os::temp_dir().join(&format!("ferrous-actions-lock-hash-test - {}", chrono::Local::now()));
+        let first = dir.join("first").join("Cargo.lock");
+        let second = dir.join("second").join("Cargo.lock");
+        let missing = dir.join("missing").join("Cargo.lock");
+        for (path, content) in [(&first, "first"), (&second, "second")] {
+            node::fs::create_dir_all(&path.parent().expect("Path should have a parent")).await?;
+            node::fs::write_file(path, content.as_bytes()).await?;
+        }
+        let forward = hash_lock_files(&[first.clone(), second.clone()])
+            .await
+            .expect("Failed to hash lock files");
+        let backward = hash_lock_files(&[second.clone(), missing, first.clone()])
+            .await
+            .expect("Failed to hash lock files");
+        assert_eq!(forward.num_files, 2);
+        assert_eq!(backward.num_files, 2);
+        assert_eq!(forward.bytes, backward.bytes);
+        io::rm_rf(&dir).await?;
+        Ok(())
+    }
+}
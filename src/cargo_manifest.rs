@@ -0,0 +1,204 @@
+use crate::dir_tree::match_relative_paths;
+use crate::node::path::Path;
+use crate::{node, Error};
+use cargo_metadata::semver::Version;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A field which may instead be inherited from the workspace using
+/// `field.workspace = true`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum MaybeInherited<T> {
+    Value(T),
+    Inherited { workspace: bool },
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct PackageSection {
+    #[serde(rename = "rust-version")]
+    rust_version: Option<MaybeInherited<String>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct WorkspacePackageSection {
+    #[serde(rename = "rust-version")]
+    rust_version: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct WorkspaceSection {
+    package: Option<WorkspacePackageSection>,
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default, rename = "default-members")]
+    default_members: Vec<String>,
+}
+
+/// The subset of a `Cargo.toml` file that Ferrous Actions makes use of
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct CargoManifest {
+    package: Option<PackageSection>,
+    workspace: Option<WorkspaceSection>,
+}
+
+fn parse_rust_version(version: &str) -> Option<Version> {
+    // Cargo permits two-component versions whereas semantic versions require three
+    let mut version = version.trim().to_string();
+    if version.matches('.').count() == 1 {
+        version += ".0";
+    }
+    Version::parse(&version)
+        .ok()
+        .filter(|v| v.pre.is_empty() && v.build.is_empty())
+}
+
+impl CargoManifest {
+    #[allow(dead_code)]
+    pub async fn read(path: &Path) -> Result<CargoManifest, Error> {
+        let content = node::fs::read_file_string(path).await?;
+        CargoManifest::from_str(&content)
+    }
+
+    /// The minimum supported Rust version of the package. An inherited value is
+    /// resolved against this manifest's own `[workspace]` table.
+    #[allow(dead_code)]
+    pub fn rust_version(&self) -> Option<Version> {
+        self.rust_version_in_workspace(self)
+    }
+
+    /// The minimum supported Rust version of the package, where an inherited
+    /// value is resolved against the supplied workspace root manifest
+    pub fn rust_version_in_workspace(&self, workspace_root: &CargoManifest) -> Option<Version> {
+        match self.package.as_ref()?.rust_version.as_ref()? {
+            MaybeInherited::Value(version) => parse_rust_version(version),
+            MaybeInherited::Inherited { workspace: true } => {
+                let version = workspace_root
+                    .workspace
+                    .as_ref()?
+                    .package
+                    .as_ref()?
+                    .rust_version
+                    .as_ref()?;
+                parse_rust_version(version)
+            }
+            MaybeInherited::Inherited { workspace: false } => None,
+        }
+    }
+
+    /// The paths of workspace members relative to the workspace root, found
+    /// by expanding `members` and `default-members` against the filesystem.
+    /// Only folders containing a `Cargo.toml` are returned.
+    #[allow(dead_code)]
+    pub async fn workspace_members(&self, root: &Path) -> Result<Vec<String>, Error> {
+        use simple_path_match::PathMatchBuilder;
+
+        let Some(workspace) = &self.workspace else {
+            return Ok(Vec::new());
+        };
+        let patterns: Vec<_> = workspace.members.iter().chain(&workspace.default_members).collect();
+        if patterns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut builder = PathMatchBuilder::new(&node::path::separator());
+        for pattern in patterns {
+            builder.add_pattern(pattern.trim_end_matches('/'))?;
+        }
+        let matcher = builder.build()?;
+        let mut members = Vec::new();
+        for path in match_relative_paths(root, &matcher, None, true).await? {
+            if root.join(&path).join("Cargo.toml").exists().await {
+                members.push(path.to_string());
+            }
+        }
+        members.sort();
+        members.dedup();
+        Ok(members)
+    }
+}
+
+impl FromStr for CargoManifest {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<CargoManifest, Error> {
+        basic_toml::from_str(string).map_err(|e| Error::CargoManifestParse(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CargoManifest;
+    use crate::actions::io;
+    use crate::node;
+    use crate::node::path::Path;
+    use cargo_metadata::semver::Version;
+    use std::str::FromStr as _;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn direct_rust_version() {
+        let manifest = CargoManifest::from_str("[package]\nname = \"a\"\nrust-version = \"1.70\"\n")
+            .expect("Failed to parse manifest");
+        assert_eq!(manifest.rust_version(), Some(Version::new(1, 70, 0)));
+    }
+
+    #[wasm_bindgen_test]
+    fn inherited_rust_version() {
+        let manifest = CargoManifest::from_str(concat!(
+            "[workspace.package]\nrust-version = \"1.65.1\"\n\n",
+            "[package]\nname = \"a\"\nrust-version.workspace = true\n",
+        ))
+        .expect("Failed to parse manifest");
+        assert_eq!(manifest.rust_version(), Some(Version::new(1, 65, 1)));
+
+        let member = CargoManifest::from_str("[package]\nname = \"b\"\nrust-version = { workspace = true }\n")
+            .expect("Failed to parse manifest");
+        assert_eq!(member.rust_version(), None);
+        assert_eq!(
+            member.rust_version_in_workspace(&manifest),
+            Some(Version::new(1, 65, 1))
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn absent_rust_version() {
+        let manifest = CargoManifest::from_str("[package]\nname = \"a\"\nversion = \"0.1.0\"\n")
+            .expect("Failed to parse manifest");
+        assert_eq!(manifest.rust_version(), None);
+        let workspace = CargoManifest::from_str("[workspace]\nmembers = [\"a\"]\n").expect("Failed to parse manifest");
+        assert_eq!(workspace.rust_version(), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn expand_workspace_members() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-workspace-test - {}", chrono::Local::now()));
+        let crates = [
+            Path::from("crates").join("a"),
+            Path::from("crates").join("b"),
+            Path::from("tools").join("c"),
+        ];
+        for path in &crates {
+            let path = root.join(path);
+            node::fs::create_dir_all(&path).await?;
+            node::fs::write_file(&path.join("Cargo.toml"), b"[package]\n").await?;
+        }
+        // A folder matching a member glob without a manifest is not a member
+        node::fs::create_dir_all(&root.join("crates").join("not-a-crate")).await?;
+
+        let manifest = CargoManifest::from_str(concat!(
+            "[workspace]\n",
+            "members = [\"crates/*\", \"tools/c\"]\n",
+            "default-members = [\"tools/c\"]\n",
+        ))
+        .expect("Failed to parse manifest");
+        let members = manifest
+            .workspace_members(&root)
+            .await
+            .expect("Failed to expand workspace members");
+        let expected: Vec<_> = crates.iter().map(Path::to_string).collect();
+        assert_eq!(members, expected);
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+}
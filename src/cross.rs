@@ -1,6 +1,32 @@
-use crate::actions::io;
+use crate::actions::exec::Command;
+use crate::actions::{core, io, tool_cache};
+use crate::cargo_hooks::Hook as CargoHook;
+use crate::input_manager::{self, Input};
 use crate::node::path::Path;
-use crate::{debug, info, input_manager, Cargo, Error};
+use crate::node::process;
+use crate::{debug, info, warning, Cargo, Error};
+use async_trait::async_trait;
+use std::str::FromStr;
+use strum::{EnumString, IntoStaticStr};
+
+const CROSS_TOOL_NAME: &str = "cross";
+const CROSS_CONFIG_VAR: &str = "CROSS_CONFIG";
+const CROSS_CONTAINER_ENGINE_VAR: &str = "CROSS_CONTAINER_ENGINE";
+
+fn install_args(version: Option<&str>) -> Vec<&str> {
+    let mut args = vec![CROSS_TOOL_NAME];
+    if let Some(version) = version {
+        // The `cargo install` hook hashes these arguments into the key of the
+        // "cargo install build artifacts" cache entry, so pinning a different
+        // version does not restore the build directory of another
+        args.extend(["--version", version]);
+    }
+    args
+}
+
+fn tool_cache_version_spec(version: Option<&str>) -> &str {
+    version.unwrap_or("*")
+}
 
 #[derive(Clone, Debug)]
 pub struct Cross {
@@ -16,24 +42,220 @@ impl Cross {
     }
 
     pub async fn get_or_install(input_manager: &input_manager::Manager) -> Result<Cross, Error> {
+        let version = input_manager.get(Input::CrossVersion);
         match Self::get().await {
-            Ok(cross) => Ok(cross),
-            Err(e) => {
-                info!("Unable to find cross. Installing it now...");
-                debug!("Attempting to locate cross returned this error: {}", e);
-                Self::install(input_manager).await
+            Ok(cross) => {
+                if cross.has_version(version).await? {
+                    return Ok(cross);
+                }
+                info!(
+                    "Found cross at {} but it is not the requested version {}",
+                    cross.get_path(),
+                    version.unwrap_or_default()
+                );
             }
+            Err(e) => debug!("Attempting to locate cross returned this error: {}", e),
+        }
+        if let Some(cross) = Self::find_cached(version)? {
+            info!("Found cross in tool cache at {}", cross.get_path());
+            return Ok(cross);
         }
+        info!("Unable to find cross. Installing it now...");
+        Self::install(version, input_manager).await
     }
 
-    async fn install(input_manager: &input_manager::Manager) -> Result<Cross, Error> {
+    async fn has_version(&self, version: Option<&str>) -> Result<bool, Error> {
+        // Version requirements other than exact versions will not match, causing a
+        // lookup in the tool cache instead
+        Ok(match version {
+            Some(version) => self.get_version().await?.as_deref() == Some(version),
+            None => true,
+        })
+    }
+
+    fn find_cached(version: Option<&str>) -> Result<Option<Cross>, Error> {
+        if let Some(dir) = tool_cache::find(CROSS_TOOL_NAME, tool_cache_version_spec(version), None)? {
+            core::add_path(&dir);
+            Ok(Some(Cross {
+                path: dir.join(CROSS_TOOL_NAME).with_exe_suffix(),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn install(version: Option<&str>, input_manager: &input_manager::Manager) -> Result<Cross, Error> {
         let mut cargo = Cargo::from_environment().await?;
-        let args = ["cross"];
-        cargo.run(None, "install", args, input_manager).await?;
-        Self::get().await
+        cargo.report_jobs(false);
+        cargo.run(None, "install", install_args(version), input_manager).await?;
+        let cross = Self::get().await?;
+        if let Err(e) = cross.add_to_tool_cache().await {
+            warning!("Unable to add cross to tool cache: {}", e);
+        }
+        Ok(cross)
+    }
+
+    async fn get_version(&self) -> Result<Option<String>, Error> {
+        use parking_lot::Mutex;
+        use std::sync::Arc;
+
+        let version: Arc<Mutex<Option<String>>> = Arc::default();
+        let version_captured = version.clone();
+        Command::from(&self.path)
+            .arg("--version")
+            .outline(move |line| {
+                // The first line is of the form "cross 0.2.5"
+                let mut version = version_captured.lock();
+                if version.is_none() {
+                    *version = line.split_whitespace().nth(1).map(String::from);
+                }
+            })
+            .exec()
+            .await
+            .map_err(Error::Js)?;
+        let version = version.lock().take();
+        Ok(version)
+    }
+
+    async fn add_to_tool_cache(&self) -> Result<(), Error> {
+        if let Some(version) = self.get_version().await? {
+            let file_name = self.path.file_name();
+            let dir = tool_cache::cache_file(&self.path, &file_name, CROSS_TOOL_NAME, &version, None).await?;
+            info!("Cached cross {} in tool cache at {}", version, dir);
+        } else {
+            warning!("Unable to determine version of cross, so it will not be added to the tool cache");
+        }
+        Ok(())
     }
 
     pub fn get_path(&self) -> Path {
         self.path.clone()
     }
 }
+
+/// The container engine cross uses to run builds
+#[derive(Clone, Copy, Debug, Eq, PartialEq, EnumString, IntoStaticStr)]
+pub enum ContainerEngine {
+    #[strum(serialize = "docker")]
+    Docker,
+
+    #[strum(serialize = "podman")]
+    Podman,
+}
+
+fn parse_container_engine(value: &str) -> Result<ContainerEngine, Error> {
+    ContainerEngine::from_str(value).map_err(|_| {
+        let input_name: &str = Input::CrossContainerEngine.into();
+        Error::OptionParseError(input_name.into(), value.into())
+    })
+}
+
+/// Environment variables used to configure cross. Any not supplied are left
+/// to cross to determine.
+#[derive(Clone, Debug, Default)]
+pub struct Environment {
+    config: Option<Path>,
+    container_engine: Option<ContainerEngine>,
+}
+
+impl Environment {
+    pub fn from_input(input_manager: &input_manager::Manager) -> Result<Environment, Error> {
+        let config = input_manager
+            .get(Input::CrossConfig)
+            .map(|path| process::cwd().join(path));
+        let container_engine = input_manager
+            .get(Input::CrossContainerEngine)
+            .map(parse_container_engine)
+            .transpose()?;
+        Ok(Environment {
+            config,
+            container_engine,
+        })
+    }
+
+    fn vars(&self) -> Vec<(&'static str, String)> {
+        let mut result = Vec::new();
+        if let Some(config) = &self.config {
+            result.push((CROSS_CONFIG_VAR, config.to_string()));
+        }
+        if let Some(engine) = self.container_engine {
+            let engine: &str = engine.into();
+            result.push((CROSS_CONTAINER_ENGINE_VAR, engine.to_string()));
+        }
+        result
+    }
+}
+
+#[async_trait(?Send)]
+impl CargoHook for Environment {
+    fn modify_command(&self, command: &mut Command) {
+        command.envs(self.vars());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{install_args, parse_container_engine, tool_cache_version_spec, ContainerEngine, Environment};
+    use crate::actions::exec::Command;
+    use crate::actions::io;
+    use crate::cargo_hooks::Hook as _;
+    use crate::node::path::Path;
+    use crate::Error;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn install_args_without_pin() {
+        assert_eq!(install_args(None), ["cross"]);
+        assert_eq!(tool_cache_version_spec(None), "*");
+    }
+
+    #[wasm_bindgen_test]
+    fn install_args_with_pin() {
+        assert_eq!(install_args(Some("0.2.5")), ["cross", "--version", "0.2.5"]);
+        assert_eq!(tool_cache_version_spec(Some("0.2.5")), "0.2.5");
+    }
+
+    #[wasm_bindgen_test]
+    async fn command_sees_config() -> Result<(), JsValue> {
+        let config = Path::from("ci").join("Cross.toml");
+        let environment = Environment {
+            config: Some(config.clone()),
+            container_engine: None,
+        };
+        let node = io::which("node", true).await?;
+        let output: Arc<Mutex<String>> = Arc::default();
+        let output_captured = output.clone();
+        let mut command = Command::from(&node);
+        command.args(["-e", "process.stdout.write(process.env.CROSS_CONFIG)"]);
+        environment.modify_command(&mut command);
+        command
+            .outline(move |line| *output_captured.lock() += line)
+            .exec()
+            .await?;
+        assert_eq!(output.lock().as_str(), config.to_string());
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn container_engine_exported() {
+        for (name, engine) in [("docker", ContainerEngine::Docker), ("podman", ContainerEngine::Podman)] {
+            assert_eq!(parse_container_engine(name).expect("Failed to parse engine"), engine);
+            let environment = Environment {
+                config: None,
+                container_engine: Some(engine),
+            };
+            assert_eq!(environment.vars(), [("CROSS_CONTAINER_ENGINE", name.to_string())]);
+        }
+        assert!(Environment::default().vars().is_empty());
+        match parse_container_engine("lxc") {
+            Err(Error::OptionParseError(name, value)) => {
+                assert_eq!(name, "cross-container-engine");
+                assert_eq!(value, "lxc");
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
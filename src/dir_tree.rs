@@ -1,17 +1,28 @@
 use crate::node::fs;
 use crate::node::path::Path;
-use crate::Error;
+use crate::system::semaphore::{Permit, Semaphore};
+use crate::{node, Error};
 use async_recursion::async_recursion;
 use async_trait::async_trait;
+use futures::future::LocalBoxFuture;
 use simple_path_match::PathMatch;
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 
 pub const ROOT_NAME: &str = ".";
 
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    matcher: Arc<PathMatch>,
+    anchored: bool,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Ignores {
     map: HashMap<usize, HashSet<String>>,
+    patterns: Vec<IgnorePattern>,
 }
 
 impl Ignores {
@@ -19,9 +30,142 @@ impl Ignores {
         self.map.entry(depth).or_default().insert(name.to_string());
     }
 
+    /// Adds a gitignore-style glob pattern. Patterns containing a `/` (other
+    /// than a leading `**/` or a trailing `/`) are matched against the path
+    /// relative to the root of the walk. Other patterns are matched against
+    /// the file name at any depth.
+    pub fn add_pattern(&mut self, pattern: &str) -> Result<(), Error> {
+        let pattern = pattern.trim_end_matches('/');
+        let (pattern, anchored) = match pattern.strip_prefix("**/") {
+            Some(pattern) => (pattern, false),
+            None => (pattern, pattern.contains('/')),
+        };
+        let pattern = pattern.trim_start_matches('/');
+        let matcher = PathMatch::from_pattern(pattern, &node::path::separator())?;
+        self.patterns.push(IgnorePattern {
+            matcher: Arc::new(matcher),
+            anchored,
+        });
+        Ok(())
+    }
+
     pub fn should_ignore(&self, name: &str, depth: usize) -> bool {
         self.map.get(&depth).map_or(false, |names| names.contains(name))
     }
+
+    fn has_patterns(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    fn should_ignore_path(&self, relative: &Path) -> bool {
+        let name = relative.file_name();
+        let relative = relative.to_string();
+        self.patterns.iter().any(|pattern| {
+            if pattern.anchored {
+                pattern.matcher.matches(&relative)
+            } else {
+                pattern.matcher.matches(&name)
+            }
+        })
+    }
+}
+
+/// Controls how a directory tree is walked
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WalkOptions {
+    /// Folders at this depth or greater are visited but not entered. The root
+    /// is at depth zero.
+    pub max_depth: Option<usize>,
+
+    /// Whether symbolic links should be followed. Each folder is entered at
+    /// most once, so cycles are not a problem.
+    pub follow_symlinks: bool,
+}
+
+struct WalkState<'a> {
+    ignores: &'a Ignores,
+    options: &'a WalkOptions,
+    visited: RefCell<HashSet<(u64, u64)>>,
+    permits: Option<Semaphore>,
+}
+
+impl<'a> WalkState<'a> {
+    fn new(ignores: &'a Ignores, options: &'a WalkOptions) -> WalkState<'a> {
+        WalkState {
+            ignores,
+            options,
+            visited: RefCell::default(),
+            permits: None,
+        }
+    }
+
+    /// Constructs state for a walk which performs at most `concurrency` file
+    /// system operations at once, across the whole tree
+    fn with_concurrency(ignores: &'a Ignores, options: &'a WalkOptions, concurrency: usize) -> WalkState<'a> {
+        WalkState {
+            permits: Some(Semaphore::new(concurrency)),
+            ..Self::new(ignores, options)
+        }
+    }
+
+    fn is_ignored(&self, depth: usize, path: &Path, relative: Option<&Path>) -> bool {
+        let file_name: Cow<str> = if depth == 0 {
+            ROOT_NAME.into()
+        } else {
+            path.file_name().into()
+        };
+        // Relative paths are only tracked when there are patterns to match them against
+        self.ignores.should_ignore(&file_name, depth)
+            || relative.is_some_and(|relative| self.ignores.should_ignore_path(relative))
+    }
+
+    async fn read_metadata(&self, path: &Path) -> Result<fs::Metadata, Error> {
+        let _permit = self.acquire_permit().await;
+        let metadata = if self.options.follow_symlinks {
+            // Fall back to the link itself if it is dangling
+            match fs::metadata(path).await {
+                Ok(metadata) => metadata,
+                Err(_) => fs::symlink_metadata(path).await?,
+            }
+        } else {
+            fs::symlink_metadata(path).await?
+        };
+        Ok(metadata)
+    }
+
+    fn within_depth(&self, depth: usize) -> bool {
+        !matches!(self.options.max_depth, Some(max_depth) if depth >= max_depth)
+    }
+
+    /// Records that a folder is being entered, returning false if it was
+    /// already entered via a symbolic link. This must only be called once the
+    /// folder is known to be entered, otherwise a folder declined along one
+    /// path would be skipped along every other.
+    fn mark_visited(&self, metadata: &fs::Metadata) -> bool {
+        !self.options.follow_symlinks || self.visited.borrow_mut().insert((metadata.dev(), metadata.ino()))
+    }
+
+    async fn acquire_permit(&self) -> Option<Permit<'_>> {
+        match &self.permits {
+            Some(permits) => Some(permits.acquire().await),
+            None => None,
+        }
+    }
+
+    async fn list_children(&self, path: &Path, relative: Option<&Path>) -> Result<Vec<(Path, Option<Path>)>, Error> {
+        let _permit = self.acquire_permit().await;
+        let children = fs::read_dir(path)
+            .await?
+            .map(|entry| {
+                let child_relative = self.ignores.has_patterns().then(|| match relative {
+                    Some(relative) => relative.join(&entry.file_name()),
+                    None => Path::from(&entry.file_name()),
+                });
+                (entry.path(), child_relative)
+            })
+            .collect();
+        Ok(children)
+    }
 }
 
 #[async_trait(?Send)]
@@ -32,54 +176,157 @@ pub trait Visitor {
     async fn enter_folder(&mut self, path: &Path) -> Result<(), Error>;
     async fn visit_entry(&mut self, name: &Path, is_file: bool) -> Result<(), Error>;
     async fn exit_folder(&mut self, path: &Path) -> Result<(), Error>;
+
+    /// Visits an entry using the metadata already read during the walk.
+    /// Visitors which need the metadata can override this to avoid reading it
+    /// a second time.
+    async fn visit_entry_with_metadata(
+        &mut self,
+        path: &Path,
+        is_file: bool,
+        _metadata: &fs::Metadata,
+    ) -> Result<(), Error> {
+        self.visit_entry(path, is_file).await
+    }
+}
+
+/// A visitor which can be split so that sibling subtrees are walked
+/// concurrently
+pub trait ConcurrentVisitor: Visitor + Sized {
+    /// Creates a visitor for walking a single subtree of the folder `self` has
+    /// most recently entered. The subtree is visited exactly as it would be by
+    /// `self`.
+    fn fork(&self) -> Self;
+
+    /// Incorporates the results of a visitor returned by `fork` once its
+    /// subtree has been walked. Forks are merged in directory listing order.
+    fn merge(&mut self, fork: Self);
 }
 
 pub async fn apply_visitor<V>(folder_path: &Path, ignores: &Ignores, visitor: &mut V) -> Result<(), Error>
 where
     V: Visitor,
 {
-    apply_visitor_impl(0, folder_path, ignores, visitor).await
+    apply_visitor_with_options(folder_path, ignores, &WalkOptions::default(), visitor).await
+}
+
+pub async fn apply_visitor_with_options<V>(
+    folder_path: &Path,
+    ignores: &Ignores,
+    options: &WalkOptions,
+    visitor: &mut V,
+) -> Result<(), Error>
+where
+    V: Visitor,
+{
+    let state = WalkState::new(ignores, options);
+    apply_visitor_impl(0, folder_path, None, &state, visitor).await
+}
+
+/// Walks a tree like `apply_visitor_with_options`, but walks the entries of
+/// each folder at the same time. At most `concurrency` file system operations
+/// are in progress at once across the whole walk. Each sibling is walked by a
+/// fork of the visitor and the forks are merged back before the folder is
+/// exited, so the visitor sees the same nesting of calls as it would during a
+/// serial walk.
+pub async fn apply_visitor_concurrently<V>(
+    folder_path: &Path,
+    ignores: &Ignores,
+    options: &WalkOptions,
+    concurrency: usize,
+    visitor: &mut V,
+) -> Result<(), Error>
+where
+    V: ConcurrentVisitor,
+{
+    let state = WalkState::with_concurrency(ignores, options, concurrency);
+    apply_visitor_concurrently_impl(0, folder_path, None, &state, visitor).await
 }
 
 #[async_recursion(?Send)]
 async fn apply_visitor_impl(
     depth: usize,
     path: &Path,
-    ignores: &Ignores,
+    relative: Option<&Path>,
+    state: &WalkState<'_>,
     visitor: &mut dyn Visitor,
 ) -> Result<(), Error> {
-    let file_name: Cow<str> = if depth == 0 {
-        ROOT_NAME.into()
-    } else {
-        path.file_name().into()
-    };
-    if ignores.should_ignore(&file_name, depth) {
+    if state.is_ignored(depth, path, relative) {
         return Ok(());
     }
-    let metadata = fs::symlink_metadata(path).await?;
+    let metadata = state.read_metadata(path).await?;
     if metadata.is_directory() {
-        if visitor.should_enter(path).await? {
+        if state.within_depth(depth) && visitor.should_enter(path).await? && state.mark_visited(&metadata) {
             visitor.enter_folder(path).await?;
-            let depth = depth + 1;
-            let dir = fs::read_dir(path).await?;
-            for entry in dir {
-                let path = entry.path();
-                apply_visitor_impl(depth, &path, ignores, visitor).await?;
+            for (child, child_relative) in state.list_children(path, relative).await? {
+                apply_visitor_impl(depth + 1, &child, child_relative.as_ref(), state, visitor).await?;
             }
             visitor.exit_folder(path).await?;
         } else {
-            visitor.visit_entry(path, false).await?;
+            visitor.visit_entry_with_metadata(path, false, &metadata).await?;
         }
     } else {
-        visitor.visit_entry(path, true).await?;
+        visitor.visit_entry_with_metadata(path, true, &metadata).await?;
     }
     Ok(())
 }
 
+fn apply_visitor_concurrently_impl<'a, V>(
+    depth: usize,
+    path: &'a Path,
+    relative: Option<&'a Path>,
+    state: &'a WalkState<'_>,
+    visitor: &'a mut V,
+) -> LocalBoxFuture<'a, Result<(), Error>>
+where
+    V: ConcurrentVisitor,
+{
+    use futures::FutureExt as _;
+
+    async move {
+        if state.is_ignored(depth, path, relative) {
+            return Ok(());
+        }
+        let metadata = state.read_metadata(path).await?;
+        if metadata.is_directory() {
+            if state.within_depth(depth) && visitor.should_enter(path).await? && state.mark_visited(&metadata) {
+                visitor.enter_folder(path).await?;
+                let children = state.list_children(path, relative).await?;
+                let forks: Vec<V> = {
+                    let parent = &*visitor;
+                    // The permits held during file system operations bound the
+                    // walk, so every child is started at once
+                    futures::future::try_join_all(children.iter().map(|(child, child_relative)| {
+                        let mut fork = parent.fork();
+                        async move {
+                            let relative = child_relative.as_ref();
+                            apply_visitor_concurrently_impl(depth + 1, child, relative, state, &mut fork)
+                                .await
+                                .map(|()| fork)
+                        }
+                    }))
+                    .await?
+                };
+                for fork in forks {
+                    visitor.merge(fork);
+                }
+                visitor.exit_folder(path).await?;
+            } else {
+                visitor.visit_entry_with_metadata(path, false, &metadata).await?;
+            }
+        } else {
+            visitor.visit_entry_with_metadata(path, true, &metadata).await?;
+        }
+        Ok(())
+    }
+    .boxed_local()
+}
+
 #[derive(Debug)]
 struct PathMatchVisitor<'a> {
     matching_paths: Vec<Path>,
     matcher: &'a PathMatch,
+    exclude: Option<&'a PathMatch>,
     path_stack: VecDeque<Path>,
     output_relative: bool,
 }
@@ -91,8 +338,13 @@ impl<'a> PathMatchVisitor<'a> {
             .map_or_else(|| Path::from("."), |p| p.join(&full_path.file_name()))
     }
 
+    fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude
+            .is_some_and(|exclude| exclude.matches(relative.to_string()))
+    }
+
     fn visit_path(&mut self, absolute: &Path, relative: &Path) {
-        if self.matcher.matches(relative.to_string()) {
+        if self.matcher.matches(relative.to_string()) && !self.is_excluded(relative) {
             let path = if self.output_relative { relative } else { absolute }.clone();
             self.matching_paths.push(path);
         }
@@ -106,7 +358,7 @@ impl<'a> Visitor for PathMatchVisitor<'a> {
             false
         } else {
             let relative = self.full_path_to_relative(full);
-            self.matcher.matches_prefix(relative.to_string())
+            self.matcher.matches_prefix(relative.to_string()) && !self.is_excluded(&relative)
         };
         Ok(result)
     }
@@ -130,10 +382,19 @@ impl<'a> Visitor for PathMatchVisitor<'a> {
     }
 }
 
-pub async fn match_relative_paths(path: &Path, matcher: &PathMatch, output_relative: bool) -> Result<Vec<Path>, Error> {
+/// Finds the paths beneath `path` which match `matcher` but not `exclude`.
+/// Folders which match `exclude` are not entered, so everything beneath them is
+/// also excluded.
+pub async fn match_relative_paths(
+    path: &Path,
+    matcher: &PathMatch,
+    exclude: Option<&PathMatch>,
+    output_relative: bool,
+) -> Result<Vec<Path>, Error> {
     let mut visitor = PathMatchVisitor {
         matching_paths: Vec::new(),
         matcher,
+        exclude,
         path_stack: VecDeque::new(),
         output_relative,
     };
@@ -141,3 +402,261 @@ pub async fn match_relative_paths(path: &Path, matcher: &PathMatch, output_relat
     apply_visitor(path, &ignores, &mut visitor).await?;
     Ok(visitor.matching_paths)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        apply_visitor, apply_visitor_concurrently, apply_visitor_with_options, match_relative_paths, ConcurrentVisitor,
+        Ignores, Visitor, WalkOptions,
+    };
+    use crate::actions::io;
+    use crate::node;
+    use crate::node::path::Path;
+    use crate::Error;
+    use async_trait::async_trait;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[derive(Default)]
+    struct FileCollector {
+        files: Vec<Path>,
+        declined: Vec<Path>,
+    }
+
+    #[async_trait(?Send)]
+    impl Visitor for FileCollector {
+        async fn should_enter(&self, path: &Path) -> Result<bool, Error> {
+            Ok(!self.declined.contains(path))
+        }
+
+        async fn enter_folder(&mut self, _: &Path) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn visit_entry(&mut self, path: &Path, is_file: bool) -> Result<(), Error> {
+            if is_file {
+                self.files.push(path.clone());
+            }
+            Ok(())
+        }
+
+        async fn exit_folder(&mut self, _: &Path) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn pattern_ignores_at_any_depth() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-ignores-test - {}", chrono::Local::now()));
+        let kept = [
+            Path::from("src").join("main.rs"),
+            Path::from("member").join("src").join("lib.rs"),
+        ];
+        let excluded = [
+            Path::from("target").join("debug").join("binary"),
+            Path::from("member").join("target").join("release").join("library"),
+        ];
+        for path in kept.iter().chain(excluded.iter()) {
+            let path = root.join(path);
+            node::fs::create_dir_all(&path.parent().expect("Path should have a parent")).await?;
+            node::fs::write_file(&path, b"content").await?;
+        }
+
+        let mut ignores = Ignores::default();
+        ignores.add_pattern("**/target").expect("Failed to add pattern");
+        let mut collector = FileCollector::default();
+        apply_visitor(&root, &ignores, &mut collector)
+            .await
+            .expect("Failed to walk tree");
+        let mut found: Vec<_> = collector
+            .files
+            .iter()
+            .map(|p| p.relative_to(&root).to_string())
+            .collect();
+        found.sort();
+        let mut expected: Vec<_> = kept.iter().map(Path::to_string).collect();
+        expected.sort();
+        assert_eq!(found, expected);
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn symlink_cycle_terminates() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-symlink-test - {}", chrono::Local::now()));
+        let nested = root.join("nested");
+        node::fs::create_dir_all(&nested).await?;
+        node::fs::write_file(&nested.join("file"), b"content").await?;
+        node::fs::symlink("..", &nested.join("loop")).await?;
+
+        let options = WalkOptions {
+            max_depth: None,
+            follow_symlinks: true,
+        };
+        let mut collector = FileCollector::default();
+        apply_visitor_with_options(&root, &Ignores::default(), &options, &mut collector)
+            .await
+            .expect("Failed to walk tree");
+        assert_eq!(collector.files.len(), 1);
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn declined_folder_is_entered_via_symlink() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-declined-test - {}", chrono::Local::now()));
+        let target = root.join("deep").join("target");
+        node::fs::create_dir_all(&target).await?;
+        node::fs::write_file(&target.join("file"), b"content").await?;
+        node::fs::symlink(&target, &root.join("link")).await?;
+
+        // Whichever path is walked first, declining the folder along one must
+        // not prevent it being entered along the other
+        let options = WalkOptions {
+            max_depth: None,
+            follow_symlinks: true,
+        };
+        let mut collector = FileCollector {
+            declined: vec![target.clone()],
+            ..FileCollector::default()
+        };
+        apply_visitor_with_options(&root, &Ignores::default(), &options, &mut collector)
+            .await
+            .expect("Failed to walk tree");
+        assert_eq!(collector.files, [root.join("link").join("file")]);
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn max_depth_limits_walk() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-depth-test - {}", chrono::Local::now()));
+        let nested = root.join("nested");
+        node::fs::create_dir_all(&nested).await?;
+        node::fs::write_file(&root.join("shallow"), b"content").await?;
+        node::fs::write_file(&nested.join("deep"), b"content").await?;
+
+        let options = WalkOptions {
+            max_depth: Some(1),
+            follow_symlinks: false,
+        };
+        let mut collector = FileCollector::default();
+        apply_visitor_with_options(&root, &Ignores::default(), &options, &mut collector)
+            .await
+            .expect("Failed to walk tree");
+        assert_eq!(collector.files, [root.join("shallow")]);
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct EventRecorder {
+        events: Vec<String>,
+    }
+
+    #[async_trait(?Send)]
+    impl Visitor for EventRecorder {
+        async fn enter_folder(&mut self, path: &Path) -> Result<(), Error> {
+            self.events.push(format!("enter {}", path));
+            Ok(())
+        }
+
+        async fn visit_entry(&mut self, path: &Path, is_file: bool) -> Result<(), Error> {
+            self.events.push(format!("visit {} {}", path, is_file));
+            Ok(())
+        }
+
+        async fn exit_folder(&mut self, path: &Path) -> Result<(), Error> {
+            self.events.push(format!("exit {}", path));
+            Ok(())
+        }
+    }
+
+    impl ConcurrentVisitor for EventRecorder {
+        fn fork(&self) -> EventRecorder {
+            EventRecorder::default()
+        }
+
+        fn merge(&mut self, fork: EventRecorder) {
+            self.events.extend(fork.events);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn concurrent_walk_matches_serial() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-concurrent-test - {}", chrono::Local::now()));
+        for dir in ["a", "b", "c"] {
+            for nested in ["x", "y"] {
+                let nested = root.join(dir).join(nested);
+                node::fs::create_dir_all(&nested).await?;
+                for file in ["1", "2", "3"] {
+                    node::fs::write_file(&nested.join(file), b"content").await?;
+                }
+            }
+            node::fs::write_file(&root.join(dir).join("file"), b"content").await?;
+        }
+        node::fs::create_dir_all(&root.join("empty")).await?;
+
+        let ignores = Ignores::default();
+        let options = WalkOptions::default();
+        let mut serial = EventRecorder::default();
+        apply_visitor_with_options(&root, &ignores, &options, &mut serial)
+            .await
+            .expect("Failed to walk tree serially");
+        for concurrency in [1, 2, 16] {
+            let mut concurrent = EventRecorder::default();
+            apply_visitor_concurrently(&root, &ignores, &options, concurrency, &mut concurrent)
+                .await
+                .expect("Failed to walk tree concurrently");
+            assert_eq!(concurrent.events, serial.events);
+        }
+        // 11 folders are entered and exited and 21 files are visited
+        assert_eq!(serial.events.len(), 2 * 11 + 21);
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn exclude_removes_matches() -> Result<(), JsValue> {
+        use simple_path_match::PathMatchBuilder;
+
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-exclude-test - {}", chrono::Local::now()));
+        let src = Path::from("src");
+        for path in [
+            src.join("main.rs"),
+            src.join("generated").join("a.rs"),
+            src.join("generated").join("b.rs"),
+            src.join("nested").join("c.rs"),
+        ] {
+            let path = root.join(&path);
+            node::fs::create_dir_all(&path.parent().expect("Path should have a parent")).await?;
+            node::fs::write_file(&path, b"content").await?;
+        }
+
+        let mut builder = PathMatchBuilder::new(&node::path::separator());
+        builder.add_pattern("src/*").expect("Failed to add pattern");
+        builder.add_pattern("src/*/*").expect("Failed to add pattern");
+        let matcher = builder.build().expect("Failed to build matcher");
+        let mut builder = PathMatchBuilder::new(&node::path::separator());
+        builder.add_pattern("src/generated").expect("Failed to add pattern");
+        let exclude = builder.build().expect("Failed to build matcher");
+
+        let excluded = match_relative_paths(&root, &matcher, Some(&exclude), true)
+            .await
+            .expect("Failed to match paths");
+        let mut found: Vec<_> = excluded.iter().map(Path::to_string).collect();
+        found.sort();
+        let mut expected: Vec<_> = [src.join("main.rs"), src.join("nested"), src.join("nested").join("c.rs")]
+            .iter()
+            .map(Path::to_string)
+            .collect();
+        expected.sort();
+        assert_eq!(found, expected);
+        let unfiltered = match_relative_paths(&root, &matcher, None, true)
+            .await
+            .expect("Failed to match paths");
+        assert_eq!(unfiltered.len(), expected.len() + 3);
+        io::rm_rf(&root).await?;
+        Ok(())
+    }
+}
@@ -55,16 +55,71 @@ pub enum Error {
     #[error("Required input was not supplied: {0}")]
     MissingInput(String),
 
+    #[error("Unable to parse Cargo manifest: {0}")]
+    CargoManifestParse(String),
+
+    #[error("Unable to parse toolchain file: {0}")]
+    ToolchainFileParse(String),
+
+    #[error("Invalid toolchain name: `{0}`")]
+    InvalidToolchainName(String),
+
     #[error("Toolchain install backend does not support {0} functionality")]
     ToolchainInstallFunctionality(String),
 
+    #[error("Toolchain {0} is not installed and cannot be downloaded in offline mode")]
+    OfflineToolchainMissing(String),
+
+    #[error("Unable to decode safely-encoded data: {0}")]
+    DecodeError(#[from] base64::DecodeError),
+
     #[error("Postcard serialization/deserialization error: {0}")]
     SerdePostcard(#[from] postcard::Error),
+
+    #[error("Unable to parse nonce: {0}")]
+    NonceParse(String),
+
+    #[error("{message}: {source}")]
+    Context { message: String, source: Box<Error> },
+
+    #[error("{0}")]
+    Timeout(String),
+}
+
+impl Error {
+    /// Wraps the error with a description of the operation that failed
+    pub fn context<M: Into<String>>(self, message: M) -> Error {
+        Error::Context {
+            message: message.into(),
+            source: Box::new(self),
+        }
+    }
+}
+
+/// Adds context to the error of a `Result`
+pub trait Context<T> {
+    fn context(self, message: &str) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+    fn context(self, message: &str) -> Result<T, Error> {
+        self.map_err(|e| e.into().context(message))
+    }
 }
 
 impl From<JsValue> for Error {
     fn from(value: JsValue) -> Error {
-        Error::Js(value)
+        use wasm_bindgen::JsCast as _;
+
+        if crate::actions::exec::is_timeout(&value) {
+            let message = value
+                .dyn_ref::<js_sys::Error>()
+                .map(|e| String::from(e.message()))
+                .unwrap_or_default();
+            Error::Timeout(message)
+        } else {
+            Error::Js(value)
+        }
     }
 }
 
@@ -73,3 +128,22 @@ impl From<simple_path_match::Error> for Error {
         Error::PathMatchPatternError(value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Context as _, Error};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn context_chain_display() {
+        let result: Result<(), Error> = Err(Error::PathDoesNotExist("/nowhere".into()));
+        let error = result
+            .context("Unable to read dependency file")
+            .context("Failed to restore crate files cache")
+            .expect_err("Context should preserve the error");
+        assert_eq!(
+            error.to_string(),
+            "Failed to restore crate files cache: Unable to read dependency file: Path does not exist: /nowhere"
+        );
+    }
+}
@@ -1,15 +1,15 @@
 use crate::delta::Action as DeltaAction;
 pub use crate::dir_tree::Ignores;
 use crate::node::fs;
-use crate::node::path::{self, Path};
+use crate::node::path::Path;
+use crate::system::concurrency;
 use crate::{dir_tree, Error};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use itertools::{Either, EitherOrBoth};
+use itertools::EitherOrBoth;
 use serde::{Deserialize, Serialize};
-use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::{btree_map, BTreeMap, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::hash::{Hash, Hasher};
 
 const ROOT_NAME: &str = ".";
@@ -70,45 +70,6 @@ pub struct Fingerprint {
     root: Entry,
 }
 
-type BranchIter<'a> = btree_map::Iter<'a, String, Entry>;
-
-#[derive(Debug)]
-struct FlatteningIterator<'a> {
-    separator: String,
-    stack: VecDeque<(Option<String>, Either<BranchIter<'a>, Metadata>)>,
-}
-
-impl<'a> Iterator for FlatteningIterator<'a> {
-    type Item = (Cow<'a, str>, Metadata);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        while let Some((path, content)) = self.stack.pop_back() {
-            match content {
-                Either::Left(mut iter) => match iter.next() {
-                    None => {}
-                    Some((base_name, entry)) => {
-                        let mut item_path = path.as_deref().unwrap_or("").to_string();
-                        item_path += base_name;
-                        self.stack.push_back((path, Either::Left(iter)));
-                        match entry {
-                            Entry::File(metadata) => return Some((item_path.into(), *metadata)),
-                            Entry::Dir(sub_tree) => {
-                                item_path += self.separator.as_str();
-                                self.stack.push_back((Some(item_path), Either::Left(sub_tree.iter())));
-                            }
-                        }
-                    }
-                },
-                Either::Right(metadata) => {
-                    let path = path.unwrap_or_else(|| ROOT_NAME.to_string());
-                    return Some((path.into(), metadata));
-                }
-            }
-        }
-        None
-    }
-}
-
 impl Fingerprint {
     pub fn content_hash(&self) -> u64 {
         self.content_hash
@@ -139,32 +100,55 @@ impl Fingerprint {
         self.accessed
     }
 
-    fn sorted_file_paths_and_metadata(&self) -> FlatteningIterator<'_> {
-        let root_content = match &self.root {
-            Entry::File(metadata) => Either::Right(*metadata),
-            Entry::Dir(sub_tree) => Either::Left(sub_tree.iter()),
-        };
-        FlatteningIterator {
-            stack: VecDeque::from([(None, root_content)]),
-            separator: path::separator().into(),
-        }
+    /// Lists the files which were added, removed or changed going from this
+    /// fingerprint to `other`. Identical subtrees are skipped without visiting
+    /// the files within them.
+    pub fn diff(&self, other: &Fingerprint) -> Vec<(Path, DeltaAction)> {
+        let mut result = Vec::new();
+        Self::diff_entries(&Path::from(ROOT_NAME), Some(&self.root), Some(&other.root), &mut result);
+        result
     }
 
-    pub fn changes_from(&self, other: &Fingerprint) -> Vec<(String, DeltaAction)> {
+    fn diff_entries(path: &Path, from: Option<&Entry>, to: Option<&Entry>, result: &mut Vec<(Path, DeltaAction)>) {
         use itertools::Itertools as _;
 
-        let from_iter = other.sorted_file_paths_and_metadata();
-        let to_iter = self.sorted_file_paths_and_metadata();
-        from_iter
-            .merge_join_by(to_iter, |left, right| left.0.cmp(&right.0))
-            .filter_map(|element| match element {
-                EitherOrBoth::Both(left, right) => {
-                    (!left.1.equal_noteworthy(&right.1)).then(|| (left.0.into_owned(), DeltaAction::Changed))
+        match (from, to) {
+            (Some(from), Some(to)) if from == to => {}
+            (Some(Entry::File(from)), Some(Entry::File(to))) => {
+                if !from.equal_noteworthy(to) {
+                    result.push((path.clone(), DeltaAction::Changed));
+                }
+            }
+            (Some(Entry::Dir(from)), Some(Entry::Dir(to))) => {
+                for element in from.iter().merge_join_by(to.iter(), |left, right| left.0.cmp(right.0)) {
+                    let (name, from, to) = match element {
+                        EitherOrBoth::Both(left, right) => (left.0, Some(left.1), Some(right.1)),
+                        EitherOrBoth::Left(left) => (left.0, Some(left.1), None),
+                        EitherOrBoth::Right(right) => (right.0, None, Some(right.1)),
+                    };
+                    Self::diff_entries(&path.join(name.as_str()), from, to, result);
+                }
+            }
+            (from, to) => {
+                // Either one side is missing or a file has been replaced by a folder (or vice versa)
+                for (entry, action) in [(from, DeltaAction::Removed), (to, DeltaAction::Added)] {
+                    if let Some(entry) = entry {
+                        Self::list_files(path, entry, action, result);
+                    }
+                }
+            }
+        }
+    }
+
+    fn list_files(path: &Path, entry: &Entry, action: DeltaAction, result: &mut Vec<(Path, DeltaAction)>) {
+        match entry {
+            Entry::File(_) => result.push((path.clone(), action)),
+            Entry::Dir(sub_tree) => {
+                for (name, entry) in sub_tree {
+                    Self::list_files(&path.join(name.as_str()), entry, action, result);
                 }
-                EitherOrBoth::Left(left) => Some((left.0.into_owned(), DeltaAction::Removed)),
-                EitherOrBoth::Right(right) => Some((right.0.into_owned(), DeltaAction::Added)),
-            })
-            .collect()
+            }
+        }
     }
 }
 
@@ -242,13 +226,38 @@ impl dir_tree::Visitor for BuildFingerprintVisitor {
     }
 }
 
+impl dir_tree::ConcurrentVisitor for BuildFingerprintVisitor {
+    fn fork(&self) -> BuildFingerprintVisitor {
+        // The fork's subtree is inserted into an empty stand-in for the
+        // current folder
+        BuildFingerprintVisitor {
+            stack: VecDeque::from([Entry::Dir(BTreeMap::new())]),
+            modified: None,
+            accessed: None,
+        }
+    }
+
+    fn merge(&mut self, mut fork: BuildFingerprintVisitor) {
+        self.modified = self.modified.max(fork.modified);
+        self.accessed = self.accessed.max(fork.accessed);
+        let Some(Entry::Dir(entries)) = fork.stack.pop_back() else {
+            panic!("Forked tree visitor did not finish within its parent folder");
+        };
+        match self.stack.back_mut() {
+            Some(Entry::Dir(map)) => map.extend(entries),
+            _ => panic!("Missing parent folder on tree visitor stack"),
+        }
+    }
+}
+
 pub async fn fingerprint_path_with_ignores(path: &Path, ignores: &Ignores) -> Result<Fingerprint, Error> {
     let mut visitor = BuildFingerprintVisitor {
         stack: VecDeque::new(),
         modified: None,
         accessed: None,
     };
-    dir_tree::apply_visitor(path, ignores, &mut visitor).await?;
+    let options = dir_tree::WalkOptions::default();
+    dir_tree::apply_visitor_concurrently(path, ignores, &options, concurrency::limit(), &mut visitor).await?;
     assert_eq!(visitor.stack.len(), 1, "Tree data stack should only have single entry");
     let root = visitor
         .stack
@@ -263,3 +272,82 @@ pub async fn fingerprint_path_with_ignores(path: &Path, ignores: &Ignores) -> Re
     };
     Ok(result)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Entry, Fingerprint, Metadata, ROOT_NAME};
+    use crate::delta::Action;
+    use crate::node::path::Path;
+    use chrono::{TimeZone as _, Utc};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn file(len: u64, accessed: i64) -> Entry {
+        let timestamp = |secs| Utc.timestamp_opt(secs, 0).single().expect("Invalid timestamp");
+        let modified = timestamp(1_700_000_000);
+        let accessed = timestamp(accessed);
+        Entry::File(Metadata {
+            uid: 1000,
+            gid: 1000,
+            len,
+            mode: 0o644,
+            modified,
+            accessed,
+        })
+    }
+
+    fn dir<const N: usize>(entries: [(&str, Entry); N]) -> Entry {
+        Entry::Dir(
+            entries
+                .into_iter()
+                .map(|(name, entry)| (name.to_string(), entry))
+                .collect(),
+        )
+    }
+
+    fn fingerprint(root: Entry) -> Fingerprint {
+        Fingerprint {
+            content_hash: Fingerprint::compute_entry_hash(&root),
+            modified: None,
+            accessed: None,
+            root,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn diff_slightly_different_trees() {
+        let before = fingerprint(dir([
+            ("Cargo.toml", file(10, 0)),
+            ("src", dir([("lib.rs", file(20, 0)), ("main.rs", file(30, 0))])),
+            ("target", dir([("debug", dir([("a", file(1, 0)), ("b", file(2, 0))]))])),
+            ("unchanged", dir([("x", file(5, 0))])),
+        ]));
+        let after = fingerprint(dir([
+            // Only the access time differs, which is not noteworthy
+            ("Cargo.toml", file(10, 100)),
+            ("src", dir([("lib.rs", file(21, 0)), ("new.rs", file(40, 0))])),
+            ("target", file(3, 0)),
+            ("unchanged", dir([("x", file(5, 0))])),
+        ]));
+        let path = |path: &str| {
+            path.split('/')
+                .fold(Path::from(ROOT_NAME), |parent, name| parent.join(name))
+        };
+        let expected: Vec<(Path, Action)> = [
+            ("src/lib.rs", Action::Changed),
+            ("src/main.rs", Action::Removed),
+            ("src/new.rs", Action::Added),
+            ("target/debug/a", Action::Removed),
+            ("target/debug/b", Action::Removed),
+            ("target", Action::Added),
+        ]
+        .into_iter()
+        .map(|(name, action)| (path(name), action))
+        .collect();
+        assert_eq!(before.diff(&after), expected);
+        assert!(after.diff(&after).is_empty());
+        assert_eq!(
+            fingerprint(dir([])).diff(&fingerprint(dir([("a", file(1, 0))]))),
+            [(path("a"), Action::Added)]
+        );
+    }
+}
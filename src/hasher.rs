@@ -1,6 +1,19 @@
+use crate::node::path::Path;
+use crate::{node, Error};
 use rustup_toolchain_manifest::HashValue;
 
-#[derive(Debug, Default)]
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The hash algorithms that can be used for cache keys
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Algorithm {
+    #[default]
+    Blake3,
+    #[allow(dead_code)]
+    Sha256,
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Blake3 {
     inner: blake3::Hasher,
 }
@@ -19,12 +32,148 @@ impl std::hash::Hasher for Blake3 {
 }
 
 impl Blake3 {
-    pub fn inner(&self) -> &blake3::Hasher {
-        &self.inner
-    }
-
     pub fn hash_value(&self) -> HashValue {
         let hash = self.inner.finalize();
         HashValue::from_bytes(&hash.as_bytes()[..])
     }
 }
+
+#[derive(Clone, Debug, Default)]
+pub struct Sha256 {
+    inner: sha2::Sha256,
+}
+
+impl std::hash::Hasher for Sha256 {
+    fn finish(&self) -> u64 {
+        let hash = self.hash_value();
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hash.as_ref()[..8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        use sha2::Digest as _;
+        self.inner.update(bytes);
+    }
+}
+
+impl Sha256 {
+    pub fn hash_value(&self) -> HashValue {
+        use sha2::Digest as _;
+        let hash = self.inner.clone().finalize();
+        HashValue::from_bytes(&hash[..])
+    }
+}
+
+/// A hasher using an algorithm selected at runtime
+#[derive(Clone, Debug)]
+pub enum Hasher {
+    Blake3(Box<Blake3>),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Hasher {
+        match algorithm {
+            Algorithm::Blake3 => Hasher::Blake3(Box::default()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::default()),
+        }
+    }
+
+    pub fn hash_value(&self) -> HashValue {
+        match self {
+            Hasher::Blake3(hasher) => hasher.hash_value(),
+            Hasher::Sha256(hasher) => hasher.hash_value(),
+        }
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Hasher {
+        Hasher::new(Algorithm::default())
+    }
+}
+
+impl std::hash::Hasher for Hasher {
+    fn finish(&self) -> u64 {
+        match self {
+            Hasher::Blake3(hasher) => hasher.finish(),
+            Hasher::Sha256(hasher) => hasher.finish(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Blake3(hasher) => hasher.write(bytes),
+            Hasher::Sha256(hasher) => hasher.write(bytes),
+        }
+    }
+}
+
+/// Computes the Blake3 hash of the file at the specified path. The file is
+/// read in fixed-size chunks so it is never held in memory in its entirety.
+pub async fn hash_file(path: &Path) -> Result<HashValue, Error> {
+    let mut file = node::fs::File::open(path).await?;
+    let mut hasher = Blake3::default();
+    let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+    let result = loop {
+        match file.read(&mut buffer).await {
+            Ok(0) => break Ok(hasher.hash_value()),
+            Ok(bytes_read) => hasher.inner.update(&buffer[..bytes_read]),
+            Err(e) => break Err(e),
+        };
+    };
+    file.close().await?;
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Algorithm, Hasher};
+    use crate::actions::io;
+    use crate::node;
+    use std::hash::Hasher as _;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn digest_hex(algorithm: Algorithm, data: &[u8]) -> String {
+        use std::fmt::Write as _;
+
+        let mut hasher = Hasher::new(algorithm);
+        hasher.write(data);
+        let hash = hasher.hash_value();
+        hash.as_ref().iter().fold(String::new(), |mut hex, byte| {
+            write!(hex, "{:02x}", byte).expect("Failed to format byte");
+            hex
+        })
+    }
+
+    #[wasm_bindgen_test]
+    fn blake3_reference_digest() {
+        assert_eq!(
+            digest_hex(Algorithm::Blake3, b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn sha256_reference_digest() {
+        assert_eq!(
+            digest_hex(Algorithm::Sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn hash_file_matches_one_shot() -> Result<(), JsValue> {
+        let size: u32 = 3 * 1024 * 1024 + 17;
+        #[allow(clippy::cast_possible_truncation)]
+        let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        let path = node::os::temp_dir().join(&format!("ferrous-actions-hash-file-test - {}", chrono::Local::now()));
+        node::fs::write_file(&path, &data).await?;
+        let streamed = super::hash_file(&path).await.expect("Failed to hash file");
+        io::rm_rf(&path).await?;
+        assert_eq!(streamed.as_ref(), blake3::hash(&data).as_bytes());
+        Ok(())
+    }
+}
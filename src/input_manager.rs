@@ -1,5 +1,5 @@
 use crate::actions::core;
-use crate::Error;
+use crate::{job, node, Error};
 use parking_lot::Mutex;
 use std::collections::{HashMap, HashSet};
 use strum::{EnumIter, IntoEnumIterator as _, IntoStaticStr};
@@ -12,21 +12,78 @@ pub enum Input {
     #[strum(serialize = "args")]
     Args,
 
+    #[strum(serialize = "atime-offset-hours")]
+    AtimeOffsetHours,
+
     #[strum(serialize = "cache-only")]
     CacheOnly,
 
+    #[strum(serialize = "cache-target-dir")]
+    CacheTargetDir,
+
+    #[strum(serialize = "cache-version")]
+    CacheVersion,
+
+    #[strum(serialize = "cargo-color")]
+    CargoColor,
+
+    #[strum(serialize = "cargo-path")]
+    CargoPath,
+
+    #[strum(serialize = "cargo-retries")]
+    CargoRetries,
+
+    #[strum(serialize = "cargo-timeout")]
+    CargoTimeout,
+
+    #[strum(serialize = "clean-key-prefix")]
+    CleanKeyPrefix,
+
     #[strum(serialize = "command")]
     Command,
 
     #[strum(serialize = "components")]
     Components,
 
+    #[strum(serialize = "confirm-clean")]
+    ConfirmClean,
+
+    #[strum(serialize = "cross-config")]
+    CrossConfig,
+
+    #[strum(serialize = "cross-container-engine")]
+    CrossContainerEngine,
+
     #[strum(serialize = "cross-platform-sharing")]
     CrossPlatformSharing,
 
+    #[strum(serialize = "cross-version")]
+    CrossVersion,
+
     #[strum(serialize = "default")]
     Default,
 
+    #[strum(serialize = "dry-run")]
+    DryRun,
+
+    #[strum(serialize = "frozen")]
+    Frozen,
+
+    #[strum(serialize = "github-token")]
+    GithubToken,
+
+    #[strum(serialize = "http-proxy")]
+    HttpProxy,
+
+    #[strum(serialize = "https-proxy")]
+    HttpsProxy,
+
+    #[strum(serialize = "locked")]
+    Locked,
+
+    #[strum(serialize = "max-concurrency")]
+    MaxConcurrency,
+
     #[strum(serialize = "min-recache-crates")]
     MinRecacheCrates,
 
@@ -36,14 +93,28 @@ pub enum Input {
     #[strum(serialize = "min-recache-indices")]
     MinRecacheIndices,
 
+    #[strum(serialize = "offline")]
+    Offline,
+
     #[strum(serialize = "override")]
     Override,
 
     #[strum(serialize = "profile")]
     Profile,
 
-    // We name this target instead of targets since actions-rs only has target
-    #[strum(serialize = "target")]
+    #[strum(serialize = "rustflags")]
+    RustFlags,
+
+    #[strum(serialize = "rustup-self-update")]
+    RustupSelfUpdate,
+
+    #[strum(serialize = "rustup-version")]
+    RustupVersion,
+
+    #[strum(serialize = "sccache")]
+    Sccache,
+
+    #[strum(serialize = "targets")]
     Targets,
 
     #[strum(serialize = "toolchain")]
@@ -53,9 +124,79 @@ pub enum Input {
     UseCross,
 }
 
+impl Input {
+    /// Other names this input may also be supplied under
+    pub fn aliases(self) -> &'static [&'static str] {
+        match self {
+            // actions-rs only has `target`, so it remains supported for
+            // workflows migrating from it
+            Input::Targets => &["target"],
+            _ => &[],
+        }
+    }
+}
+
+/// An input value and the alias it was supplied under, if any
+#[derive(Debug, PartialEq)]
+struct ResolvedInput {
+    value: String,
+    alias: Option<&'static str>,
+}
+
+/// Looks up an input under its primary name, and then under any aliases. The
+/// primary name takes precedence.
+fn resolve_input<F>(input: Input, mut lookup: F) -> Result<Option<ResolvedInput>, Error>
+where
+    F: FnMut(&str) -> Result<Option<String>, Error>,
+{
+    let input_name: &str = input.into();
+    if let Some(value) = lookup(input_name)? {
+        return Ok(Some(ResolvedInput { value, alias: None }));
+    }
+    for alias in input.aliases() {
+        if let Some(value) = lookup(alias)? {
+            return Ok(Some(ResolvedInput {
+                value,
+                alias: Some(alias),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Prefix the Actions runner uses for environment variables holding inputs
+const INPUT_ENV_PREFIX: &str = "INPUT_";
+
+/// Converts an input name to the environment variable name used to supply it,
+/// matching the conversion in the Actions Toolkit
+fn input_env_name(name: &str) -> String {
+    format!("{}{}", INPUT_ENV_PREFIX, name.replace(' ', "_").to_uppercase())
+}
+
+/// Returns the names of inputs supplied via the environment which the action
+/// does not know about
+fn find_unknown<'a, I>(env_keys: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let known: HashSet<String> = Input::iter()
+        .flat_map(|input| std::iter::once(input.into()).chain(input.aliases().iter().copied()))
+        .chain(job::INTERNAL_INPUTS)
+        .map(input_env_name)
+        .collect();
+    let mut result: Vec<String> = env_keys
+        .into_iter()
+        .filter(|key| key.starts_with(INPUT_ENV_PREFIX) && !known.contains(*key))
+        .map(|key| key[INPUT_ENV_PREFIX.len()..].to_lowercase())
+        .collect();
+    result.sort();
+    result
+}
+
 #[derive(Debug)]
 pub struct Manager {
-    inputs: HashMap<Input, String>,
+    inputs: HashMap<Input, ResolvedInput>,
+    unknown: Vec<String>,
     accessed: Mutex<HashSet<Input>>,
 }
 
@@ -63,20 +204,23 @@ impl Manager {
     pub fn build() -> Result<Manager, Error> {
         let mut inputs = HashMap::new();
         for input in Input::iter() {
-            let input_name: &str = input.into();
-            if let Some(value) = core::Input::from(input_name).get()? {
-                inputs.insert(input, value);
+            let lookup = |name: &str| Ok(core::Input::from(name).get()?);
+            if let Some(resolved) = resolve_input(input, lookup)? {
+                inputs.insert(input, resolved);
             }
         }
+        let env = node::process::get_env();
+        let unknown = find_unknown(env.keys().map(String::as_str));
         Ok(Manager {
             inputs,
+            unknown,
             accessed: Mutex::default(),
         })
     }
 
     pub fn get(&self, input: Input) -> Option<&str> {
         self.accessed.lock().insert(input);
-        self.inputs.get(&input).map(String::as_str)
+        self.inputs.get(&input).map(|resolved| resolved.value.as_str())
     }
 
     pub fn get_required(&self, input: Input) -> Result<&str, Error> {
@@ -86,8 +230,178 @@ impl Manager {
         })
     }
 
-    pub fn unused(&self) -> HashSet<Input> {
-        let available: HashSet<_> = self.inputs.keys().copied().collect();
-        &available - &self.accessed.lock()
+    pub fn get_bool(&self, input: Input) -> Result<Option<bool>, Error> {
+        self.get(input).map(|value| parse_bool(input, value)).transpose()
+    }
+
+    pub fn get_u64(&self, input: Input) -> Result<Option<u64>, Error> {
+        self.get(input).map(|value| parse_u64(input, value)).transpose()
+    }
+
+    /// Reads a duration such as `30s` or `5m 30s`
+    pub fn get_duration(&self, input: Input) -> Result<Option<chrono::Duration>, Error> {
+        self.get(input).map(|value| parse_duration(input, value)).transpose()
+    }
+
+    /// Names of inputs that were supplied but never read, as they were
+    /// supplied
+    pub fn unused(&self) -> Vec<&'static str> {
+        let accessed = self.accessed.lock();
+        let mut result: Vec<&'static str> = self
+            .inputs
+            .iter()
+            .filter(|(input, _)| !accessed.contains(*input))
+            .map(|(input, resolved)| resolved.alias.unwrap_or_else(|| (*input).into()))
+            .collect();
+        result.sort_unstable();
+        result
+    }
+
+    /// Names of inputs that were supplied but are not recognised by the action
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+}
+
+fn parse_error(input: Input, value: &str) -> Error {
+    let input_name: &str = input.into();
+    Error::OptionParseError(input_name.into(), value.into())
+}
+
+fn parse_bool(input: Input, value: &str) -> Result<bool, Error> {
+    value.trim().parse().map_err(|_| parse_error(input, value))
+}
+
+fn parse_u64(input: Input, value: &str) -> Result<u64, Error> {
+    value.trim().parse().map_err(|_| parse_error(input, value))
+}
+
+fn parse_duration(input: Input, value: &str) -> Result<chrono::Duration, Error> {
+    let duration = humantime::parse_duration(value.trim()).map_err(|_| parse_error(input, value))?;
+    chrono::Duration::from_std(duration).map_err(|_| parse_error(input, value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_unknown, parse_bool, parse_duration, parse_u64, resolve_input, Input, Manager, ResolvedInput};
+    use crate::{node, Error};
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn assert_parse_error<T: std::fmt::Debug>(result: Result<T, Error>, input: Input, value: &str) {
+        match result {
+            Err(Error::OptionParseError(name, raw)) => {
+                assert_eq!(name, input.to_string());
+                assert_eq!(raw, value);
+            }
+            other => panic!("Expected parse error, got {:?}", other),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn bool_values() {
+        assert!(parse_bool(Input::Locked, "true").expect("Failed to parse"));
+        assert!(!parse_bool(Input::Locked, " false ").expect("Failed to parse"));
+        for value in ["", "yes", "1"] {
+            assert_parse_error(parse_bool(Input::Locked, value), Input::Locked, value);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn u64_values() {
+        assert_eq!(parse_u64(Input::AtimeOffsetHours, "24").expect("Failed to parse"), 24);
+        for value in ["", "-1", "1.5", "many"] {
+            assert_parse_error(
+                parse_u64(Input::AtimeOffsetHours, value),
+                Input::AtimeOffsetHours,
+                value,
+            );
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn duration_values() {
+        let input = Input::MinRecacheCrates;
+        assert_eq!(
+            parse_duration(input, "30s").expect("Failed to parse"),
+            chrono::Duration::seconds(30)
+        );
+        assert_eq!(
+            parse_duration(input, "5m 30s").expect("Failed to parse"),
+            chrono::Duration::seconds(330)
+        );
+        for value in ["", "5", "five minutes"] {
+            assert_parse_error(parse_duration(input, value), input, value);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn alias_resolves_when_primary_absent() {
+        let supplied = HashMap::from([("target", "wasm32-unknown-unknown")]);
+        let lookup = |name: &str| Ok(supplied.get(name).map(ToString::to_string));
+        let resolved = resolve_input(Input::Targets, lookup).expect("Failed to resolve input");
+        assert_eq!(
+            resolved,
+            Some(ResolvedInput {
+                value: "wasm32-unknown-unknown".into(),
+                alias: Some("target"),
+            })
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn primary_preferred_over_alias() {
+        let supplied = HashMap::from([("target", "alias"), ("targets", "primary")]);
+        let lookup = |name: &str| Ok(supplied.get(name).map(ToString::to_string));
+        let resolved = resolve_input(Input::Targets, lookup).expect("Failed to resolve input");
+        assert_eq!(
+            resolved,
+            Some(ResolvedInput {
+                value: "primary".into(),
+                alias: None,
+            })
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn unused_reported_under_supplied_name() {
+        let resolved = |value: &str, alias| ResolvedInput {
+            value: value.into(),
+            alias,
+        };
+        let manager = Manager {
+            inputs: HashMap::from([
+                (Input::Targets, resolved("wasm32-unknown-unknown", Some("target"))),
+                (Input::Toolchain, resolved("stable", None)),
+                (Input::Profile, resolved("minimal", None)),
+            ]),
+            unknown: Vec::new(),
+            accessed: Mutex::default(),
+        };
+        assert_eq!(manager.get(Input::Profile), Some("minimal"));
+        assert_eq!(manager.unused(), ["target", "toolchain"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn unknown_inputs_reported() {
+        let bogus_var = "INPUT_FERROUS-ACTIONS-BOGUS";
+        node::process::set_var(bogus_var, "value");
+        let manager = Manager::build();
+        node::process::remove_var(bogus_var);
+        let manager = manager.expect("Failed to build input manager");
+        assert!(manager.unknown().contains(&"ferrous-actions-bogus".to_string()));
+    }
+
+    #[wasm_bindgen_test]
+    fn known_inputs_not_reported_as_unknown() {
+        let keys = [
+            "INPUT_TOOLCHAIN",
+            "INPUT_TARGET",
+            "INPUT_INTERNAL-USE-MATRIX",
+            "INPUT_TOOLCHIAN",
+            "PATH",
+        ];
+        assert_eq!(find_unknown(keys), ["toolchian"]);
     }
 }
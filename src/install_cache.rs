@@ -0,0 +1,342 @@
+use crate::actions::exec::{Command, Stdio};
+use crate::actions::{io, tool_cache};
+use crate::cache_cargo_home::find_cargo_home;
+use crate::cargo::create_empty_dir;
+use crate::hasher::Blake3 as Blake3Hasher;
+use crate::input_manager;
+use crate::node::path::Path;
+use crate::{debug, info, node, warning, Cargo, Error};
+use cargo_metadata::semver::Version;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+const INSTALL_ROOT_ENV_VAR: &str = "CARGO_INSTALL_ROOT";
+const TOOL_NAME_PREFIX: &str = "cargo-install-";
+
+/// Options which take a value, so that the value is not mistaken for a crate
+/// name
+const OPTIONS_WITH_VALUES: [&str; 18] = [
+    "--bin",
+    "--branch",
+    "--config",
+    "--example",
+    "--features",
+    "--index",
+    "--jobs",
+    "--profile",
+    "--registry",
+    "--rev",
+    "--tag",
+    "--target",
+    "--target-dir",
+    "--vers",
+    "--version",
+    "-F",
+    "-Z",
+    "-j",
+];
+
+/// Options which mean that the binaries built may differ between runs even
+/// though the crate name and version do not
+const UNCACHEABLE_OPTIONS: [&str; 3] = ["--git", "--path", "--root"];
+
+/// A crate installed at an exact version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Package {
+    name: String,
+    version: String,
+}
+
+impl Package {
+    /// Determines the package being installed from the arguments to `cargo
+    /// install`. This only succeeds when a single crate is installed from a
+    /// registry at an exact version.
+    pub fn from_install_args(args: &[String]) -> Option<Package> {
+        let mut names = Vec::new();
+        let mut version = None;
+        let mut args = args.iter().map(String::as_str);
+        while let Some(arg) = args.next() {
+            let (option, inline_value) = arg.split_once('=').map_or((arg, None), |(o, v)| (o, Some(v)));
+            if UNCACHEABLE_OPTIONS.contains(&option) {
+                return None;
+            } else if OPTIONS_WITH_VALUES.contains(&option) {
+                let value = match inline_value {
+                    Some(value) => Some(value),
+                    None => args.next(),
+                };
+                if option == "--version" || option == "--vers" {
+                    version = value;
+                }
+            } else if !arg.starts_with('-') {
+                names.push(arg);
+            }
+        }
+        let (name, version) = match (&names[..], version) {
+            ([name], Some(version)) => (*name, version),
+            ([name], None) => name.split_once('@')?,
+            _ => return None,
+        };
+        let version = version.strip_prefix('=').unwrap_or(version);
+        // Version requirements such as `1.2` could match different releases over time
+        Version::parse(version).ok()?;
+        Some(Package {
+            name: name.into(),
+            version: version.into(),
+        })
+    }
+}
+
+/// Identifies a set of installed binaries in the tool cache
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Key {
+    tool: String,
+    version: String,
+    arch: String,
+}
+
+impl Key {
+    pub fn new(package: &Package, toolchain_version: &str, args: &[String]) -> Key {
+        use std::hash::Hash as _;
+
+        // The tool cache only distinguishes entries by name, version and
+        // architecture, so the compiler and remaining arguments (e.g. features)
+        // are folded into the architecture
+        let mut hasher = Blake3Hasher::default();
+        toolchain_version.hash(&mut hasher);
+        args.hash(&mut hasher);
+        Key {
+            tool: format!("{}{}", TOOL_NAME_PREFIX, package.name),
+            version: package.version.clone(),
+            arch: format!("{}-{}", node::os::arch(), hasher.hash_value()),
+        }
+    }
+}
+
+fn get_install_bin_dir() -> Path {
+    node::process::get_var(INSTALL_ROOT_ENV_VAR)
+        .map_or_else(find_cargo_home, |root| Path::from(root.as_str()))
+        .join("bin")
+}
+
+/// Copies cached binaries into `bin_dir`, returning whether they were present
+/// in the tool cache
+pub async fn restore(key: &Key, bin_dir: &Path) -> Result<bool, Error> {
+    let Some(cached_dir) = tool_cache::find(&key.tool, &key.version, Some(&key.arch))? else {
+        return Ok(false);
+    };
+    node::fs::create_dir_all(bin_dir).await?;
+    for entry in node::fs::read_dir(&cached_dir).await? {
+        let dest = bin_dir.join(&entry.file_name());
+        io::cp(&entry.path(), &dest, false, true).await?;
+        if node::os::current_platform() != node::os::Platform::Windows {
+            // The tool cache does not guarantee that executable bits survive
+            let permissions = node::fs::metadata(&dest).await?.permissions();
+            if !permissions.is_executable() {
+                node::fs::chmod(&dest, permissions.octal() | 0o111).await?;
+            }
+        }
+        debug!("Restored {} from tool cache", dest);
+    }
+    Ok(true)
+}
+
+/// Saves the named binaries from `bin_dir` into the tool cache
+pub async fn save(key: &Key, bin_dir: &Path, binaries: &[String]) -> Result<(), Error> {
+    // Each call to the tool cache replaces the whole version folder, so the
+    // binaries must be cached together
+    let staging_dir = create_empty_dir().await?;
+    for binary in binaries {
+        io::cp(&bin_dir.join(binary), &staging_dir.join(binary), false, true).await?;
+    }
+    let cached = tool_cache::cache_dir(&key.tool, &key.version, &staging_dir, Some(&key.arch)).await;
+    io::rm_rf(&staging_dir).await?;
+    cached?;
+    Ok(())
+}
+
+fn parse_installed_binaries<'a, I>(lines: I, package: &Package) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let header = format!("{} v{}:", package.name, package.version);
+    lines
+        .into_iter()
+        .skip_while(|line| *line != header)
+        .skip(1)
+        .take_while(|line| line.starts_with(char::is_whitespace))
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+async fn list_installed_binaries(cargo: &Cargo, package: &Package) -> Result<Vec<String>, Error> {
+    let output: Arc<Mutex<Vec<String>>> = Arc::default();
+    {
+        let output = output.clone();
+        Command::from(&cargo.get_path())
+            .args(["install", "--list"])
+            .stdout(Stdio::null())
+            .outline(move |line| output.lock().push(line.to_string()))
+            .exec()
+            .await
+            .map_err(Error::Js)?;
+    }
+    let output = output.lock();
+    Ok(parse_installed_binaries(output.iter().map(String::as_str), package))
+}
+
+/// Runs `cargo install`, skipping the build entirely if binaries for the same
+/// crate version, compiler and arguments are present in the tool cache.
+///
+/// This complements `cargo_hooks::Install`, which saves the build directory to
+/// the GitHub Actions cache so that a rebuild is incremental. That works for
+/// any arguments, but still invokes the build. The binaries cached here are
+/// only reused for an exact registry version, and live in the runner's tool
+/// cache, which persists between jobs on self-hosted runners.
+pub async fn install(
+    cargo: &mut Cargo,
+    toolchain: Option<&str>,
+    args: &[String],
+    input_manager: &input_manager::Manager,
+) -> Result<(), Error> {
+    let Some(package) = Package::from_install_args(args) else {
+        debug!("Installed binaries will not be cached since no exact crate version was specified");
+        return cargo
+            .run(toolchain, "install", args.iter().map(String::as_str), input_manager)
+            .await;
+    };
+    let empty_dir = create_empty_dir().await?;
+    let toolchain_version = cargo.get_toolchain_version(toolchain, Some(&empty_dir)).await?;
+    let key = Key::new(&package, &toolchain_version.long(), args);
+    let bin_dir = get_install_bin_dir();
+    if restore(&key, &bin_dir).await? {
+        info!(
+            "Restored binaries for {} {} from tool cache, skipping build",
+            package.name, package.version
+        );
+        return Ok(());
+    }
+    cargo
+        .run(toolchain, "install", args.iter().map(String::as_str), input_manager)
+        .await?;
+    let saved = match list_installed_binaries(cargo, &package).await {
+        Ok(binaries) => save(&key, &bin_dir, &binaries).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        warning!("Failed to save installed binaries to tool cache: {}", e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_installed_binaries, Key, Package};
+    use crate::node;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().copied().map(String::from).collect()
+    }
+
+    fn package(name: &str, version: &str) -> Package {
+        Package {
+            name: name.into(),
+            version: version.into(),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn package_from_args() {
+        let expected = Some(package("cargo-nextest", "0.9.50"));
+        for install_args in [
+            args(&["cargo-nextest", "--version", "0.9.50"]),
+            args(&["--version=0.9.50", "--locked", "cargo-nextest"]),
+            args(&["--features", "foo", "cargo-nextest@0.9.50"]),
+            args(&["cargo-nextest", "--vers", "=0.9.50"]),
+        ] {
+            assert_eq!(Package::from_install_args(&install_args), expected);
+        }
+        for install_args in [
+            args(&["cargo-nextest"]),
+            args(&["cargo-nextest", "--version", "0.9"]),
+            args(&["cargo-nextest", "grcov", "--version", "0.9.50"]),
+            args(&["cargo-nextest@0.9.50", "--root", "tools"]),
+            args(&["--git", "https://example.com/nextest.git", "cargo-nextest@0.9.50"]),
+        ] {
+            assert_eq!(Package::from_install_args(&install_args), None);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn key_construction() {
+        let nextest = package("cargo-nextest", "0.9.50");
+        let install_args = args(&["cargo-nextest@0.9.50"]);
+        let key = Key::new(&nextest, "rustc 1.70.0", &install_args);
+        assert_eq!(key.tool, "cargo-install-cargo-nextest");
+        assert_eq!(key.version, "0.9.50");
+        assert_eq!(key, Key::new(&nextest, "rustc 1.70.0", &install_args));
+        assert_ne!(key.arch, Key::new(&nextest, "rustc 1.71.0", &install_args).arch);
+        let with_features = args(&["cargo-nextest@0.9.50", "--features", "foo"]);
+        assert_ne!(key.arch, Key::new(&nextest, "rustc 1.70.0", &with_features).arch);
+    }
+
+    #[wasm_bindgen_test]
+    fn installed_binaries_parsed() {
+        let output = [
+            "cargo-nextest v0.9.50:",
+            "    cargo-nextest",
+            "grcov v0.8.18:",
+            "    grcov",
+            "    grcov-extra",
+        ];
+        assert_eq!(
+            parse_installed_binaries(output, &package("grcov", "0.8.18")),
+            ["grcov", "grcov-extra"]
+        );
+        assert!(parse_installed_binaries(output, &package("grcov", "0.8.19")).is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn restored_only_when_cached() -> Result<(), JsValue> {
+        let root = node::os::temp_dir().join(&format!(
+            "ferrous-actions-install-cache-test - {}",
+            chrono::Local::now()
+        ));
+        let cache_root = root.join("tool-cache");
+        let bin_dir = root.join("bin");
+        node::fs::create_dir_all(&cache_root).await?;
+        node::fs::create_dir_all(&bin_dir).await?;
+        let binaries = ["example-tool".to_string(), "example-tool-helper".to_string()];
+        for binary in &binaries {
+            node::fs::write_file(&bin_dir.join(binary), b"Nothing to see here\n").await?;
+        }
+        let previous_tool_cache = node::process::get_var("RUNNER_TOOL_CACHE");
+        node::process::set_var("RUNNER_TOOL_CACHE", &cache_root.to_string());
+
+        let key = Key::new(
+            &package("example-tool", "1.2.3"),
+            "rustc 1.70.0",
+            &args(&["example-tool@1.2.3"]),
+        );
+        let restored_dir = root.join("restored");
+        let restore = || super::restore(&key, &restored_dir);
+        let before_save = restore().await;
+        let saved = super::save(&key, &bin_dir, &binaries).await;
+        let after_save = restore().await;
+        // Restore the environment before anything can panic
+        match previous_tool_cache {
+            Some(value) => node::process::set_var("RUNNER_TOOL_CACHE", &value),
+            None => node::process::remove_var("RUNNER_TOOL_CACHE"),
+        }
+
+        assert!(!before_save.expect("Failed to check tool cache"));
+        saved.expect("Failed to save to tool cache");
+        assert!(after_save.expect("Failed to restore from tool cache"));
+        for binary in &binaries {
+            assert!(restored_dir.join(binary).exists().await);
+        }
+        crate::actions::io::rm_rf(&root).await?;
+        Ok(())
+    }
+}
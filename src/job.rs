@@ -1,12 +1,15 @@
 use crate::actions::core;
 use crate::Error;
 use serde::de::DeserializeOwned;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 const JOB_INPUT: &str = "internal-use-github-job";
 const MATRIX_INPUT: &str = "internal-use-matrix";
 const WORKFLOW_INPUT: &str = "internal-use-github-workflow";
 
+/// Inputs used internally to identify the job
+pub const INTERNAL_INPUTS: [&str; 3] = [JOB_INPUT, MATRIX_INPUT, WORKFLOW_INPUT];
+
 #[derive(Clone, Debug, Hash)]
 pub struct Job {
     workflow: String,
@@ -52,3 +55,59 @@ impl Job {
             .map(|p| serde_json::to_string(p).expect("Failed to serialize a map of String to String to JSON"))
     }
 }
+
+/// Whether a crate was built or found to be up to date
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, strum::Display)]
+pub enum CrateStatus {
+    Restored,
+    Built,
+}
+
+/// Records which crates were restored from a previous build and which were
+/// built afresh
+#[derive(Clone, Debug, Default)]
+pub struct JobReport {
+    restored: BTreeSet<String>,
+    built: BTreeSet<String>,
+}
+
+impl JobReport {
+    pub fn record_restored(&mut self, name: &str) {
+        self.restored.insert(name.into());
+    }
+
+    pub fn record_built(&mut self, name: &str) {
+        self.built.insert(name.into());
+    }
+
+    pub fn record(&mut self, status: CrateStatus, name: &str) {
+        match status {
+            CrateStatus::Restored => self.record_restored(name),
+            CrateStatus::Built => self.record_built(name),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.restored.is_empty() && self.built.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.restored.clear();
+        self.built.clear();
+    }
+
+    pub fn render(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut result = String::new();
+        let items = self
+            .restored
+            .iter()
+            .map(|name| (CrateStatus::Restored, name))
+            .chain(self.built.iter().map(|name| (CrateStatus::Built, name)));
+        for (status, name) in items {
+            writeln!(&mut result, "{}: {}", status, name).expect("Unable to write to string");
+        }
+        result
+    }
+}
@@ -25,17 +25,24 @@ pub mod node;
 #[cfg(feature = "github_actions_bindings")]
 pub mod actions;
 
+#[cfg(feature = "github_actions_bindings")]
+mod system;
+
 cfg_if::cfg_if! {
 if #[cfg(feature = "action")] {
 
 mod access_times;
 mod action_paths;
 mod agnostic_path;
+mod cache_backend;
 mod cache_cargo_home;
+mod cache_clean;
 mod cache_key_builder;
+mod cache_target_dir;
 mod cargo;
 mod cargo_hooks;
 mod cargo_lock_hashing;
+mod cargo_manifest;
 mod cross;
 mod delta;
 mod dir_tree;
@@ -43,13 +50,15 @@ mod error;
 mod fingerprinting;
 mod hasher;
 mod input_manager;
+mod install_cache;
 mod job;
 mod nonce;
 mod package_manifest;
+mod proxy;
 mod run;
 mod rustup;
 mod safe_encoding;
-mod system;
+mod sccache;
 mod toolchain;
 mod utils;
 
@@ -126,6 +126,84 @@ impl Iterator for ReadDir {
     }
 }
 
+/// The checks performed by `access`, which may be combined with `|`. The
+/// values correspond to node's `fs.constants`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AccessMode {
+    bits: u32,
+}
+
+impl AccessMode {
+    /// The path is visible to the process (`F_OK`)
+    pub const EXISTS: AccessMode = AccessMode { bits: 0 };
+
+    /// The path can be read (`R_OK`)
+    pub const READ: AccessMode = AccessMode { bits: 4 };
+
+    /// The path can be written (`W_OK`)
+    pub const WRITE: AccessMode = AccessMode { bits: 2 };
+
+    /// The path can be executed (`X_OK`). On Windows this behaves like
+    /// `EXISTS`.
+    pub const EXECUTE: AccessMode = AccessMode { bits: 1 };
+
+    /// Returns the raw mode value passed to node
+    pub fn bits(self) -> u32 {
+        self.bits
+    }
+
+    /// Returns `true` if all checks in `other` are also in `self`
+    pub fn contains(self, other: AccessMode) -> bool {
+        self.bits & other.bits == other.bits
+    }
+}
+
+impl std::ops::BitOr for AccessMode {
+    type Output = AccessMode;
+
+    fn bitor(self, rhs: AccessMode) -> AccessMode {
+        AccessMode {
+            bits: self.bits | rhs.bits,
+        }
+    }
+}
+
+impl std::ops::BitOrAssign for AccessMode {
+    fn bitor_assign(&mut self, rhs: AccessMode) {
+        self.bits |= rhs.bits;
+    }
+}
+
+/// Error codes from `access` which mean the check failed rather than that the
+/// check could not be performed
+const ACCESS_DENIED_CODES: [&str; 5] = ["EACCES", "ENOENT", "ENOTDIR", "EPERM", "EROFS"];
+
+/// The `code` property of an error thrown by a file system function
+fn error_code(error: &JsValue) -> Option<String> {
+    js_sys::Reflect::get(error, &"code".into())
+        .ok()
+        .and_then(|code| code.as_string())
+}
+
+/// Returns whether an error thrown by a file system function indicates that
+/// the path did not exist
+pub fn is_not_found(error: &JsValue) -> bool {
+    error_code(error).as_deref() == Some("ENOENT")
+}
+
+/// Checks whether the process may access the specified path in the specified
+/// way, returning `false` if it does not exist or the access is not permitted
+pub async fn access<P: Into<JsString>>(path: P, mode: AccessMode) -> Result<bool, JsValue> {
+    let path: JsString = path.into();
+    match ffi::access(&path, Some(mode.bits())).await {
+        Ok(_) => Ok(true),
+        Err(error) => match error_code(&error) {
+            Some(code) if ACCESS_DENIED_CODES.contains(&code.as_str()) => Ok(false),
+            _ => Err(error),
+        },
+    }
+}
+
 /// Changes the permissions of the specified path to the specified mode
 pub async fn chmod<P: Into<JsString>>(path: P, mode: u16) -> Result<(), JsValue> {
     let path: JsString = path.into();
@@ -145,6 +223,17 @@ pub async fn read_file<P: Into<JsString>>(path: P) -> Result<Vec<u8>, JsValue> {
     Ok(result)
 }
 
+/// Reads the file at the specified path into a `String`, failing if it is not
+/// valid UTF-8
+pub async fn read_file_string<P: Into<JsString>>(path: P) -> Result<String, JsValue> {
+    let path: JsString = path.into();
+    let data = read_file(path.clone()).await?;
+    String::from_utf8(data).map_err(|e| {
+        let error = format!("File {} is not valid UTF-8: {}", String::from(&path), e.utf8_error());
+        JsError::new(&error).into()
+    })
+}
+
 /// Write the supplied `Vec` to a file at the specified path
 pub async fn write_file<P: Into<JsString>>(path: P, data: &[u8]) -> Result<(), JsValue> {
     let path: JsString = path.into();
@@ -152,6 +241,21 @@ pub async fn write_file<P: Into<JsString>>(path: P, data: &[u8]) -> Result<(), J
     Ok(())
 }
 
+/// Writes the supplied data to a file at the specified path unless the file
+/// already holds exactly that data, so that its modification time is only
+/// updated when its content changes. Returns whether a write occurred.
+pub async fn write_file_if_changed<P: Into<JsString>>(path: P, data: &[u8]) -> Result<bool, JsValue> {
+    let path: JsString = path.into();
+    // Any failure to read the existing file is resolved by writing it
+    if let Ok(existing) = read_file(path.clone()).await {
+        if existing == data {
+            return Ok(false);
+        }
+    }
+    write_file(path, data).await?;
+    Ok(true)
+}
+
 /// Reads all entries in the specified folder and returns an iterator
 pub async fn read_dir<P: Into<JsString>>(path: P) -> Result<ReadDir, JsValue> {
     let path: JsString = path.into();
@@ -204,6 +308,29 @@ pub async fn remove_file<P: Into<JsString>>(path: P) -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Options controlling the behaviour of `rm`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RmOptions {
+    /// Whether a folder and its contents may be removed. If not set, removing a
+    /// folder is an error.
+    pub recursive: bool,
+
+    /// Whether a missing path should be ignored rather than being an error
+    pub force: bool,
+}
+
+/// Deletes the file, or folder if `options.recursive` is set, at the
+/// specified path
+pub async fn rm<P: Into<JsString>>(path: P, options: RmOptions) -> Result<(), JsValue> {
+    let path: JsString = path.into();
+    let js_options = js_sys::Map::new();
+    js_options.set(&"recursive".into(), &options.recursive.into());
+    js_options.set(&"force".into(), &options.force.into());
+    let js_options = Object::from_entries(&js_options).expect("Failed to convert options map to object");
+    ffi::rm(&path, Some(js_options)).await?;
+    Ok(())
+}
+
 /// Renames a file from one path to another
 pub async fn rename<P: Into<JsString>>(from: P, to: P) -> Result<(), JsValue> {
     let from: JsString = from.into();
@@ -212,6 +339,55 @@ pub async fn rename<P: Into<JsString>>(from: P, to: P) -> Result<(), JsValue> {
     Ok(())
 }
 
+/// The Unix permission bits of a file
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Permissions {
+    mode: u16,
+}
+
+impl Permissions {
+    /// Constructs permissions from a mode, ignoring any file type bits
+    pub fn from_mode(mode: u64) -> Permissions {
+        let mode = u16::try_from(mode & 0o7777).expect("Masked mode should fit in 16 bits");
+        Permissions { mode }
+    }
+
+    /// The permission bits in the form accepted by `chmod`
+    pub fn octal(self) -> u16 {
+        self.mode
+    }
+
+    /// Can the owner read the file?
+    pub fn is_readable_by_owner(self) -> bool {
+        self.mode & 0o400 != 0
+    }
+
+    /// Can the owner write the file?
+    pub fn is_writable_by_owner(self) -> bool {
+        self.mode & 0o200 != 0
+    }
+
+    /// Can anyone execute the file?
+    pub fn is_executable(self) -> bool {
+        self.mode & 0o111 != 0
+    }
+
+    /// Is the set-user-ID bit set?
+    pub fn is_setuid(self) -> bool {
+        self.mode & 0o4000 != 0
+    }
+
+    /// Is the set-group-ID bit set?
+    pub fn is_setgid(self) -> bool {
+        self.mode & 0o2000 != 0
+    }
+
+    /// Is the sticky bit set?
+    pub fn is_sticky(self) -> bool {
+        self.mode & 0o1000 != 0
+    }
+}
+
 /// File metadata
 #[derive(Debug)]
 pub struct Metadata {
@@ -240,6 +416,21 @@ impl Metadata {
         self.inner.mode().try_into().expect("File mode too large")
     }
 
+    /// The Unix permission flags for the file without the file type
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.mode())
+    }
+
+    /// The ID of the device containing the file
+    pub fn dev(&self) -> u64 {
+        self.inner.dev().try_into().expect("Device ID too large")
+    }
+
+    /// The inode number of the file
+    pub fn ino(&self) -> u64 {
+        self.inner.ino().try_into().expect("Inode number too large")
+    }
+
     fn utc_ns_to_time(ns: BigInt) -> DateTime<Utc> {
         const NS_IN_S: i128 = 1000 * 1000 * 1000;
         let ns = i128::try_from(ns).expect("Timestamp out of range");
@@ -289,16 +480,94 @@ impl Metadata {
     }
 }
 
+fn stat_options() -> Object {
+    let options = js_sys::Map::new();
+    options.set(&"bigint".into(), &true.into());
+    Object::from_entries(&options).expect("Failed to convert options map to object")
+}
+
+/// Returns metadata about the specified path, dereferencing symlinks
+pub async fn metadata<P: Into<JsString>>(path: P) -> Result<Metadata, JsValue> {
+    let path = path.into();
+    let stats = ffi::stat(&path, Some(stat_options()))
+        .await
+        .map(Into::<ffi::Stats>::into)?;
+    Ok(Metadata { inner: stats })
+}
+
 /// Returns metadata about the specified path, without dereferencing symlinks
 pub async fn symlink_metadata<P: Into<JsString>>(path: P) -> Result<Metadata, JsValue> {
     let path = path.into();
-    let options = js_sys::Map::new();
-    options.set(&"bigint".into(), &true.into());
-    let options = Object::from_entries(&options).expect("Failed to convert options map to object");
-    let stats = ffi::lstat(&path, Some(options)).await.map(Into::<ffi::Stats>::into)?;
+    let stats = ffi::lstat(&path, Some(stat_options()))
+        .await
+        .map(Into::<ffi::Stats>::into)?;
     Ok(Metadata { inner: stats })
 }
 
+/// Creates a symbolic link at `path` pointing to `target`
+pub async fn symlink<T: Into<JsString>, P: Into<JsString>>(target: T, path: P) -> Result<(), JsValue> {
+    let target = target.into();
+    let path = path.into();
+    ffi::symlink(&target, &path).await?;
+    Ok(())
+}
+
+/// A file opened for reading
+#[derive(Debug)]
+pub struct File {
+    inner: ffi::FileHandle,
+}
+
+impl File {
+    /// Opens the file at the specified path for reading
+    pub async fn open<P: Into<JsString>>(path: P) -> Result<File, JsValue> {
+        let path = path.into();
+        let handle = ffi::open(&path, &"r".into()).await?;
+        // `FileHandle` isn't exported by the `fs` module so an `instanceof` check
+        // would always fail
+        let handle = handle.unchecked_into::<ffi::FileHandle>();
+        Ok(File { inner: handle })
+    }
+
+    /// Reads bytes from the current position in the file into the supplied
+    /// buffer, returning the number of bytes read. A return value of zero
+    /// indicates the end of the file.
+    pub async fn read(&mut self, buffer: &mut [u8]) -> Result<usize, JsValue> {
+        let length = u32::try_from(buffer.len()).map_err(|_| JsError::new("Read buffer is too large"))?;
+        let js_buffer = Uint8Array::new_with_length(length);
+        let result = self.inner.read(&js_buffer, 0, length, &JsValue::NULL).await?;
+        let bytes_read = js_sys::Reflect::get(&result, &"bytesRead".into())?
+            .as_f64()
+            .ok_or_else(|| JsError::new("read didn't return a byte count"))?;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let bytes_read = bytes_read as u32;
+        let bytes_read_usize = bytes_read as usize;
+        js_buffer
+            .subarray(0, bytes_read)
+            .copy_to(&mut buffer[..bytes_read_usize]);
+        Ok(bytes_read_usize)
+    }
+
+    /// Closes the file
+    pub async fn close(self) -> Result<(), JsValue> {
+        self.inner.close().await?;
+        Ok(())
+    }
+}
+
+/// Opens a readable stream over the contents of the file at the specified path
+pub fn create_read_stream<P: Into<JsString>>(path: P) -> super::stream::ffi::Readable {
+    let path = path.into();
+    ffi::create_read_stream(&path)
+}
+
+/// Opens a writable stream which replaces the contents of the file at the
+/// specified path
+pub fn create_write_stream<P: Into<JsString>>(path: P) -> super::stream::ffi::Writable {
+    let path = path.into();
+    ffi::create_write_stream(&path)
+}
+
 fn timestamp_to_seconds(timestamp: &DateTime<Utc>) -> f64 {
     // utimes takes timestamps in seconds - this was fun to debug
     const NS_IN_S: f64 = 1e9;
@@ -308,6 +577,18 @@ fn timestamp_to_seconds(timestamp: &DateTime<Utc>) -> f64 {
     whole + fractional
 }
 
+/// Sets the access and modification times of the file at the specified path,
+/// following symbolic links
+pub async fn utimes<P: Into<JsString>>(path: P, a_time: &DateTime<Utc>, m_time: &DateTime<Utc>) -> Result<(), JsValue> {
+    use js_sys::Number;
+
+    let path = path.into();
+    let a_time: Number = timestamp_to_seconds(a_time).into();
+    let m_time: Number = timestamp_to_seconds(m_time).into();
+    ffi::utimes(&path, a_time.as_ref(), m_time.as_ref()).await?;
+    Ok(())
+}
+
 /// Sets the access and modification times of the file at the specified path
 pub async fn lutimes<P: Into<JsString>>(
     path: P,
@@ -325,7 +606,8 @@ pub async fn lutimes<P: Into<JsString>>(
 
 /// Low-level bindings for node.js filesystem functions
 pub mod ffi {
-    use js_sys::{BigInt, JsString, Object};
+    use crate::node::stream::ffi::{Readable, Writable};
+    use js_sys::{BigInt, JsString, Object, Uint8Array};
     use wasm_bindgen::prelude::*;
     use wasm_bindgen::JsValue;
 
@@ -389,6 +671,34 @@ pub mod ffi {
 
         #[wasm_bindgen(method, getter)]
         pub fn mode(this: &Stats) -> BigInt;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn dev(this: &Stats) -> BigInt;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn ino(this: &Stats) -> BigInt;
+
+        #[wasm_bindgen(js_name = "createReadStream")]
+        pub fn create_read_stream(path: &JsString) -> Readable;
+
+        #[wasm_bindgen(js_name = "createWriteStream")]
+        pub fn create_write_stream(path: &JsString) -> Writable;
+
+        #[derive(Debug)]
+        #[wasm_bindgen(js_name = "FileHandle")]
+        pub type FileHandle;
+
+        #[wasm_bindgen(method, catch)]
+        pub async fn read(
+            this: &FileHandle,
+            buffer: &Uint8Array,
+            offset: u32,
+            length: u32,
+            position: &JsValue,
+        ) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(method, catch)]
+        pub async fn close(this: &FileHandle) -> Result<JsValue, JsValue>;
     }
 
     #[wasm_bindgen(module = "fs/promises")]
@@ -396,6 +706,9 @@ pub mod ffi {
         #[wasm_bindgen(catch)]
         pub async fn chmod(path: &JsString, mode: u16) -> Result<JsValue, JsValue>;
 
+        #[wasm_bindgen(catch)]
+        pub async fn open(path: &JsString, flags: &JsString) -> Result<JsValue, JsValue>;
+
         #[wasm_bindgen(catch, js_name = "readFile")]
         pub async fn read_file(path: &JsString) -> Result<JsValue, JsValue>;
 
@@ -411,6 +724,9 @@ pub mod ffi {
         #[wasm_bindgen(catch)]
         pub async fn rename(old: &JsString, new: &JsString) -> Result<JsValue, JsValue>;
 
+        #[wasm_bindgen(catch)]
+        pub async fn rm(path: &JsString, options: Option<Object>) -> Result<JsValue, JsValue>;
+
         #[wasm_bindgen(catch)]
         pub async fn rmdir(path: &JsString, options: Option<Object>) -> Result<JsValue, JsValue>;
 
@@ -420,6 +736,15 @@ pub mod ffi {
         #[wasm_bindgen(catch)]
         pub async fn lstat(path: &JsString, options: Option<Object>) -> Result<JsValue, JsValue>;
 
+        #[wasm_bindgen(catch)]
+        pub async fn stat(path: &JsString, options: Option<Object>) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(catch)]
+        pub async fn symlink(target: &JsString, path: &JsString) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(catch)]
+        pub async fn utimes(path: &JsString, atime: &JsValue, mtime: &JsValue) -> Result<JsValue, JsValue>;
+
         #[wasm_bindgen(catch)]
         pub async fn lutimes(path: &JsString, atime: &JsValue, mtime: &JsValue) -> Result<JsValue, JsValue>;
 
@@ -484,6 +809,136 @@ mod test {
         Ok(())
     }
 
+    #[wasm_bindgen_test]
+    async fn access_checks() -> Result<(), JsValue> {
+        use super::AccessMode;
+
+        let path = temp_path();
+        assert!(!super::access(&path, AccessMode::EXISTS).await?);
+        node::fs::write_file(&path, b"Nothing to see here\n").await?;
+        let read_write = AccessMode::READ | AccessMode::WRITE;
+        assert!(read_write.contains(AccessMode::WRITE));
+        assert!(!read_write.contains(AccessMode::EXECUTE));
+        assert!(super::access(&path, AccessMode::EXISTS).await?);
+        assert!(super::access(&path, read_write).await?);
+        assert!(!super::access(path.join("child"), AccessMode::READ).await?);
+        node::fs::remove_file(&path).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn permissions_from_mode() {
+        use super::Permissions;
+
+        // A regular file with rwxr-xr-x
+        let permissions = Permissions::from_mode(0o100_755);
+        assert_eq!(permissions.octal(), 0o755);
+        assert!(permissions.is_readable_by_owner());
+        assert!(permissions.is_writable_by_owner());
+        assert!(permissions.is_executable());
+        assert!(!permissions.is_setuid());
+        assert!(!permissions.is_setgid());
+        assert!(!permissions.is_sticky());
+
+        let permissions = Permissions::from_mode(0o040_200);
+        assert_eq!(permissions.octal(), 0o200);
+        assert!(!permissions.is_readable_by_owner());
+        assert!(!permissions.is_executable());
+
+        let permissions = Permissions::from_mode(0o7_001);
+        assert!(permissions.is_executable());
+        assert!(permissions.is_setuid());
+        assert!(permissions.is_setgid());
+        assert!(permissions.is_sticky());
+    }
+
+    #[wasm_bindgen_test]
+    async fn read_file_as_string() -> Result<(), JsValue> {
+        let path = temp_path();
+        let text = "Ferrous \u{2699} actions\n";
+        node::fs::write_file(&path, text.as_bytes()).await?;
+        assert_eq!(node::fs::read_file_string(&path).await?, text);
+        node::fs::write_file(&path, &[b'a', 0xff, 0xfe]).await?;
+        assert!(node::fs::read_file_string(&path).await.is_err());
+        node::fs::remove_file(&path).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn missing_path_is_not_found() -> Result<(), JsValue> {
+        let path = temp_path();
+        let error = node::fs::metadata(&path).await.expect_err("Path should not exist");
+        assert!(super::is_not_found(&error));
+        node::fs::write_file(&path, b"content").await?;
+        let error = node::fs::read_dir(&path).await.expect_err("Path is not a folder");
+        assert!(!super::is_not_found(&error));
+        node::fs::remove_file(&path).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn open_and_read_file() -> Result<(), JsValue> {
+        let path = temp_path();
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        node::fs::write_file(&path, &data).await?;
+        let mut file = super::File::open(&path).await?;
+        let mut read = Vec::new();
+        let mut buffer = [0u8; 300];
+        loop {
+            let bytes_read = file.read(&mut buffer).await?;
+            if bytes_read == 0 {
+                break;
+            }
+            read.extend_from_slice(&buffer[..bytes_read]);
+        }
+        file.close().await?;
+        node::fs::remove_file(&path).await?;
+        assert_eq!(read, data);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn write_only_if_changed() -> Result<(), JsValue> {
+        let path = temp_path();
+        assert!(node::fs::write_file_if_changed(&path, b"first").await?);
+        let modified = node::fs::metadata(&path).await?.modified();
+        assert!(!node::fs::write_file_if_changed(&path, b"first").await?);
+        assert_eq!(node::fs::metadata(&path).await?.modified(), modified);
+        assert!(node::fs::write_file_if_changed(&path, b"second").await?);
+        assert_eq!(node::fs::read_file(&path).await?, b"second");
+        node::fs::remove_file(&path).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn rm_file_and_dir() -> Result<(), JsValue> {
+        use super::RmOptions;
+
+        let file = temp_path();
+        node::fs::write_file(&file, b"content").await?;
+        super::rm(&file, RmOptions::default()).await?;
+        assert!(!file.exists().await);
+        assert!(super::rm(&file, RmOptions::default()).await.is_err());
+        let force = RmOptions {
+            force: true,
+            ..RmOptions::default()
+        };
+        super::rm(&file, force).await?;
+
+        let dir = temp_path();
+        node::fs::create_dir_all(&dir).await?;
+        node::fs::write_file(&dir.join("file"), b"content").await?;
+        assert!(super::rm(&dir, RmOptions::default()).await.is_err());
+        assert!(dir.join("file").exists().await);
+        let recursive = RmOptions {
+            recursive: true,
+            ..RmOptions::default()
+        };
+        super::rm(&dir, recursive).await?;
+        assert!(!dir.exists().await);
+        Ok(())
+    }
+
     #[wasm_bindgen_test]
     async fn create_remove_dir() -> Result<(), JsValue> {
         let first = temp_path();
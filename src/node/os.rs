@@ -24,6 +24,53 @@ pub fn platform() -> String {
     ffi::platform().into()
 }
 
+/// An operating system platform, as identified by node.js
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Platform {
+    /// Linux
+    Linux,
+
+    /// macOS
+    MacOs,
+
+    /// Windows
+    Windows,
+
+    /// Any other platform, holding the name reported by node.js
+    Other(String),
+}
+
+impl From<&str> for Platform {
+    fn from(name: &str) -> Platform {
+        match name {
+            "linux" => Platform::Linux,
+            "darwin" => Platform::MacOs,
+            "win32" => Platform::Windows,
+            other => Platform::Other(other.into()),
+        }
+    }
+}
+
+impl Platform {
+    /// The suffix of executable file names on this platform
+    pub fn exe_suffix(&self) -> &'static str {
+        match self {
+            Platform::Windows => ".exe",
+            _ => "",
+        }
+    }
+}
+
+/// The platform we are currently running on
+pub fn current_platform() -> Platform {
+    Platform::from(platform().as_str())
+}
+
+/// The suffix of executable file names on the platform we are running on
+pub fn exe_suffix() -> &'static str {
+    current_platform().exe_suffix()
+}
+
 /// The name of the machine type
 pub fn machine() -> String {
     ffi::machine().into()
@@ -39,6 +86,51 @@ pub fn homedir() -> path::Path {
     path::Path::from(ffi::homedir())
 }
 
+/// Information about the user the process is running as
+#[derive(Clone, Debug)]
+pub struct UserInfo {
+    /// The user name
+    pub username: String,
+
+    /// The user ID, or `None` on Windows
+    pub uid: Option<u32>,
+
+    /// The group ID, or `None` on Windows
+    pub gid: Option<u32>,
+
+    /// The user's home directory as recorded by the operating system. Unlike
+    /// `homedir`, this does not consult environment variables like `HOME`.
+    pub homedir: path::Path,
+
+    /// The user's shell, or `None` on Windows
+    pub shell: Option<String>,
+}
+
+/// Retrieves information about the current user. This fails if the user has
+/// no user name or home directory.
+pub fn user_info() -> Result<UserInfo, wasm_bindgen::JsValue> {
+    let info = ffi::user_info()?;
+    // Windows reports -1 for IDs it does not have
+    let id = |value: f64| {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let value = (value >= 0.0).then_some(value as u32);
+        value
+    };
+    Ok(UserInfo {
+        username: info.username().into(),
+        uid: id(info.uid()),
+        gid: id(info.gid()),
+        homedir: path::Path::from(info.homedir()),
+        shell: info.shell().map(String::from),
+    })
+}
+
+/// The number of logical CPUs. This is at least 1, even on platforms where
+/// node.js is unable to report any.
+pub fn num_cpus() -> usize {
+    std::cmp::max(ffi::cpus().length(), 1) as usize
+}
+
 /// Path to the temporary directory
 pub fn temp_dir() -> path::Path {
     path::Path::from(ffi::tmpdir())
@@ -46,7 +138,7 @@ pub fn temp_dir() -> path::Path {
 
 /// Low-level bindings for node.js operating system functions
 pub mod ffi {
-    use js_sys::{JsString, Object};
+    use js_sys::{Array, JsString, Object};
     use wasm_bindgen::prelude::*;
 
     #[wasm_bindgen(module = "os")]
@@ -55,10 +147,31 @@ pub mod ffi {
         pub static EOL: Object;
 
         pub fn arch() -> JsString;
+        pub fn cpus() -> Array;
         pub fn homedir() -> JsString;
         pub fn machine() -> JsString;
         pub fn platform() -> JsString;
         pub fn tmpdir() -> JsString;
+
+        #[wasm_bindgen(js_name = "userInfo", catch)]
+        pub fn user_info() -> Result<UserInfo, JsValue>;
+
+        pub type UserInfo;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn username(this: &UserInfo) -> JsString;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn uid(this: &UserInfo) -> f64;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn gid(this: &UserInfo) -> f64;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn homedir(this: &UserInfo) -> JsString;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn shell(this: &UserInfo) -> Option<JsString>;
     }
 }
 
@@ -76,6 +189,13 @@ mod test {
         super::homedir();
     }
 
+    #[wasm_bindgen_test]
+    fn user_info_has_homedir() {
+        let info = super::user_info().expect("Failed to get user info");
+        assert!(!info.homedir.to_string().is_empty());
+        assert!(!info.username.is_empty());
+    }
+
     #[wasm_bindgen_test]
     fn invoke_machine() {
         super::machine();
@@ -86,6 +206,37 @@ mod test {
         super::platform();
     }
 
+    #[wasm_bindgen_test]
+    fn platform_from_name() {
+        use super::Platform;
+
+        for (name, expected) in [
+            ("linux", Platform::Linux),
+            ("darwin", Platform::MacOs),
+            ("win32", Platform::Windows),
+            ("freebsd", Platform::Other("freebsd".into())),
+            ("windows", Platform::Other("windows".into())),
+        ] {
+            assert_eq!(Platform::from(name), expected);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn exe_suffix_for_platform() {
+        use super::Platform;
+
+        assert_eq!(Platform::Windows.exe_suffix(), ".exe");
+        assert_eq!(Platform::Linux.exe_suffix(), "");
+        assert_eq!(Platform::MacOs.exe_suffix(), "");
+        assert_eq!(Platform::Other("freebsd".into()).exe_suffix(), "");
+        assert_eq!(super::exe_suffix(), super::current_platform().exe_suffix());
+    }
+
+    #[wasm_bindgen_test]
+    fn num_cpus_nonzero() {
+        assert!(super::num_cpus() >= 1);
+    }
+
     #[wasm_bindgen_test]
     fn invoke_temp_dir() {
         super::temp_dir();
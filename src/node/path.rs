@@ -67,11 +67,27 @@ impl Path {
         self.inner.clone()
     }
 
-    /// Returns the parent path
+    /// Returns the parent path, or `None` if the path is a root or the current
+    /// directory
     #[must_use]
-    pub fn parent(&self) -> Path {
+    pub fn parent(&self) -> Option<Path> {
         let parent = ffi::dirname(&self.inner);
-        Path { inner: parent }
+        // dirname returns its input unchanged when there is no parent
+        if parent == self.inner {
+            None
+        } else {
+            Some(Path { inner: parent })
+        }
+    }
+
+    /// Returns the path with `.` and `..` components and redundant separators
+    /// collapsed. Paths are normalized on construction, so this is only
+    /// required for paths built from raw JavaScript strings.
+    #[must_use]
+    pub fn normalize(&self) -> Path {
+        Path {
+            inner: ffi::normalize(&self.inner),
+        }
     }
 
     /// Returns `true` if the path is absolute, `false` otherwise
@@ -85,6 +101,39 @@ impl Path {
         result.into()
     }
 
+    /// The extension of the file name without the leading dot. File names
+    /// which begin with their only dot (e.g. `.gitignore`) have no extension,
+    /// and a trailing dot yields an empty extension.
+    pub fn extension(&self) -> Option<String> {
+        let extension = String::from(ffi::extname(&self.inner));
+        extension.strip_prefix('.').map(String::from)
+    }
+
+    /// Returns a path with the extension replaced by the one supplied. If the
+    /// supplied extension is empty, the existing extension is removed.
+    #[must_use]
+    pub fn with_extension(&self, extension: &str) -> Path {
+        let current = ffi::extname(&self.inner);
+        let mut file_name = if current.length() == 0 {
+            self.file_name()
+        } else {
+            ffi::basename(&self.inner, Some(current)).into()
+        };
+        if !extension.is_empty() {
+            file_name = format!("{}.{}", file_name, extension);
+        }
+        let parent = ffi::dirname(&self.inner);
+        Path::from(parent).join(file_name.as_str())
+    }
+
+    /// Returns the path with the executable suffix for this platform appended
+    /// to the file name, e.g. `.exe` on Windows
+    #[must_use]
+    pub fn with_exe_suffix(&self) -> Path {
+        let path = format!("{}{}", self, super::os::exe_suffix());
+        Path::from(path.as_str())
+    }
+
     /// Returns `true` if the path can be determined to exist
     pub async fn exists(&self) -> bool {
         super::fs::ffi::access(&self.inner, None).await.is_ok()
@@ -98,6 +147,73 @@ impl Path {
         result
     }
 
+    /// Joins each of the segments onto this path in order, as chained calls to
+    /// `join` would
+    #[must_use]
+    pub fn join_all<I, S>(&self, segments: I) -> Path
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Path>,
+    {
+        let mut result = self.clone();
+        for segment in segments {
+            result.push(segment);
+        }
+        result
+    }
+
+    /// Splits the normalized path into its components, ignoring redundant
+    /// separators and `.` components. Windows paths are compared
+    /// case-insensitively, matching the behaviour of equality.
+    fn components(&self) -> Vec<String> {
+        let separator = separator();
+        let path = String::from(ffi::normalize(&self.inner));
+        let path = if separator == "\\" { path.to_lowercase() } else { path };
+        path.split(separator.as_ref())
+            .filter(|c| !c.is_empty() && *c != ".")
+            .map(String::from)
+            .collect()
+    }
+
+    /// Returns `true` if `base` is this path or one of its ancestors. Only
+    /// whole components are compared, so `a/bc` does not start with `a/b`.
+    pub fn starts_with(&self, base: &Path) -> bool {
+        self.strip_prefix(base).is_some()
+    }
+
+    /// Returns this path relative to `base`, or `None` if it is not located
+    /// under `base`. Unlike `relative_to`, this never produces a path
+    /// containing `..`.
+    pub fn strip_prefix(&self, base: &Path) -> Option<Path> {
+        if self.is_absolute() != base.is_absolute() {
+            return None;
+        }
+        let components = self.components();
+        let base_components = base.components();
+        if components.len() < base_components.len() || base_components.iter().any(|c| c == "..") {
+            return None;
+        }
+        if components.iter().zip(&base_components).any(|(c, b)| c != b) {
+            return None;
+        }
+        // Take the remainder from the original string to preserve its case
+        let remainder = String::from(ffi::normalize(&self.inner));
+        let remainder: Vec<_> = remainder
+            .split(separator().as_ref())
+            .filter(|c| !c.is_empty() && *c != ".")
+            .skip(base_components.len())
+            .collect();
+        // A relative path such as `../x` normalizes to one which leaves `base`
+        if remainder.contains(&"..") {
+            return None;
+        }
+        if remainder.is_empty() {
+            Some(".".into())
+        } else {
+            Some(remainder.join(separator().as_ref()).as_str().into())
+        }
+    }
+
     /// Returns this path relative to the supplied path
     #[must_use]
     pub fn relative_to<P: Into<Path>>(&self, path: P) -> Path {
@@ -198,6 +314,8 @@ pub mod ffi {
         pub fn relative(from: &JsString, to: &JsString) -> JsString;
         #[wasm_bindgen]
         pub fn basename(path: &JsString, suffix: Option<JsString>) -> JsString;
+        #[wasm_bindgen]
+        pub fn extname(path: &JsString) -> JsString;
     }
 }
 
@@ -220,6 +338,85 @@ mod test {
         assert!(!relative.is_absolute());
     }
 
+    #[wasm_bindgen_test]
+    fn check_normalize() {
+        use itertools::Itertools as _;
+
+        let separator = super::separator();
+        let unnormalized = ["a", ".", "b", "..", "c", ""].into_iter().join(&separator);
+        let path = Path {
+            inner: unnormalized.as_str().into(),
+        };
+        let expected = format!("a{}c{}", separator, separator);
+        assert_eq!(path.normalize().to_string(), expected);
+        assert!(!path.normalize().is_absolute());
+
+        let cwd = node::process::cwd();
+        let absolute = Path {
+            inner: format!("{}{}..{}{}", cwd, separator, separator, cwd.file_name()).into(),
+        };
+        assert!(absolute.is_absolute());
+        assert_eq!(absolute.normalize().to_string(), cwd.to_string());
+
+        if separator == "\\" {
+            // Windows accepts both separators
+            let mixed = Path { inner: "a/b\\c".into() };
+            assert_eq!(mixed.normalize().to_string(), "a\\b\\c");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn check_join_all() {
+        let base = Path::from("base");
+        assert_eq!(base.join_all(["a", "b", "c"]), base.join("a").join("b").join("c"));
+        assert_eq!(base.join_all(Vec::<Path>::new()), base);
+        for separator in ["/", &super::separator()] {
+            let nested = format!("b{}c", separator);
+            assert_eq!(
+                base.join_all(["a", nested.as_str()]).to_string(),
+                base.join("a").join("b").join("c").to_string()
+            );
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn check_strip_prefix() {
+        let cwd = node::process::cwd();
+        let base = cwd.join("base");
+        let nested = base.join_all(["a", "b"]);
+        assert!(nested.starts_with(&base));
+        assert_eq!(nested.strip_prefix(&base), Some(Path::from("a").join("b")));
+        assert_eq!(base.strip_prefix(&base), Some(Path::from(".")));
+        assert!(!base.starts_with(&nested));
+        assert_eq!(base.strip_prefix(&nested), None);
+
+        let sibling = cwd.join("base-sibling").join("a");
+        assert!(!sibling.starts_with(&base));
+        assert_eq!(sibling.strip_prefix(&base), None);
+        let escaped = base.join("..").join("outside");
+        assert_eq!(escaped.strip_prefix(&base), None);
+
+        assert_eq!(
+            Path::from("a").join("b").strip_prefix(&Path::from("a")),
+            Some(Path::from("b"))
+        );
+        assert_eq!(Path::from("a").strip_prefix(&base), None);
+        assert_eq!(Path::from("a").strip_prefix(&Path::from("..")), None);
+        assert_eq!(Path::from("..").join("x").strip_prefix(&Path::from(".")), None);
+        assert_eq!(
+            Path::from("a").join("..").join("..").strip_prefix(&Path::from(".")),
+            None
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn check_with_exe_suffix() {
+        let path = Path::from("bin").join("tool");
+        let expected = format!("tool{}", node::os::exe_suffix());
+        assert_eq!(path.with_exe_suffix().file_name(), expected);
+        assert_eq!(path.with_exe_suffix().parent(), path.parent());
+    }
+
     #[wasm_bindgen_test]
     fn check_separator() {
         let separator = super::separator();
@@ -236,10 +433,28 @@ mod test {
     fn check_parent() {
         let parent_name = "parent";
         let path = Path::from(&format!("{}{}{}", parent_name, super::separator(), "child"));
-        let parent_path = path.parent();
+        let parent_path = path.parent().expect("Path should have a parent");
         assert_eq!(parent_path.to_string(), parent_name);
     }
 
+    #[wasm_bindgen_test]
+    fn check_parent_absolute_and_relative() {
+        let separator = super::separator();
+        let cwd = node::process::cwd();
+        let child = cwd.join("child");
+        assert_eq!(child.parent(), Some(cwd));
+
+        let mut root = node::process::cwd();
+        while let Some(parent) = root.parent() {
+            root = parent;
+        }
+        assert!(root.is_absolute());
+        assert!(root.to_string().ends_with(separator.as_ref()));
+
+        assert_eq!(Path::from("child").parent().map(|p| p.to_string()), Some(".".into()));
+        assert!(Path::from(".").parent().is_none());
+    }
+
     #[wasm_bindgen_test]
     fn check_basename() {
         let child_base = "child.";
@@ -261,6 +476,32 @@ mod test {
         );
     }
 
+    #[wasm_bindgen_test]
+    fn check_extension() {
+        let parent = Path::from("parent");
+        assert_eq!(parent.join("file.rlib").extension().as_deref(), Some("rlib"));
+        assert_eq!(parent.join("archive.tar.gz").extension().as_deref(), Some("gz"));
+        assert_eq!(parent.join("file").extension(), None);
+        assert_eq!(parent.join(".gitignore").extension(), None);
+        assert_eq!(parent.join("file.").extension().as_deref(), Some(""));
+    }
+
+    #[wasm_bindgen_test]
+    fn check_with_extension() {
+        let parent = Path::from("parent");
+        assert_eq!(
+            parent.join("file.rlib").with_extension("rmeta"),
+            parent.join("file.rmeta")
+        );
+        assert_eq!(parent.join("file").with_extension("txt"), parent.join("file.txt"));
+        assert_eq!(parent.join("file.txt").with_extension(""), parent.join("file"));
+        assert_eq!(
+            parent.join(".gitignore").with_extension("bak"),
+            parent.join(".gitignore.bak")
+        );
+        assert_eq!(Path::from("file.txt").with_extension("md"), Path::from("file.md"));
+    }
+
     #[wasm_bindgen_test]
     fn check_push() {
         let parent_name = "a";
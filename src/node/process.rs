@@ -1,5 +1,6 @@
 use super::path::{self, Path};
 use std::collections::HashMap;
+use std::time::Duration;
 use wasm_bindgen::JsValue;
 
 /// Returns the current working directory of the process
@@ -25,6 +26,13 @@ pub fn get_env() -> HashMap<String, String> {
     env
 }
 
+/// Returns the value of an environment variable, if it is set
+pub fn get_var(name: &str) -> Option<String> {
+    js_sys::Reflect::get(&ffi::ENV, &name.into())
+        .expect("process.env wasn't an object")
+        .as_string()
+}
+
 /// Set an environment variable to a specified value
 pub fn set_var(name: &str, value: &str) {
     use js_sys::{JsString, Map, Object};
@@ -45,6 +53,11 @@ pub fn remove_var(name: &str) {
     js_sys::Reflect::delete_property(&ffi::ENV, &name.into()).expect("process.env wasn't an object");
 }
 
+/// Returns the absolute path of the node.js executable running this process
+pub fn exec_path() -> Path {
+    Path::from(ffi::EXEC_PATH.clone())
+}
+
 /// Changes the current working directory to the specified path
 pub fn chdir<P: Into<Path>>(path: P) -> Result<(), JsValue> {
     let path = path.into();
@@ -52,9 +65,49 @@ pub fn chdir<P: Into<Path>>(path: P) -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Returns the time elapsed since an arbitrary point in the past. Unlike the
+/// system clock this is monotonic, so should be used to measure durations.
+pub fn hrtime() -> Duration {
+    let nanos: u64 = ffi::hrtime_bigint().try_into().expect("hrtime too large");
+    Duration::from_nanos(nanos)
+}
+
+/// Memory usage of the node.js process in bytes
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryUsage {
+    /// Resident set size, including code and stacks as well as the heap
+    pub rss: u64,
+
+    /// Total size of the V8 heap
+    pub heap_total: u64,
+
+    /// Portion of the V8 heap in use
+    pub heap_used: u64,
+
+    /// Memory used by C++ objects bound to JavaScript objects, which includes
+    /// WebAssembly memories
+    pub external: u64,
+}
+
+/// Returns the current memory usage of the process
+pub fn memory_usage() -> MemoryUsage {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn to_bytes(value: f64) -> u64 {
+        value as u64
+    }
+
+    let usage = ffi::memory_usage();
+    MemoryUsage {
+        rss: to_bytes(usage.rss()),
+        heap_total: to_bytes(usage.heap_total()),
+        heap_used: to_bytes(usage.heap_used()),
+        external: to_bytes(usage.external()),
+    }
+}
+
 /// Low-level bindings for node.js process functions and variables
 pub mod ffi {
-    use js_sys::{JsString, Object};
+    use js_sys::{BigInt, JsString, Object};
     use wasm_bindgen::prelude::*;
 
     #[wasm_bindgen(module = "process")]
@@ -62,10 +115,34 @@ pub mod ffi {
         #[wasm_bindgen(js_name = "env")]
         pub static ENV: Object;
 
+        #[wasm_bindgen(js_name = "execPath")]
+        pub static EXEC_PATH: JsString;
+
         pub fn cwd() -> JsString;
 
         #[wasm_bindgen(catch)]
         pub fn chdir(path: &JsString) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(js_namespace = hrtime, js_name = "bigint")]
+        pub fn hrtime_bigint() -> BigInt;
+
+        #[wasm_bindgen(js_name = "memoryUsage")]
+        pub fn memory_usage() -> MemoryUsage;
+
+        #[derive(Debug)]
+        pub type MemoryUsage;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn rss(this: &MemoryUsage) -> f64;
+
+        #[wasm_bindgen(method, getter, js_name = "heapTotal")]
+        pub fn heap_total(this: &MemoryUsage) -> f64;
+
+        #[wasm_bindgen(method, getter, js_name = "heapUsed")]
+        pub fn heap_used(this: &MemoryUsage) -> f64;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn external(this: &MemoryUsage) -> f64;
     }
 }
 
@@ -78,9 +155,44 @@ mod test {
         super::get_env();
     }
 
+    #[wasm_bindgen_test]
+    fn get_var_sees_set_var() {
+        let name = "FERROUS_ACTIONS_GET_VAR_TEST";
+        super::set_var(name, "value");
+        assert_eq!(super::get_var(name).as_deref(), Some("value"));
+        super::remove_var(name);
+        assert_eq!(super::get_var(name), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn exec_path_exists() {
+        assert!(super::exec_path().exists().await);
+    }
+
     #[wasm_bindgen_test]
     async fn invoke_cwd() {
         let cwd = super::cwd();
         assert!(cwd.exists().await);
     }
+
+    #[wasm_bindgen_test]
+    fn invoke_memory_usage() {
+        let usage = super::memory_usage();
+        assert!(usage.rss > 0);
+        assert!(usage.heap_used <= usage.heap_total);
+    }
+
+    #[cfg(feature = "github_actions_bindings")]
+    #[wasm_bindgen_test]
+    async fn hrtime_measures_sleep() {
+        use std::time::Duration;
+
+        let sleep_time = Duration::from_millis(50);
+        let start = super::hrtime();
+        crate::system::sleep::sleep(&sleep_time).await;
+        let elapsed = super::hrtime().saturating_sub(start);
+        // Timers may fire marginally early but not by a whole millisecond
+        assert!(elapsed + Duration::from_millis(1) >= sleep_time);
+        assert!(elapsed < Duration::from_secs(10));
+    }
 }
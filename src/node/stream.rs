@@ -1,3 +1,29 @@
+use js_sys::Uint8Array;
+use wasm_bindgen::{JsCast as _, JsError, JsValue};
+
+/// Pipes a readable stream into a writable one, completing once all data has
+/// been written and both streams have closed
+pub async fn pipe(readable: &ffi::Readable, writable: &ffi::Writable) -> Result<(), JsValue> {
+    ffi::pipeline(readable, writable).await?;
+    Ok(())
+}
+
+/// Reads a readable stream to completion, returning its contents
+pub async fn collect(readable: &ffi::Readable) -> Result<Vec<u8>, JsValue> {
+    let buffer = ffi::buffer(readable).await?;
+    let buffer = buffer
+        .dyn_ref::<Uint8Array>()
+        .ok_or_else(|| JsError::new("buffer didn't return an array"))?;
+    Ok(buffer.to_vec())
+}
+
+/// Ends a writable stream and waits until all data has been flushed
+pub async fn end(writable: &ffi::Writable) -> Result<(), JsValue> {
+    writable.end();
+    ffi::finished(writable).await?;
+    Ok(())
+}
+
 /// Low-level bindings for node.js stream functions and variables
 pub mod ffi {
     use js_sys::Object;
@@ -5,18 +31,45 @@ pub mod ffi {
 
     #[wasm_bindgen(module = "stream")]
     extern "C" {
+        #[derive(Debug)]
+        #[wasm_bindgen(js_name = "Readable")]
+        pub type Readable;
+
         #[derive(Debug)]
         #[wasm_bindgen(js_name = "Writable")]
         pub type Writable;
 
         #[wasm_bindgen(constructor)]
         pub fn new(options: Option<Object>) -> Writable;
+
+        #[wasm_bindgen(method)]
+        pub fn write(this: &Writable, chunk: &JsValue) -> bool;
+
+        #[wasm_bindgen(method)]
+        pub fn end(this: &Writable);
+    }
+
+    #[wasm_bindgen(module = "stream/promises")]
+    extern "C" {
+        #[wasm_bindgen(catch)]
+        pub async fn finished(stream: &JsValue) -> Result<JsValue, JsValue>;
+
+        #[wasm_bindgen(catch)]
+        pub async fn pipeline(source: &Readable, destination: &Writable) -> Result<JsValue, JsValue>;
+    }
+
+    #[wasm_bindgen(module = "stream/consumers")]
+    extern "C" {
+        #[wasm_bindgen(catch)]
+        pub async fn buffer(stream: &Readable) -> Result<JsValue, JsValue>;
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::actions::io;
+    use crate::node;
     use js_sys::Object;
     use wasm_bindgen_test::wasm_bindgen_test;
 
@@ -25,4 +78,25 @@ mod test {
         let options = Object::default();
         ffi::Writable::new(Some(options));
     }
+
+    #[wasm_bindgen_test]
+    async fn pipe_and_collect_file() -> Result<(), JsValue> {
+        let dir = node::os::temp_dir().join(&format!("ferrous-actions-stream-test - {}", chrono::Local::now()));
+        node::fs::create_dir_all(&dir).await?;
+        let source = dir.join("source");
+        let destination = dir.join("destination");
+        #[allow(clippy::cast_possible_truncation)]
+        let data: Vec<u8> = (0..256 * 1024).map(|i: u32| (i % 251) as u8).collect();
+        node::fs::write_file(&source, &data).await?;
+
+        let readable = node::fs::create_read_stream(&source);
+        let writable = node::fs::create_write_stream(&destination);
+        pipe(&readable, &writable).await?;
+        assert_eq!(node::fs::read_file(&destination).await?, data);
+
+        let collected = collect(&node::fs::create_read_stream(&destination)).await?;
+        assert_eq!(collected, data);
+        io::rm_rf(&dir).await?;
+        Ok(())
+    }
 }
@@ -1,7 +1,83 @@
-use rustup_toolchain_manifest::HashValue;
+use crate::system::rng;
+use crate::Error;
+use std::fmt;
+use std::str::FromStr;
 
-pub fn build(num_bytes: usize) -> HashValue {
-    let mut bytes = vec![0u8; num_bytes];
-    getrandom::getrandom(&mut bytes).unwrap_or_else(|e| panic!("Unable to get random data: {}", e));
-    HashValue::from_bytes(&bytes)
+const DEFAULT_NUM_BYTES: usize = 8;
+
+/// A random value used to make names and keys unique
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Nonce {
+    bytes: Vec<u8>,
+}
+
+impl Nonce {
+    /// Generates a nonce of the default length
+    pub fn generate() -> Nonce {
+        Self::with_length(DEFAULT_NUM_BYTES)
+    }
+
+    /// Generates a nonce containing `num_bytes` random bytes
+    pub fn with_length(num_bytes: usize) -> Nonce {
+        let mut bytes = vec![0u8; num_bytes];
+        rng::fill_bytes(&mut bytes);
+        Nonce { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl fmt::Display for Nonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Lower-case hex since nonces are used in paths on filesystems which
+        // might not be case sensitive
+        for byte in &self.bytes {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Nonce {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Nonce, Error> {
+        let invalid = || Error::NonceParse(s.to_string());
+        if s.is_empty() {
+            return Err(invalid());
+        }
+        let bytes = s
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| match std::str::from_utf8(pair) {
+                Ok(pair) if pair.len() == 2 && pair.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                    Ok(u8::from_str_radix(pair, 16).expect("Hex digits should parse"))
+                }
+                _ => Err(invalid()),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Nonce { bytes })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Nonce;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn generated_nonces_differ_and_round_trip() {
+        let first = Nonce::generate();
+        let second = Nonce::generate();
+        assert_ne!(first, second);
+        assert_eq!(first.as_bytes().len(), 8);
+        let parsed: Nonce = first.to_string().parse().expect("Failed to parse nonce");
+        assert_eq!(parsed, first);
+        assert!("0g".parse::<Nonce>().is_err());
+        assert!("abc".parse::<Nonce>().is_err());
+        assert!("+a".parse::<Nonce>().is_err());
+        assert!("".parse::<Nonce>().is_err());
+    }
 }
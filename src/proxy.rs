@@ -0,0 +1,98 @@
+use crate::actions::exec::Command;
+use crate::cargo_hooks::Hook as CargoHook;
+use crate::input_manager::{self, Input};
+use crate::node;
+use async_trait::async_trait;
+
+/// Proxy servers supplied as inputs. Any proxy not supplied is inherited from
+/// the environment.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Proxy {
+    http: Option<String>,
+    https: Option<String>,
+}
+
+impl Proxy {
+    pub fn from_input(input_manager: &input_manager::Manager) -> Proxy {
+        Proxy {
+            http: input_manager.get(Input::HttpProxy).map(String::from),
+            https: input_manager.get(Input::HttpsProxy).map(String::from),
+        }
+    }
+
+    fn vars(&self) -> Vec<(&'static str, &str)> {
+        // Tools disagree on whether these should be upper or lower case, so we set both
+        let mut result = Vec::new();
+        for (names, value) in [
+            (["HTTP_PROXY", "http_proxy"], &self.http),
+            (["HTTPS_PROXY", "https_proxy"], &self.https),
+        ] {
+            if let Some(value) = value {
+                result.extend(names.into_iter().map(|name| (name, value.as_str())));
+            }
+        }
+        result
+    }
+
+    /// Sets the proxy variables on a command
+    pub fn apply(&self, command: &mut Command) {
+        command.envs(self.vars());
+    }
+
+    /// Sets the proxy variables for this process, and therefore for downloads
+    /// it performs and any processes it spawns
+    pub fn export(&self) {
+        for (name, value) in self.vars() {
+            node::process::set_var(name, value);
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl CargoHook for Proxy {
+    fn modify_command(&self, command: &mut Command) {
+        self.apply(command);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Proxy;
+    use crate::actions::exec::Command;
+    use crate::actions::io;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn unset_proxies_are_inherited() {
+        assert!(Proxy::default().vars().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn command_sees_proxy() -> Result<(), JsValue> {
+        let proxy = Proxy {
+            http: None,
+            https: Some("http://proxy.example.com:3128".into()),
+        };
+        let node = io::which("node", true).await?;
+        let output: Arc<Mutex<String>> = Arc::default();
+        let output_captured = output.clone();
+        let mut command = Command::from(&node);
+        command.args([
+            "-e",
+            "process.stdout.write(`${process.env.HTTPS_PROXY} ${process.env.https_proxy}`)",
+        ]);
+        proxy.apply(&mut command);
+        command
+            .outline(move |line| *output_captured.lock() += line)
+            .exec()
+            .await?;
+        assert_eq!(
+            output.lock().as_str(),
+            "http://proxy.example.com:3128 http://proxy.example.com:3128"
+        );
+        Ok(())
+    }
+}
@@ -1,14 +1,25 @@
-use crate::cache_cargo_home::{restore_cargo_cache, save_cargo_cache};
+use crate::actions::io;
+use crate::cache_cargo_home::{report_cache_keys, restore_cargo_cache, save_cargo_cache};
 use crate::cross::Cross;
 use crate::input_manager::{Input, Manager as InputManager};
+use crate::proxy::Proxy;
 use crate::rustup::{self, ToolchainConfig};
-use crate::{node, toolchain, warning, Cargo, Error};
+use crate::sccache::{self, Sccache};
+use crate::system::concurrency;
+use crate::{cache_clean, cache_key_builder, debug, info, install_cache, node, toolchain, warning, Cargo, Error};
 
-fn get_toolchain_config(input_manager: &InputManager) -> Result<ToolchainConfig, Error> {
-    let mut toolchain_config = ToolchainConfig::default();
-    if let Some(toolchain) = input_manager.get(Input::Toolchain) {
-        toolchain_config.name = toolchain.into();
-    }
+async fn get_toolchain_config(input_manager: &InputManager) -> Result<ToolchainConfig, Error> {
+    let mut toolchain_config = if let Some(toolchain) = input_manager.get(Input::Toolchain) {
+        ToolchainConfig {
+            name: toolchain.into(),
+            ..ToolchainConfig::default()
+        }
+    } else if let Some(path) = rustup::find_toolchain_file(&node::process::cwd()).await {
+        info!("Reading toolchain configuration from {}", path);
+        rustup::read_toolchain_file(&path).await?
+    } else {
+        ToolchainConfig::default()
+    };
     if let Some(profile) = input_manager.get(Input::Profile) {
         toolchain_config.profile = profile.into();
     }
@@ -18,18 +29,20 @@ fn get_toolchain_config(input_manager: &InputManager) -> Result<ToolchainConfig,
     if let Some(targets) = input_manager.get(Input::Targets) {
         toolchain_config.targets = targets.split_whitespace().map(String::from).collect();
     }
-    if let Some(set_default) = input_manager.get(Input::Default) {
-        let set_default = set_default
-            .parse::<bool>()
-            .map_err(|_| Error::OptionParseError(Input::Default.to_string(), set_default.to_string()))?;
+    if let Some(set_default) = input_manager.get_bool(Input::Default)? {
         toolchain_config.set_default = set_default;
     }
-    if let Some(set_override) = input_manager.get(Input::Override) {
-        let set_override = set_override
-            .parse::<bool>()
-            .map_err(|_| Error::OptionParseError(Input::Override.to_string(), set_override.to_string()))?;
+    if let Some(set_override) = input_manager.get_bool(Input::Override)? {
         toolchain_config.set_override = set_override;
     }
+    toolchain_config.rustup_version = input_manager.get(Input::RustupVersion).map(String::from);
+    if let Some(self_update) = input_manager.get_bool(Input::RustupSelfUpdate)? {
+        toolchain_config.rustup_self_update = self_update;
+    }
+    if let Some(offline) = input_manager.get_bool(Input::Offline)? {
+        toolchain_config.offline = offline;
+    }
+    toolchain_config.validate()?;
     Ok(toolchain_config)
 }
 
@@ -53,64 +66,157 @@ pub async fn run() -> Result<(), Error> {
     }
 }
 
+fn use_sccache(input_manager: &InputManager) -> Result<bool, Error> {
+    Ok(input_manager.get_bool(Input::Sccache)?.unwrap_or(false))
+}
+
+fn configure_cache_keys(input_manager: &InputManager) {
+    cache_key_builder::set_user_version(input_manager.get(Input::CacheVersion));
+}
+
+fn configure_concurrency(input_manager: &InputManager) -> Result<(), Error> {
+    concurrency::configure(input_manager)
+}
+
+async fn get_cargo(input_manager: &InputManager) -> Result<Cargo, Error> {
+    if input_manager.get_bool(Input::UseCross)?.unwrap_or(false) {
+        let cross = Cross::get_or_install(input_manager).await?;
+        Cargo::from_cross(&cross).await
+    } else if let Some(cargo_path) = input_manager.get(Input::CargoPath) {
+        let path = io::which(cargo_path, true).await.map_err(Error::Js)?;
+        let cargo = Cargo::from_path(&path).await?;
+        info!("Using cargo at {}", cargo.get_path());
+        Ok(cargo)
+    } else {
+        if rustup::proxy_dir().is_some() {
+            match Cargo::from_rustup_proxy().await {
+                Ok(cargo) => {
+                    info!("Using rustup proxy for cargo at {}", cargo.get_path());
+                    return Ok(cargo);
+                }
+                Err(e) => debug!("Unable to locate rustup proxy for cargo: {}", e),
+            }
+        }
+        Cargo::from_environment().await
+    }
+}
+
 pub async fn main() -> Result<(), Error> {
     let input_manager = InputManager::build()?;
+    configure_cache_keys(&input_manager);
+    configure_concurrency(&input_manager)?;
+    // Downloads and cache transfers performed by the action use the proxy too
+    Proxy::from_input(&input_manager).export();
     let command = input_manager.get_required(Input::Command)?;
     let split: Vec<&str> = command.split_whitespace().collect();
     match split[..] {
         ["install-rustup"] => {
-            let toolchain_config = get_toolchain_config(&input_manager)?;
+            let toolchain_config = get_toolchain_config(&input_manager).await?;
             rustup::install(&toolchain_config).await?;
         }
         ["install-toolchain"] => {
-            let toolchain_config = get_toolchain_config(&input_manager)?;
+            let toolchain_config = get_toolchain_config(&input_manager).await?;
             toolchain::install(&toolchain_config).await?;
         }
+        ["add-component"] => {
+            let components = input_manager.get_required(Input::Components)?;
+            let components: Vec<_> = components.split_whitespace().map(String::from).collect();
+            rustup::add_components(input_manager.get(Input::Toolchain), &components).await?;
+        }
         ["cargo", cargo_subcommand] => {
-            let use_cross = if let Some(use_cross) = input_manager.get(Input::UseCross) {
-                use_cross
-                    .parse::<bool>()
-                    .map_err(|_| Error::OptionParseError("use-cross".into(), use_cross.to_string()))?
-            } else {
-                false
-            };
-            let mut cargo = if use_cross {
-                let cross = Cross::get_or_install(&input_manager).await?;
-                Cargo::from_path(&cross.get_path()).await?
-            } else {
-                Cargo::from_environment().await?
-            };
+            if use_sccache(&input_manager)? {
+                let sccache = Sccache::get_or_install().await?;
+                info!("Using sccache at {}", sccache.get_path());
+                sccache.export();
+            }
+            let mut cargo = get_cargo(&input_manager).await?;
             let cargo_args = input_manager.get(Input::Args).unwrap_or_default();
             let cargo_args =
                 shlex::split(cargo_args).ok_or_else(|| Error::ArgumentsParseError(cargo_args.to_string()))?;
             let toolchain = input_manager.get(Input::Toolchain);
-            cargo
-                .run(
-                    toolchain,
-                    cargo_subcommand,
-                    cargo_args.iter().map(String::as_str),
-                    &input_manager,
-                )
-                .await?;
+            if cargo_subcommand == "install" {
+                install_cache::install(&mut cargo, toolchain, &cargo_args, &input_manager).await?;
+            } else {
+                cargo
+                    .run(
+                        toolchain,
+                        cargo_subcommand,
+                        cargo_args.iter().map(String::as_str),
+                        &input_manager,
+                    )
+                    .await?;
+            }
         }
         ["cache"] => restore_cargo_cache(&input_manager).await?,
+        ["cache", "clean"] => cache_clean::clean(&input_manager).await?,
+        ["cache", "keys"] => report_cache_keys(&input_manager).await?,
         _ => return Err(Error::UnknownCommand(command.to_string())),
     }
 
     for input in input_manager.unused() {
         warning!("Recognised but unused input {} was passed to action", input);
     }
+    for input in input_manager.unknown() {
+        warning!("Unknown input {} was passed to action", input);
+    }
     Ok(())
 }
 
 pub async fn post() -> Result<(), Error> {
     let input_manager = InputManager::build()?;
+    configure_cache_keys(&input_manager);
+    configure_concurrency(&input_manager)?;
+    // Downloads and cache transfers performed by the action use the proxy too
+    Proxy::from_input(&input_manager).export();
     let command = input_manager.get_required(Input::Command)?;
     let split: Vec<&str> = command.split_whitespace().collect();
-    #[allow(clippy::single_match)]
     match split[..] {
         ["cache"] => save_cargo_cache(&input_manager).await?,
+        ["cargo", _] if use_sccache(&input_manager)? => sccache::report_stats().await?,
         _ => {}
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::get_cargo;
+    use crate::actions::io;
+    use crate::input_manager::Manager as InputManager;
+    use crate::node;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    const CARGO_PATH_VAR: &str = "INPUT_CARGO-PATH";
+
+    #[wasm_bindgen_test]
+    async fn cargo_path_selects_cargo() -> Result<(), JsValue> {
+        // Any binary will do since it is only located, not run
+        let node_path = io::which("node", true).await?;
+        node::process::set_var(CARGO_PATH_VAR, &node_path.to_string());
+        let input_manager = InputManager::build();
+        node::process::remove_var(CARGO_PATH_VAR);
+        let cargo = get_cargo(&input_manager.expect("Failed to build input manager"))
+            .await
+            .expect("Failed to select cargo");
+        assert_eq!(cargo.get_path(), node_path);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn cargo_found_on_path_by_default() -> Result<(), JsValue> {
+        let input_manager = InputManager::build().expect("Failed to build input manager");
+        let cargo = get_cargo(&input_manager).await.expect("Failed to select cargo");
+        assert_eq!(cargo.get_path(), io::which("cargo", true).await?);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn missing_cargo_path_is_error() {
+        node::process::set_var(CARGO_PATH_VAR, "ferrous-actions-no-such-cargo");
+        let input_manager = InputManager::build();
+        node::process::remove_var(CARGO_PATH_VAR);
+        let input_manager = input_manager.expect("Failed to build input manager");
+        assert!(get_cargo(&input_manager).await.is_err());
+    }
+}
@@ -1,21 +1,157 @@
 use crate::actions::exec::Command;
 use crate::actions::{core, io, tool_cache};
+use crate::delta::{render_list as render_delta_list, Action as DeltaAction};
 use crate::node::path::Path;
-use crate::{debug, info, node, Error};
+use crate::{debug, info, node, warning, Error};
 use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
 const NO_DEFAULT_TOOLCHAIN_NAME: &str = "none";
+const LEGACY_TOOLCHAIN_FILE_NAME: &str = "rust-toolchain";
+const TOOLCHAIN_FILE_NAME: &str = "rust-toolchain.toml";
+const ADDED_COMPONENTS_VAR: &str = "FERROUS_ACTIONS_ADDED_COMPONENTS";
+const PROXY_DIR_VAR: &str = "FERROUS_ACTIONS_RUSTUP_PROXY_DIR";
+const PROFILES: [&str; 3] = ["minimal", "default", "complete"];
+const KNOWN_COMPONENTS: [&str; 17] = [
+    "cargo",
+    "clippy",
+    "llvm-bitcode-linker",
+    "llvm-tools",
+    "miri",
+    "rls",
+    "rust-analysis",
+    "rust-analyzer",
+    "rust-docs",
+    "rust-mingw",
+    "rust-src",
+    "rust-std",
+    "rustc",
+    "rustc-codegen-cranelift",
+    "rustc-dev",
+    "rustc-docs",
+    "rustfmt",
+];
 
 pub async fn install(toolchain_config: &ToolchainConfig) -> Result<(), Error> {
-    let rustup = Rustup::get_or_install().await?;
+    let pinned_version = toolchain_config.rustup_version.as_deref();
+    let rustup = match pinned_version {
+        Some(version) => Rustup::get_or_install_version(version).await?,
+        None => Rustup::get_or_install().await?,
+    };
     debug!("Rustup installed at: {}", rustup.get_path());
-    rustup.update().await?;
+    if toolchain_config.offline {
+        info!("Offline mode enabled, so not updating rustup or installed toolchains");
+    } else {
+        if toolchain_config.rustup_self_update {
+            rustup.self_update().await?;
+        }
+        // Updating toolchains would otherwise also update rustup, undoing any pin
+        rustup.update(pinned_version.is_none()).await?;
+    }
+    let toolchain = Some(toolchain_config.name.as_str()).filter(|name| *name != NO_DEFAULT_TOOLCHAIN_NAME);
+    let before = match toolchain {
+        Some(toolchain) => rustup.list_installed(Some(toolchain)).await?,
+        None => BTreeSet::new(),
+    };
     rustup.install_toolchain(toolchain_config).await?;
+    if let Some(toolchain) = toolchain {
+        let after = rustup.list_installed(Some(toolchain)).await?;
+        report_installed_delta(&before, &after).await;
+    }
+    // Rustup installs its proxies for cargo and friends alongside itself
+    if let Some(proxy_dir) = rustup.get_path().parent() {
+        core::export_variable(PROXY_DIR_VAR, proxy_dir.to_string());
+    }
+    if toolchain_config.name != NO_DEFAULT_TOOLCHAIN_NAME {
+        crate::cargo::set_version_outputs(Some(&toolchain_config.name)).await?;
+    }
+    Ok(())
+}
+
+/// Adds components to an already installed toolchain (the active one if
+/// `toolchain` is `None`) and records them for use in the target directory
+/// cache key. Rustup toolchains are not themselves cached, so there is no
+/// toolchain key to update.
+pub async fn add_components(toolchain: Option<&str>, components: &[String]) -> Result<(), Error> {
+    for component in components.iter().filter(|c| !is_known_component(c)) {
+        warning!("Component {} is not a known rustup component", component);
+    }
+    let rustup = Rustup::get_or_install().await?;
+    let before = rustup.list_installed(toolchain).await?;
+    rustup.add_components(toolchain, components).await?;
+    let after = rustup.list_installed(toolchain).await?;
+    report_installed_delta(&before, &after).await;
+    let mut recorded = added_components();
+    recorded.extend(components.iter().cloned());
+    recorded.sort();
+    recorded.dedup();
+    core::export_variable(ADDED_COMPONENTS_VAR, recorded.join(" "));
     Ok(())
 }
 
+/// The components added by previous invocations of `add_components` in this
+/// job, sorted and without duplicates
+pub fn added_components() -> Vec<String> {
+    node::process::get_var(ADDED_COMPONENTS_VAR)
+        .map(|value| value.split_whitespace().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// The version reported by the rustup on the path, if there is one
+pub async fn installed_version() -> Option<String> {
+    let rustup = Rustup::get().await.ok()?;
+    rustup.version().await.unwrap_or_else(|e| {
+        debug!("Unable to determine rustup version: {}", e);
+        None
+    })
+}
+
+/// The directory holding rustup's proxy binaries, if a toolchain was
+/// installed via rustup earlier in this job
+pub fn proxy_dir() -> Option<Path> {
+    node::process::get_var(PROXY_DIR_VAR).map(|dir| Path::from(dir.as_str()))
+}
+
+/// Determines which components and targets were added and removed
+fn installed_delta(before: &BTreeSet<String>, after: &BTreeSet<String>) -> Vec<(String, DeltaAction)> {
+    let added = after.difference(before).map(|item| (item.clone(), DeltaAction::Added));
+    let removed = before
+        .difference(after)
+        .map(|item| (item.clone(), DeltaAction::Removed));
+    let mut delta: Vec<_> = added.chain(removed).collect();
+    delta.sort_by(|(a, _), (b, _)| a.cmp(b));
+    delta
+}
+
+/// Logs the components and targets which changed and adds them to the job
+/// summary
+async fn report_installed_delta(before: &BTreeSet<String>, after: &BTreeSet<String>) {
+    let delta = installed_delta(before, after);
+    if delta.is_empty() {
+        info!("No components or targets were added or removed");
+        return;
+    }
+    let rendered = render_delta_list(&delta);
+    info!("Installed components and targets changed:\n{}", rendered);
+    let summary = format!("### Toolchain components and targets changed\n\n```\n{}```", rendered);
+    if let Err(e) = core::append_summary(summary).await {
+        warning!("Unable to write toolchain summary: {:?}", e);
+    }
+}
+
+fn component_add_args(toolchain: Option<&str>, components: &[String]) -> Vec<String> {
+    let mut args: Vec<_> = ["component", "add"].into_iter().map(String::from).collect();
+    if let Some(toolchain) = toolchain {
+        args.extend(["--toolchain".into(), toolchain.into()]);
+    }
+    args.extend(components.iter().cloned());
+    args
+}
+
 #[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ToolchainConfig {
     pub name: String,
     pub profile: String,
@@ -23,6 +159,28 @@ pub struct ToolchainConfig {
     pub targets: Vec<String>,
     pub set_default: bool,
     pub set_override: bool,
+    pub rustup_version: Option<String>,
+    pub rustup_self_update: bool,
+    pub offline: bool,
+}
+
+impl ToolchainConfig {
+    /// Checks the profile is one rustup knows about, and warns about any
+    /// unrecognised components since new ones are occasionally added
+    pub fn validate(&self) -> Result<(), Error> {
+        if !PROFILES.contains(&self.profile.as_str()) {
+            return Err(Error::OptionParseError("profile".into(), self.profile.clone()));
+        }
+        for component in self.components.iter().filter(|c| !is_known_component(c)) {
+            warning!("Component {} is not a known rustup component", component);
+        }
+        Ok(())
+    }
+}
+
+fn is_known_component(name: &str) -> bool {
+    let name = name.strip_suffix("-preview").unwrap_or(name);
+    KNOWN_COMPONENTS.contains(&name)
 }
 
 impl Default for ToolchainConfig {
@@ -34,10 +192,89 @@ impl Default for ToolchainConfig {
             targets: Vec::new(),
             set_default: true,
             set_override: false,
+            rustup_version: None,
+            rustup_self_update: false,
+            offline: false,
         }
     }
 }
 
+/// Extracts the version from the output of `rustup --version`
+fn parse_version_line(line: &str) -> Option<String> {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("rustup"), Some(version)) => Some(version.into()),
+        _ => None,
+    }
+}
+
+fn rustup_init_url(version: &str) -> Result<String, Error> {
+    let target = crate::toolchain::default_target_for_platform()?;
+    Ok(format!(
+        "https://static.rust-lang.org/rustup/archive/{}/{}/rustup-init{}",
+        version,
+        target,
+        node::os::exe_suffix()
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolchainFile {
+    toolchain: ToolchainFileSection,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolchainFileSection {
+    channel: Option<String>,
+    profile: Option<String>,
+    #[serde(default)]
+    components: Vec<String>,
+    #[serde(default)]
+    targets: Vec<String>,
+}
+
+fn parse_toolchain_file(content: &str, legacy_permitted: bool) -> Result<ToolchainConfig, Error> {
+    let mut config = ToolchainConfig::default();
+    let trimmed = content.trim();
+    if legacy_permitted && trimmed.lines().count() == 1 && !trimmed.contains('=') {
+        // A legacy toolchain file contains only the channel name
+        config.name = trimmed.into();
+        return Ok(config);
+    }
+    let file: ToolchainFile = basic_toml::from_str(content).map_err(|e| Error::ToolchainFileParse(e.to_string()))?;
+    let section = file.toolchain;
+    config.name = section
+        .channel
+        .ok_or_else(|| Error::ToolchainFileParse("No toolchain channel specified".into()))?;
+    if let Some(profile) = section.profile {
+        config.profile = profile;
+    }
+    config.components = section.components;
+    config.targets = section.targets;
+    Ok(config)
+}
+
+/// Reads a `rust-toolchain.toml` or legacy `rust-toolchain` file
+pub async fn read_toolchain_file(path: &Path) -> Result<ToolchainConfig, Error> {
+    let content = node::fs::read_file_string(path).await?;
+    let legacy_permitted = path.file_name() == LEGACY_TOOLCHAIN_FILE_NAME;
+    parse_toolchain_file(&content, legacy_permitted)
+}
+
+/// Searches the specified folder and its ancestors for a toolchain file
+pub async fn find_toolchain_file(path: &Path) -> Option<Path> {
+    let mut current = path.clone();
+    loop {
+        for file_name in [LEGACY_TOOLCHAIN_FILE_NAME, TOOLCHAIN_FILE_NAME] {
+            let candidate = current.join(file_name);
+            if candidate.exists().await {
+                return Some(candidate);
+            }
+        }
+        current = current.parent()?;
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Rustup {
     path: Path,
@@ -55,6 +292,25 @@ impl Rustup {
         }
     }
 
+    /// Returns rustup at the specified version, installing it if rustup is
+    /// not present or a different version is found
+    pub async fn get_or_install_version(version: &str) -> Result<Rustup, Error> {
+        if let Ok(rustup) = Self::get().await {
+            match rustup.version().await {
+                Ok(Some(installed)) if installed == version => return Ok(rustup),
+                Ok(installed) => info!(
+                    "Found rustup version {} but version {} was requested. Installing it now...",
+                    installed.as_deref().unwrap_or("unknown"),
+                    version
+                ),
+                Err(e) => debug!("Unable to determine version of existing rustup: {}", e),
+            }
+        } else {
+            info!("Unable to find rustup, Installing version {} now...", version);
+        }
+        Self::install_version(version).await
+    }
+
     pub async fn get() -> Result<Rustup, Error> {
         io::which("rustup", true)
             .await
@@ -63,46 +319,94 @@ impl Rustup {
     }
 
     pub async fn install() -> Result<Rustup, Error> {
-        let args = ["--default-toolchain", NO_DEFAULT_TOOLCHAIN_NAME, "-y"];
-        let platform = node::os::platform();
+        use node::os::Platform;
+
+        let platform = node::os::current_platform();
         info!("Getting rustup for platform: {:?}", platform);
-        match platform.as_str() {
-            "darwin" | "linux" => {
+        let installer = match platform {
+            Platform::Linux | Platform::MacOs => {
                 let rustup_script = tool_cache::download_tool("https://sh.rustup.rs")
                     .await
                     .map_err(Error::Js)?;
                 info!("Downloaded to: {:?}", rustup_script);
                 node::fs::chmod(&rustup_script, 0x755).await.map_err(Error::Js)?;
-                Command::from(&rustup_script)
-                    .args(args)
-                    .exec()
-                    .await
-                    .map_err(Error::Js)?;
+                rustup_script
             }
-            "windows" => {
+            Platform::Windows => {
                 let rustup_exe = tool_cache::download_tool("https://win.rustup.rs")
                     .await
                     .map_err(Error::Js)?;
                 info!("Downloaded to: {:?}", rustup_exe);
-                Command::from(&rustup_exe).args(args).exec().await.map_err(Error::Js)?;
+                rustup_exe
             }
-            _ => return Err(Error::UnsupportedPlatform(platform)),
-        }
+            Platform::Other(name) => return Err(Error::UnsupportedPlatform(name)),
+        };
+        Self::run_installer(&installer).await
+    }
+
+    async fn install_version(version: &str) -> Result<Rustup, Error> {
+        let url = rustup_init_url(version)?;
+        info!("Downloading rustup {} from {}", version, url);
+        let rustup_init = tool_cache::download_tool(url.as_str()).await.map_err(Error::Js)?;
+        node::fs::chmod(&rustup_init, 0o755).await.map_err(Error::Js)?;
+        Self::run_installer(&rustup_init).await
+    }
+
+    /// Runs a downloaded rustup installer without installing a default
+    /// toolchain, then adds the directory it installed into to the path
+    async fn run_installer(installer: &Path) -> Result<Rustup, Error> {
+        Command::from(installer)
+            .args(["--default-toolchain", NO_DEFAULT_TOOLCHAIN_NAME, "-y"])
+            .exec()
+            .await
+            .map_err(Error::Js)?;
         let cargo_bin_path = node::os::homedir().join(".cargo").join("bin");
         info!("Adding {:?} to path", cargo_bin_path);
         core::add_path(&cargo_bin_path);
         Self::get().await
     }
 
-    pub async fn update(&self) -> Result<(), Error> {
+    /// The version of this rustup, if it could be determined
+    pub async fn version(&self) -> Result<Option<String>, Error> {
+        let version: Arc<Mutex<Option<String>>> = Arc::default();
+        {
+            let version = Arc::clone(&version);
+            Command::from(&self.path)
+                .arg("--version")
+                .silent(true)
+                .outline(move |line| {
+                    let mut version = version.lock();
+                    if version.is_none() {
+                        *version = parse_version_line(line);
+                    }
+                })
+                .exec()
+                .await
+                .map_err(Error::Js)?;
+        }
+        let version = version.lock().take();
+        Ok(version)
+    }
+
+    pub async fn self_update(&self) -> Result<(), Error> {
         Command::from(&self.path)
-            .arg("update")
+            .args(["self", "update"])
             .exec()
             .await
             .map_err(Error::Js)?;
         Ok(())
     }
 
+    pub async fn update(&self, self_update: bool) -> Result<(), Error> {
+        let mut command = Command::from(&self.path);
+        command.arg("update");
+        if !self_update {
+            command.arg("--no-self-update");
+        }
+        command.exec().await.map_err(Error::Js)?;
+        Ok(())
+    }
+
     pub async fn install_toolchain(&self, config: &ToolchainConfig) -> Result<(), Error> {
         if config.name == NO_DEFAULT_TOOLCHAIN_NAME {
             return Ok(());
@@ -110,6 +414,9 @@ impl Rustup {
         let mut args: Vec<_> = ["toolchain", "install"].into_iter().map(String::from).collect();
         args.push(config.name.clone());
         args.extend(["--profile".into(), config.profile.clone()]);
+        if config.offline || config.rustup_version.is_some() {
+            args.push("--no-self-update".into());
+        }
         for target in &config.targets {
             args.extend(["-t".into(), target.clone()]);
         }
@@ -154,19 +461,195 @@ impl Rustup {
         Ok(toolchains)
     }
 
-    #[allow(dead_code)]
-    pub async fn install_component(&self, name: &str) -> Result<(), Error> {
+    pub async fn add_components(&self, toolchain: Option<&str>, components: &[String]) -> Result<(), Error> {
         Command::from(&self.path)
-            .arg("component")
-            .arg("add")
-            .arg(name)
+            .args(component_add_args(toolchain, components))
             .exec()
             .await
             .map_err(Error::Js)?;
         Ok(())
     }
 
+    /// Lists the installed components and targets of a toolchain (the active
+    /// one if `toolchain` is `None`), each prefixed with its kind. The list
+    /// is empty if the toolchain is not installed.
+    pub async fn list_installed(&self, toolchain: Option<&str>) -> Result<BTreeSet<String>, Error> {
+        let installed: Arc<Mutex<BTreeSet<String>>> = Arc::default();
+        for kind in ["component", "target"] {
+            let mut args = vec![kind, "list", "--installed"];
+            if let Some(toolchain) = toolchain {
+                args.extend(["--toolchain", toolchain]);
+            }
+            let installed = Arc::clone(&installed);
+            let result = Command::from(&self.path)
+                .args(args)
+                .silent(true)
+                .outline(move |line| {
+                    let line = line.trim();
+                    if !line.is_empty() {
+                        installed.lock().insert(format!("{} {}", kind, line));
+                    }
+                })
+                .exec()
+                .await;
+            if let Err(e) = result {
+                debug!("Unable to list installed {}s: {:?}", kind, e);
+                return Ok(BTreeSet::new());
+            }
+        }
+        let installed = std::mem::take(&mut *installed.lock());
+        Ok(installed)
+    }
+
     pub fn get_path(&self) -> &Path {
         &self.path
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        component_add_args, installed_delta, is_known_component, parse_toolchain_file, parse_version_line, Rustup,
+        ToolchainConfig,
+    };
+    use crate::{node, Error};
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn parse_toml_toolchain_file() {
+        let content = r#"
+            [toolchain]
+            channel = "nightly-2020-07-10"
+            components = [ "rustfmt", "rustc-dev" ]
+            targets = [ "wasm32-unknown-unknown", "thumbv2-none-eabi" ]
+            profile = "minimal"
+        "#;
+        let config = parse_toolchain_file(content, false).expect("Failed to parse toolchain file");
+        assert_eq!(config.name, "nightly-2020-07-10");
+        assert_eq!(config.profile, "minimal");
+        assert_eq!(config.components, ["rustfmt", "rustc-dev"]);
+        assert_eq!(config.targets, ["wasm32-unknown-unknown", "thumbv2-none-eabi"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn parse_minimal_toml_toolchain_file() {
+        let config =
+            parse_toolchain_file("[toolchain]\nchannel = \"1.70\"\n", true).expect("Failed to parse toolchain file");
+        assert_eq!(config.name, "1.70");
+        assert_eq!(config.profile, "default");
+        assert!(config.components.is_empty());
+        assert!(config.targets.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn parse_legacy_toolchain_file() {
+        let config = parse_toolchain_file("nightly\n", true).expect("Failed to parse legacy toolchain file");
+        assert_eq!(config.name, "nightly");
+        assert!(parse_toolchain_file("nightly\n", false).is_err());
+        assert!(parse_toolchain_file("[toolchain]\nprofile = \"minimal\"\n", true).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn validate_profiles() {
+        for profile in ["minimal", "default", "complete"] {
+            let config = ToolchainConfig {
+                profile: profile.into(),
+                ..ToolchainConfig::default()
+            };
+            assert!(config.validate().is_ok());
+        }
+        for profile in ["minimial", "", "Default"] {
+            let config = ToolchainConfig {
+                profile: profile.into(),
+                ..ToolchainConfig::default()
+            };
+            assert!(matches!(
+                config.validate(),
+                Err(Error::OptionParseError(name, value)) if name == "profile" && value == profile
+            ));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn recognise_components() {
+        for component in ["rustfmt", "clippy-preview", "llvm-tools-preview", "rust-src"] {
+            assert!(is_known_component(component));
+        }
+        assert!(!is_known_component("rustfnt"));
+    }
+
+    #[wasm_bindgen_test]
+    fn parse_rustup_version() {
+        assert_eq!(
+            parse_version_line("rustup 1.26.0 (5af9b9484 2023-04-05)").as_deref(),
+            Some("1.26.0")
+        );
+        assert_eq!(
+            parse_version_line("info: This is the version for the rustup toolchain manager"),
+            None
+        );
+        assert_eq!(parse_version_line(""), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn component_add_command() {
+        let components = vec![String::from("rust-src"), String::from("rust-analyzer")];
+        assert_eq!(
+            component_add_args(None, &components),
+            ["component", "add", "rust-src", "rust-analyzer"]
+        );
+        assert_eq!(
+            component_add_args(Some("nightly"), &components),
+            [
+                "component",
+                "add",
+                "--toolchain",
+                "nightly",
+                "rust-src",
+                "rust-analyzer"
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn installed_delta_lists_changes() {
+        use crate::delta::Action;
+        use std::collections::BTreeSet;
+
+        let set = |items: &[&str]| items.iter().copied().map(String::from).collect::<BTreeSet<_>>();
+        let before = set(&["component cargo", "component rustc", "target x86_64-unknown-linux-gnu"]);
+        let after = set(&["component cargo", "component rust-src", "target wasm32-unknown-unknown"]);
+        assert_eq!(
+            installed_delta(&before, &after),
+            [
+                ("component rust-src".to_string(), Action::Added),
+                ("component rustc".to_string(), Action::Removed),
+                ("target wasm32-unknown-unknown".to_string(), Action::Added),
+                ("target x86_64-unknown-linux-gnu".to_string(), Action::Removed),
+            ]
+        );
+        assert!(installed_delta(&after, &after).is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn version_is_read_from_binary() -> Result<(), JsValue> {
+        // The version keys the toolchain package caches, so it must come from
+        // the binary rather than the requested pin
+        if node::os::current_platform() == node::os::Platform::Windows {
+            return Ok(());
+        }
+        let root = node::os::temp_dir().join(&format!("ferrous-actions-rustup-test - {}", chrono::Local::now()));
+        node::fs::create_dir_all(&root).await?;
+        let path = root.join("rustup");
+        node::fs::write_file(&path, b"#!/bin/sh\necho 'rustup 1.27.1 (54dd3d00f 2024-04-24)'\n").await?;
+        node::fs::chmod(&path, 0o755).await?;
+        let version = Rustup { path }
+            .version()
+            .await
+            .map_err(|e| JsValue::from(e.to_string()))?;
+        assert_eq!(version.as_deref(), Some("1.27.1"));
+        crate::actions::io::rm_rf(&root).await?;
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::Error;
 use base64::engine::general_purpose::GeneralPurpose;
 use base64::Engine as _;
 
@@ -12,8 +13,43 @@ pub fn encode<I: AsRef<[u8]>>(input: I) -> String {
     engine.encode(input.as_ref())
 }
 
-pub fn decode<I: AsRef<[u8]>>(input: I) -> Result<Vec<u8>, base64::DecodeError> {
+/// Inverts `encode`, failing if the input is not validly encoded
+pub fn decode<I: AsRef<[u8]>>(input: I) -> Result<Vec<u8>, Error> {
     let engine = build_engine();
     let result = engine.decode(input.as_ref())?;
     Ok(result)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash as _, Hasher as _};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn pseudo_random_bytes(seed: usize) -> Vec<u8> {
+        let mut hasher = DefaultHasher::default();
+        seed.hash(&mut hasher);
+        let length = usize::try_from(hasher.finish() % 64).expect("Length out of range");
+        (0..length)
+            .map(|index| {
+                index.hash(&mut hasher);
+                hasher.finish().to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn decode_inverts_encode() {
+        for seed in 0..256 {
+            let bytes = pseudo_random_bytes(seed);
+            let encoded = encode(&bytes);
+            assert_eq!(decode(&encoded).expect("Failed to decode"), bytes);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn decode_rejects_malformed_input() {
+        assert!(decode("not*valid").is_err());
+    }
+}
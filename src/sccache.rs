@@ -0,0 +1,153 @@
+use crate::actions::exec::{Command, Stdio};
+use crate::actions::tool_cache::{self, DownloadTool, ExtractTar, StreamCompression};
+use crate::actions::{core, io};
+use crate::node::path::Path;
+use crate::{debug, info, node, Error};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+const SCCACHE_TOOL_NAME: &str = "sccache";
+const SCCACHE_VERSION: &str = "0.7.4";
+const RUSTC_WRAPPER_VAR: &str = "RUSTC_WRAPPER";
+
+fn release_target() -> Result<&'static str, Error> {
+    let target = match (node::os::arch().as_str(), node::os::platform().as_str()) {
+        ("arm64", "darwin") => "aarch64-apple-darwin",
+        ("arm64", "linux") => "aarch64-unknown-linux-musl",
+        ("x64", "darwin") => "x86_64-apple-darwin",
+        ("x64", "linux") => "x86_64-unknown-linux-musl",
+        ("x64", "win32") => "x86_64-pc-windows-msvc",
+        (arch, platform) => return Err(Error::UnsupportedPlatform(format!("{}-{}", platform, arch))),
+    };
+    Ok(target)
+}
+
+#[derive(Clone, Debug)]
+pub struct Sccache {
+    path: Path,
+}
+
+impl Sccache {
+    /// Locates sccache on the path or in the tool cache without installing it
+    pub async fn get() -> Result<Option<Sccache>, Error> {
+        match io::which(SCCACHE_TOOL_NAME, true).await {
+            Ok(path) => return Ok(Some(Sccache { path })),
+            Err(e) => debug!("Attempting to locate sccache returned this error: {:?}", e),
+        }
+        let Some(dir) = tool_cache::find(SCCACHE_TOOL_NAME, SCCACHE_VERSION, None)? else {
+            return Ok(None);
+        };
+        info!("Found sccache in tool cache at {}", dir);
+        Ok(Some(Self::from_dir(&dir)))
+    }
+
+    pub async fn get_or_install() -> Result<Sccache, Error> {
+        if let Some(sccache) = Self::get().await? {
+            return Ok(sccache);
+        }
+        info!("Unable to find sccache. Installing it now...");
+        let dir = Self::install().await?;
+        Ok(Self::from_dir(&dir))
+    }
+
+    fn from_dir(dir: &Path) -> Sccache {
+        Sccache {
+            path: dir.join(SCCACHE_TOOL_NAME).with_exe_suffix(),
+        }
+    }
+
+    async fn install() -> Result<Path, Error> {
+        let release_name = format!("sccache-v{}-{}", SCCACHE_VERSION, release_target()?);
+        let url = format!(
+            "https://github.com/mozilla/sccache/releases/download/v{}/{}.tar.gz",
+            SCCACHE_VERSION, release_name
+        );
+        info!("Downloading sccache from {}", url);
+        let tarball = DownloadTool::from(url.as_str())
+            .log_progress("sccache")
+            .download()
+            .await?;
+        // The release wraps everything in a directory named after the release
+        let extracted = ExtractTar::new(&tarball, StreamCompression::Gzip)
+            .strip_components(1)
+            .extract()
+            .await?;
+        let dir = tool_cache::cache_dir(SCCACHE_TOOL_NAME, SCCACHE_VERSION, &extracted, None).await?;
+        Ok(dir)
+    }
+
+    /// Adds sccache to the path and sets it as the compiler wrapper for this
+    /// and subsequent steps
+    pub fn export(&self) {
+        if let Some(parent) = self.path.parent() {
+            core::add_path(&parent);
+        }
+        core::export_variable(RUSTC_WRAPPER_VAR, SCCACHE_TOOL_NAME);
+    }
+
+    pub async fn get_stats(&self) -> Result<String, Error> {
+        let output: Arc<Mutex<String>> = Arc::default();
+        let output_captured = output.clone();
+        Command::from(&self.path)
+            .arg("--show-stats")
+            .outline(move |line| {
+                let mut output = output_captured.lock();
+                *output += line;
+                *output += "\n";
+            })
+            .stdout(Stdio::null())
+            .exec()
+            .await?;
+        let output = output.lock().trim_end().to_string();
+        Ok(output)
+    }
+
+    pub fn get_path(&self) -> Path {
+        self.path.clone()
+    }
+}
+
+/// Writes the statistics reported by sccache to the job summary
+pub async fn report_stats() -> Result<(), Error> {
+    // This runs in the post phase, which should never install anything
+    let Some(sccache) = Sccache::get().await? else {
+        info!("Unable to find sccache, so no statistics will be reported");
+        return Ok(());
+    };
+    let stats = sccache.get_stats().await?;
+    info!("{}", stats);
+    core::append_summary(format!("### sccache statistics\n\n```\n{}\n```", stats)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Sccache, RUSTC_WRAPPER_VAR, SCCACHE_TOOL_NAME};
+    use crate::node;
+    use crate::node::path::Path;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn export_sets_wrapper_and_path() {
+        let dir = node::os::temp_dir().join("ferrous-actions-sccache-test");
+        let sccache = Sccache {
+            path: dir.join(SCCACHE_TOOL_NAME),
+        };
+        let original = node::process::get_env();
+        sccache.export();
+        let env = node::process::get_env();
+        // Restore the environment before asserting so later tests never run
+        // through a wrapper which does not exist
+        for name in [RUSTC_WRAPPER_VAR, "PATH"] {
+            match original.get(name) {
+                Some(value) => node::process::set_var(name, value),
+                None => node::process::remove_var(name),
+            }
+        }
+        assert_eq!(env.get(RUSTC_WRAPPER_VAR).map(String::as_str), Some(SCCACHE_TOOL_NAME));
+        let path_var = env.get("PATH").expect("PATH should be set");
+        assert!(path_var
+            .split(node::path::delimiter().as_ref())
+            .any(|p| Path::from(p) == dir));
+    }
+}
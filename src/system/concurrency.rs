@@ -0,0 +1,69 @@
+use crate::input_manager::{self, Input};
+use crate::{node, Error};
+use parking_lot::Mutex;
+
+/// The largest number of concurrent operations used by default, however many
+/// CPUs are present
+const MAX_DEFAULT_LIMIT: usize = 8;
+
+static CONFIGURED_LIMIT: Mutex<Option<usize>> = parking_lot::const_mutex(None);
+
+fn clamp_limit(num_cpus: usize) -> usize {
+    num_cpus.clamp(1, MAX_DEFAULT_LIMIT)
+}
+
+/// The number of operations which may run concurrently when not overridden
+/// by the user, based on the number of CPUs
+pub fn default_limit() -> usize {
+    clamp_limit(node::os::num_cpus())
+}
+
+fn resolve_limit(value: Option<u64>) -> Result<usize, Error> {
+    match value.map(usize::try_from) {
+        None => Ok(default_limit()),
+        Some(Ok(limit)) if limit > 0 => Ok(limit),
+        Some(_) => {
+            let input_name: &str = Input::MaxConcurrency.into();
+            let value = value.map(|v| v.to_string()).unwrap_or_default();
+            Err(Error::OptionParseError(input_name.into(), value))
+        }
+    }
+}
+
+/// Sets the limit returned by `limit` from the `max-concurrency` input, or the
+/// default if it was not supplied
+pub fn configure(input_manager: &input_manager::Manager) -> Result<(), Error> {
+    let limit = resolve_limit(input_manager.get_u64(Input::MaxConcurrency)?)?;
+    *CONFIGURED_LIMIT.lock() = Some(limit);
+    Ok(())
+}
+
+/// The number of operations such as downloads and file system accesses which
+/// may run concurrently. This is the limit set by `configure`, or the default
+/// if it has not been called.
+pub fn limit() -> usize {
+    CONFIGURED_LIMIT.lock().unwrap_or_else(default_limit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{clamp_limit, default_limit, resolve_limit, MAX_DEFAULT_LIMIT};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn limit_clamped_and_overridden() {
+        assert_eq!(clamp_limit(0), 1);
+        assert_eq!(clamp_limit(2), 2);
+        assert_eq!(clamp_limit(MAX_DEFAULT_LIMIT * 4), MAX_DEFAULT_LIMIT);
+        assert!((1..=MAX_DEFAULT_LIMIT).contains(&default_limit()));
+
+        assert_eq!(resolve_limit(None).expect("Default limit failed"), default_limit());
+        let large = u64::try_from(MAX_DEFAULT_LIMIT * 4).expect("Limit too large");
+        assert_eq!(
+            resolve_limit(Some(large)).expect("Override failed"),
+            MAX_DEFAULT_LIMIT * 4
+        );
+        assert_eq!(resolve_limit(Some(1)).expect("Override failed"), 1);
+        assert!(resolve_limit(Some(0)).is_err());
+    }
+}
@@ -1 +1,6 @@
+#[cfg(feature = "action")]
+pub(crate) mod concurrency;
+pub(crate) mod rng;
+#[cfg(feature = "action")]
+pub(crate) mod semaphore;
 pub(crate) mod sleep;
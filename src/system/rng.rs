@@ -0,0 +1,45 @@
+/// Fills the supplied buffer with random bytes.
+///
+/// Bytes are sourced from node's `crypto` module (via `getrandom`) where it
+/// is available. Otherwise we fall back to `Math.random()`, which is not
+/// cryptographically strong but is sufficient for generating unique
+/// identifiers.
+pub fn fill_bytes(bytes: &mut [u8]) {
+    if getrandom::getrandom(bytes).is_err() {
+        fill_bytes_math_random(bytes);
+    }
+}
+
+fn fill_bytes_math_random(bytes: &mut [u8]) {
+    for byte in bytes {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let value = (js_sys::Math::random() * 256.0) as u8;
+        *byte = value;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn fill_bytes_fills_and_varies() {
+        const NUM_BYTES: usize = 32;
+        let mut first = [0u8; NUM_BYTES];
+        let mut second = [0u8; NUM_BYTES];
+        super::fill_bytes(&mut first);
+        super::fill_bytes(&mut second);
+        assert_ne!(first, [0u8; NUM_BYTES]);
+        assert_ne!(first, second);
+    }
+
+    #[wasm_bindgen_test]
+    fn math_random_fills_and_varies() {
+        const NUM_BYTES: usize = 32;
+        let mut first = [0u8; NUM_BYTES];
+        let mut second = [0u8; NUM_BYTES];
+        super::fill_bytes_math_random(&mut first);
+        super::fill_bytes_math_random(&mut second);
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,98 @@
+use futures::channel::oneshot;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Limits how many operations may be in progress at once. Since everything
+/// runs on the single node.js thread, this needs no synchronization beyond a
+/// `RefCell`.
+#[derive(Debug)]
+pub struct Semaphore {
+    state: RefCell<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    available: usize,
+    waiters: VecDeque<oneshot::Sender<()>>,
+}
+
+/// Returns its permit to the semaphore when dropped
+#[derive(Debug)]
+pub struct Permit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Constructs a semaphore with the specified number of permits, which is
+    /// raised to one if zero
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            state: RefCell::new(State {
+                available: permits.max(1),
+                waiters: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Waits until a permit is available. Permits are granted in the order
+    /// they were requested.
+    pub async fn acquire(&self) -> Permit<'_> {
+        let receiver = {
+            let mut state = self.state.borrow_mut();
+            if state.available > 0 {
+                state.available -= 1;
+                return Permit { semaphore: self };
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.waiters.push_back(sender);
+            receiver
+        };
+        // Senders are only dropped after sending, when their permit is handed over
+        receiver.await.expect("Semaphore permit was never granted");
+        Permit { semaphore: self }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.borrow_mut();
+        // Skip waiters which stopped waiting
+        while let Some(waiter) = state.waiters.pop_front() {
+            if waiter.send(()).is_ok() {
+                return;
+            }
+        }
+        state.available += 1;
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Semaphore;
+    use std::cell::Cell;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    async fn permits_bound_concurrency() {
+        use crate::system::sleep::sleep;
+        use std::time::Duration;
+
+        let semaphore = Semaphore::new(3);
+        let active = Cell::new(0usize);
+        let peak = Cell::new(0usize);
+        let tasks = (0..10).map(|_| async {
+            let _permit = semaphore.acquire().await;
+            active.set(active.get() + 1);
+            peak.set(peak.get().max(active.get()));
+            sleep(&Duration::from_millis(5)).await;
+            active.set(active.get() - 1);
+        });
+        futures::future::join_all(tasks).await;
+        assert_eq!(peak.get(), 3);
+        assert_eq!(active.get(), 0);
+    }
+}
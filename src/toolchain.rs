@@ -3,6 +3,7 @@ use crate::actions::cache::Entry as CacheEntry;
 use crate::node::path::Path;
 use crate::node::{self};
 use crate::rustup::ToolchainConfig;
+use crate::system::concurrency;
 use crate::{actions, info, Error};
 use async_recursion::async_recursion;
 use rustup_toolchain_manifest::manifest::Package as ManifestPackage;
@@ -10,8 +11,6 @@ use rustup_toolchain_manifest::Toolchain;
 use std::str::FromStr;
 use target_lexicon::Triple;
 
-const MAX_CONCURRENT_PACKAGE_INSTALLS: usize = 4;
-
 fn get_toolchain_home(toolchain: &Toolchain) -> Result<Path, Error> {
     let dir = get_action_share_dir()?.join("toolchains").join(&toolchain.to_string());
     Ok(dir)
@@ -27,17 +26,20 @@ fn get_package_decompress_path(package: &ManifestPackage) -> Result<Path, Error>
     Ok(dir)
 }
 
-fn compute_package_cache_key(package: &ManifestPackage) -> CacheEntry {
+fn compute_package_cache_key(package: &ManifestPackage, rustup_version: Option<&str>) -> CacheEntry {
     use crate::cache_key_builder::{Attribute, CacheKeyBuilder};
 
     let mut builder = CacheKeyBuilder::new(&package.name);
     builder.add_key_data(&package.unique_identifier());
     builder.set_attribute(Attribute::Target, package.supported_target.to_string());
     builder.set_attribute(Attribute::ToolchainVersion, package.version.clone());
+    if let Some(rustup_version) = rustup_version {
+        builder.set_key_attribute(Attribute::RustupVersion, rustup_version.into());
+    }
     builder.into_entry()
 }
 
-fn default_target_for_platform() -> Result<Triple, Error> {
+pub(crate) fn default_target_for_platform() -> Result<Triple, Error> {
     let target = Triple::from_str(match (node::os::arch().as_str(), node::os::platform().as_str()) {
         ("arm64", "linux") => "aarch64-unknown-linux-gnu",
         ("ia32", "linux") => "i686-unknown-linux-gnu",
@@ -81,23 +83,22 @@ async fn install_components(toolchain: &Toolchain, package: &ManifestPackage) ->
     let dir = node::fs::read_dir(&extract_path).await?;
     for entry in dir.filter(|d| d.file_type().is_dir()) {
         let components_path = entry.path().join("components");
-        let components: Vec<String> = node::fs::read_file(&components_path)
-            .await
-            .map(|data| String::from_utf8_lossy(&data[..]).into_owned())?
+        let components: Vec<String> = node::fs::read_file_string(&components_path)
+            .await?
             .lines()
             .map(String::from)
             .collect();
         for component in components {
             let component_path = entry.path().join(&component);
             let manifest_path = component_path.clone().join("manifest.in");
-            let manifest = node::fs::read_file(&manifest_path)
-                .await
-                .map(|data| String::from_utf8_lossy(&data[..]).into_owned())?;
+            let manifest = node::fs::read_file_string(&manifest_path).await?;
             let manifest = PackageManifest::from_str(manifest.as_str())?;
             for (entry_type, path) in manifest.iter() {
                 let source = component_path.join(path);
                 let dest = cargo_home.join(path);
-                node::fs::create_dir_all(&dest.parent()).await?;
+                if let Some(parent) = dest.parent() {
+                    node::fs::create_dir_all(&parent).await?;
+                }
 
                 match *entry_type {
                     EntryType::File => node::fs::rename(&source, &dest).await?,
@@ -115,12 +116,12 @@ async fn cleanup_decompressed_package(package: &ManifestPackage) -> Result<(), E
     Ok(())
 }
 
-async fn fetch_and_decompress_package(package: &ManifestPackage) -> Result<(), Error> {
+async fn fetch_and_decompress_package(package: &ManifestPackage, rustup_version: Option<&str>) -> Result<(), Error> {
     use actions::tool_cache::{self, StreamCompression};
     use rustup_toolchain_manifest::manifest::Compression;
 
     let extract_path = get_package_decompress_path(package)?;
-    let mut cache_entry = compute_package_cache_key(package);
+    let mut cache_entry = compute_package_cache_key(package, rustup_version);
     cache_entry.path(&extract_path);
     if let Some(key) = cache_entry.restore().await? {
         info!("Restored files from cache with key {}", key);
@@ -133,32 +134,26 @@ async fn fetch_and_decompress_package(package: &ManifestPackage) -> Result<(), E
             .1
             .clone();
         info!("Will need to download the following: {:#?}", remote_binary);
-        let tarball_path = tool_cache::download_tool(remote_binary.url.as_str())
+        let tarball_path = tool_cache::DownloadTool::from(remote_binary.url.as_str())
+            .log_progress(package.name.as_str())
+            .download()
             .await
             .map_err(Error::Js)?;
         info!("Downloaded tarball to {}", tarball_path);
         info!("Will extract to {}", extract_path);
         tool_cache::extract_tar(&tarball_path, StreamCompression::Gzip, Some(&extract_path)).await?;
         info!("Extracted to {}", extract_path);
-        let cache_id = cache_entry.save().await?;
+        let cache_id = cache_entry.save_id().await?;
         info!("Saved as {}", cache_id);
     }
     Ok(())
 }
 
-pub async fn install(toolchain_config: &ToolchainConfig) -> Result<(), Error> {
+async fn download_and_install(toolchain: &Toolchain, toolchain_config: &ToolchainConfig) -> Result<(), Error> {
     use actions::tool_cache;
     use futures::{StreamExt as _, TryStreamExt as _};
     use rustup_toolchain_manifest::{InstallSpec, Manifest};
 
-    let toolchain = {
-        let mut toolchain = Toolchain::from_str(&toolchain_config.name)?;
-        toolchain.host = Some(match toolchain.host {
-            Some(host) => host,
-            None => default_target_for_platform()?,
-        });
-        toolchain
-    };
     let manifest_url = toolchain.manifest_url();
     info!(
         "Will download manifest for toolchain {} from {}",
@@ -179,15 +174,44 @@ pub async fn install(toolchain_config: &ToolchainConfig) -> Result<(), Error> {
         targets: toolchain_config.targets.iter().cloned().collect(),
     };
     let downloads = manifest.find_downloads_for_install(&target, &install_spec)?;
+    let rustup_version = crate::rustup::installed_version().await;
     let process_packages = futures::stream::iter(downloads.iter())
         .map(|download| async {
-            fetch_and_decompress_package(download).await?;
-            install_components(&toolchain, download).await?;
+            fetch_and_decompress_package(download, rustup_version.as_deref()).await?;
+            install_components(toolchain, download).await?;
             cleanup_decompressed_package(download).await?;
             Ok::<_, Error>(())
         })
-        .buffer_unordered(MAX_CONCURRENT_PACKAGE_INSTALLS);
+        .buffer_unordered(concurrency::limit());
     process_packages.try_collect().await?;
+    Ok(())
+}
+
+pub async fn install(toolchain_config: &ToolchainConfig) -> Result<(), Error> {
+    let toolchain = {
+        let mut toolchain = Toolchain::from_str(&toolchain_config.name)?;
+        toolchain.host = Some(match toolchain.host {
+            Some(host) => host,
+            None => default_target_for_platform()?,
+        });
+        toolchain
+    };
+    if toolchain_config.offline {
+        // Without network access only a toolchain installed by an earlier step can be used
+        let rustc = get_toolchain_home(&toolchain)?
+            .join("bin")
+            .join("rustc")
+            .with_exe_suffix();
+        if !rustc.exists().await {
+            return Err(Error::OfflineToolchainMissing(toolchain.to_string()));
+        }
+        info!(
+            "Offline mode enabled, so using the already installed toolchain {}",
+            toolchain
+        );
+    } else {
+        download_and_install(&toolchain, toolchain_config).await?;
+    }
 
     if toolchain_config.set_default {
         let cargo_bin = get_toolchain_home(&toolchain)?.join("bin");
@@ -198,5 +222,7 @@ pub async fn install(toolchain_config: &ToolchainConfig) -> Result<(), Error> {
     if toolchain_config.set_override {
         return Err(Error::ToolchainInstallFunctionality("override".into()));
     }
+    // The toolchain was added to the front of the path above
+    crate::cargo::set_version_outputs(None).await?;
     Ok(())
 }